@@ -1,10 +1,21 @@
-from .data import Seq, AlnQualityReqs, Consensus
+from typing import Optional, Sequence
+
+from .data import Seq, AlnQualityReqs, Preset, AnalysisSettings, QcRules, Consensus, Sample
 from .data.stats import AlnData, AlnStats
 
 
 class Calculator:
     aln_quality_reqs: AlnQualityReqs
+    analysis_settings: AnalysisSettings
+    qc_rules: QcRules
 
-    def __init__(self, reqs: AlnQualityReqs): ...
+    def __init__(self, reqs: AlnQualityReqs, analysis_settings: AnalysisSettings = ...,
+                 qc_rules: QcRules = ...): ...
 
     def calculate(self, ref_path: str, aln_path: str) -> list[Consensus]: ...
+
+    def calculate_grouped(self, ref_path: str, aln_path: str,
+                          tag: Optional[str] = None) -> dict[str, list[Consensus]]: ...
+
+    def calculate_batch(self, ref_path: str, samples: Sequence[Sample],
+                        threads: int = 0) -> tuple[dict[str, list[Consensus]], dict[str, str]]: ...
@@ -1,6 +1,14 @@
-from typing import Sequence, Set, Mapping
+from enum import Enum
+from typing import Optional, Sequence, Set, Mapping, Union, overload
 
-from .stats import AlnData, AlnStats
+from .indel import Frameshift, InDel
+from .stats import AlnData, AlnStats, FamilyStats
+
+
+class Preset(Enum):
+    IlluminaAmplicon = ...
+    IlluminaShotgun = ...
+    Nanopore = ...
 
 
 class AlnQualityReqs:
@@ -10,16 +18,170 @@ class AlnQualityReqs:
     indel_cutoff: float
     save_ends: int
     min_observations: int
+    frameshift_cutoff: float
+    long_reads: bool
+    homopolymer_factor: float
+    min_aligned_length: Optional[int]
+    max_edit_fraction: Optional[float]
+    min_alignment_score: Optional[int]
+    max_softclip_fraction: Optional[float]
+    proper_pairs_only: bool
+    min_insert_size: Optional[int]
+    max_insert_size: Optional[int]
+
+    def __init__(self, min_mapq: int = 0, mandatory_flags: Union[int, str] = 0,
+                 prohibited_flags: Union[int, str] = 1540,
+                 indel_cutoff: float = 0.2, save_ends: int = 0, min_observations: Optional[int] = None,
+                 frameshift_cutoff: float = 0.5, long_reads: bool = False,
+                 homopolymer_factor: float = 0.1, min_aligned_length: Optional[int] = None,
+                 max_edit_fraction: Optional[float] = None, min_alignment_score: Optional[int] = None,
+                 max_softclip_fraction: Optional[float] = None, proper_pairs_only: bool = False,
+                 min_insert_size: Optional[int] = None, max_insert_size: Optional[int] = None): ...
+
+    @classmethod
+    def from_preset(cls, preset: Preset) -> AlnQualityReqs: ...
+
+    @classmethod
+    def from_toml(cls, path: str, preset: Optional[Preset] = None) -> AlnQualityReqs: ...
+
+
+class MateOverlap(Enum):
+    CountBoth = ...
+    PreferQuality = ...
+    DropDiscordant = ...
+
+
+class OutsideRegions(Enum):
+    Reference = ...
+    Mask = ...
+    Trim = ...
+
+
+class Region:
+    name: str
+    contig: str
+    start: int
+    end: int
+
+    def __init__(self, contig: str, start: int, end: int, name: Optional[str] = None): ...
+
+    @classmethod
+    def from_bed(cls, file: str) -> list["Region"]: ...
+
+    def contains(self, pos: int) -> bool: ...
+
+    def __len__(self) -> int: ...
+
+
+class RegionStats:
+    region: Region
+    mean_coverage: float
+    min_coverage: int
+    max_coverage: int
+    breadth: float
+
+
+class QcVerdict(Enum):
+    Pass = ...
+    Warn = ...
+    Fail = ...
+
+
+class QcRules:
+    depth_thresholds: list[int]
+    min_called_fraction: float
+    warn_called_fraction: float
+    min_mean_depth: float
+
+    def __init__(self, depth_thresholds: Sequence[int] = (1, 10), min_called_fraction: float = 0.5,
+                 warn_called_fraction: float = 0.9, min_mean_depth: float = 0.0): ...
+
+
+class QcStats:
+    breadth: list[tuple[int, float]]
+    mean_depth: float
+    median_depth: float
+    called_fraction: float
+    verdict: QcVerdict
+    reasons: list[str]
+
+
+class DiversityWindow:
+    start: int
+    end: int
+    mean_depth: float
+    mean_entropy: float
+    mean_minor_allele_frequency: float
+    mean_support: float
+
+
+class DiversityTrack:
+    depth: Sequence[int]
+    entropy: Sequence[float]
+    minor_allele_frequency: Sequence[float]
+    support: Sequence[float]
+
+    def windows(self, size: int) -> list[DiversityWindow]: ...
+
+    def __len__(self) -> int: ...
+
+
+class AnalysisSettings:
+    contig_aliases: Optional[str]
+    cds_annotation: Optional[str]
+    min_variant_frequency: float
+    min_variant_count: int
+    group_tag: Optional[str]
+    umi_tag: Optional[str]
+    min_family_size: int
+    mate_overlap: MateOverlap
+    regions: Optional[str]
+    outside_regions: OutsideRegions
+    extend_ends: bool
+    min_extension_reads: int
+
+    def __init__(self, contig_aliases: Optional[str] = None, cds_annotation: Optional[str] = None,
+                 min_variant_frequency: float = 0.03, min_variant_count: int = 5,
+                 group_tag: Optional[str] = None, umi_tag: Optional[str] = None, min_family_size: int = 1,
+                 mate_overlap: MateOverlap = MateOverlap.CountBoth, regions: Optional[str] = None,
+                 outside_regions: OutsideRegions = OutsideRegions.Reference, extend_ends: bool = False,
+                 min_extension_reads: int = 10): ...
+
+
+class Cds:
+    name: str
+    contig: str
+    segments: list[tuple[int, int]]
+    reverse: bool
+
+    def __init__(self, name: str, contig: str, segments: Sequence[tuple[int, int]], reverse: bool = False): ...
+
+    @classmethod
+    def from_file(cls, file: str) -> Sequence["Cds"]: ...
+
+    def contains(self, pos: int) -> bool: ...
 
-    def __init__(self, min_mapq: int, mandatory_flags: int, prohibited_flags: int,
-                 indel_cutoff: float, save_ends: int, min_observations: int): ...
+    def is_frameshifted_by(self, indel: InDel) -> bool: ...
+
+    def __len__(self) -> int: ...
+
+
+class Sample:
+    id: str
+    aln_path: str
+
+    def __init__(self, id: str, aln_path: str): ...
+
+    @classmethod
+    def from_sheet(cls, path: str) -> Sequence["Sample"]: ...
 
 
 class Seq:
     label: str
     sequence: str
+    qualities: Optional[list[int]]
 
-    def __init__(self, label: str, sequence: str): ...
+    def __init__(self, label: str, sequence: str, qualities: Optional[Sequence[int]] = None): ...
 
     @classmethod
     def from_fasta(cls, fasta: str) -> Sequence["Seq"]: ...
@@ -27,13 +189,113 @@ class Seq:
     @classmethod
     def from_file(cls, file: str) -> Sequence["Seq"]: ...
 
+    @classmethod
+    def from_indexed_file(cls, file: str, names: Set[str]) -> Sequence["Seq"]: ...
+
+    @classmethod
+    def from_fastq(cls, fastq: str) -> Sequence["Seq"]: ...
+
+    @classmethod
+    def from_fastq_file(cls, file: str) -> Sequence["Seq"]: ...
+
+    @staticmethod
+    def write_fastq(seqs: Sequence["Seq"], file: str) -> None: ...
+
     def to_fasta(self) -> str: ...
 
+    def to_fastq(self) -> str: ...
+
+    def reverse_complement(self) -> "Seq": ...
+
+    def subseq(self, start: int, end: int) -> "Seq": ...
+
+    def translate(self, table: int = 1) -> str: ...
+
+    def gc_content(self) -> float: ...
+
+    def n_count(self) -> int: ...
+
+    def ambiguity_count(self) -> int: ...
+
+    def upper(self) -> "Seq": ...
+
+    def lower(self) -> "Seq": ...
+
+    @overload
+    def __getitem__(self, index: int) -> str: ...
+
+    @overload
+    def __getitem__(self, index: slice) -> "Seq": ...
+
+    def __len__(self) -> int: ...
+
+    def __eq__(self, other: object) -> bool: ...
+
+    def __hash__(self) -> int: ...
+
+
+class MutationKind(Enum):
+    Substitution = ...
+    Insertion = ...
+    Deletion = ...
+    Frameshift = ...
+    PrematureStop = ...
+
+
+class AaMutation:
+    cds: str
+    kind: MutationKind
+    codon_position: int
+    ref_pos: int
+    ref_aa: str
+    alt_aa: str
+    ref_codons: str
+    alt_codons: str
+    notation: str
+
+
+class VariantKind(Enum):
+    Snv = ...
+    Insertion = ...
+    Deletion = ...
+
+
+class MinorVariant:
+    position: int
+    kind: VariantKind
+    ref_allele: str
+    consensus_allele: str
+    alt_allele: str
+    count: int
+    depth: int
+    frequency: float
+    ci_lower: float
+    ci_upper: float
+    forward_count: int
+    reverse_count: int
+
+
+class Substitution:
+    position: int
+    ref_base: str
+    consensus_base: str
+    count: int
+    depth: int
+    frequency: float
+
+
+class ReadAccounting:
+    accepted: int
+    rejected: Mapping[str, int]
+    total_rejected: int
+
 
 class AnalysisResult:
     coverage: Sequence[int]
-    valid_alns: Sequence[AlnData]
-    reads_seen: Set[str]
+    fragment_coverage: Sequence[int]
+    read_accounting: ReadAccounting
+    family_sizes: Sequence[int]
+    total_reads: int
 
 
 class Consensus:
@@ -41,8 +303,20 @@ class Consensus:
     aln_path: str
     consensus_seq: Seq
     aln_stats: AlnStats
+    family_stats: Optional[FamilyStats]
+    qc_stats: QcStats
+    region_stats: Sequence[RegionStats]
     coverage: Sequence[int]
+    fragment_coverage: Sequence[int]
     base_counts: Mapping[str, Sequence[int]]
+    diversity: DiversityTrack
+    indels: Sequence[InDel]
+    frameshifts: Sequence[Frameshift]
+    aa_mutations: Sequence[AaMutation]
+    minor_variants: Sequence[MinorVariant]
+    substitutions: Sequence[Substitution]
     total_reads: int
+    read_accounting: ReadAccounting
+    end_extensions: tuple[int, int]
     valid_reads: int
     invalid_reads: int
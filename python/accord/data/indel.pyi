@@ -24,3 +24,10 @@ class Deletion(InDel):
     stop: int
 
     def __init__(self, start: int, stop: int): ...
+
+
+class Frameshift:
+    indel: InDel
+    cds: str
+    count: int
+    support: float
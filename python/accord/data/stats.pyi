@@ -30,3 +30,10 @@ class AlnStats:
     editing_distance_distribution: DistStats
 
     def from_data(self, data: Sequence[AlnData], factors: Sequence[float], total_reads: int) -> "AlnStats": ...
+
+
+class FamilyStats:
+    families: int
+    discarded_families: int
+    singletons: int
+    size_distribution: DistStats
@@ -6,6 +6,11 @@ pub mod calculator;
 pub mod data;
 pub mod app;
 pub mod cli;
+pub mod flags;
+pub mod html;
+pub mod multiqc;
+pub mod report;
 pub mod settings;
+mod tracking;
 mod utils;
 mod types;
\ No newline at end of file
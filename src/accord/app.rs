@@ -1,43 +1,305 @@
 //! This module contains the `App` struct, which serves as entry point for the `accord` binary.
 
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use env_logger::Env;
+
 use super::calculator::Calculator;
-use super::cli::Args;
-use super::settings::AlnQualityReqs;
-use super::utils::write_file;
-use crate::accord::data::seq::Seq;
+use super::cli::{Args, TableFormat};
+use super::data::consensus::Consensus;
+use super::data::qc::QcVerdict;
+use super::data::sample::Sample;
+use super::data::seq::Seq;
+use super::html::HtmlReport;
+use super::multiqc::MultiQc;
+use super::report::Report;
+use super::types::BatchResults;
+use super::utils::{change_suffix, try_write_file, write_file};
+
+/// Exit code, if processing a sample failed.
+const ERROR_EXIT_CODE: i32 = 1;
+
+/// Exit code, if a consensus failed the quality control.
+const QC_FAIL_EXIT_CODE: i32 = 2;
 
 pub struct App;
 
 impl App {
     pub fn main() {
+        env_logger::Builder::from_env(Env::default().default_filter_or("warn")).init();
+
         let args = Args::parse_args();
-        let ref_seqs = Seq::from_file(&args.ref_path);
-        let aln_path = args.aln_path;
+        let samples = args.samples();
 
-        let calculator = Calculator::new(args.aln_reqs);
-        let consensuses = calculator.calculate(ref_seqs, aln_path);
+        let calculator = Calculator::new(args.aln_reqs.clone(), args.analysis_settings.clone(), args.qc_rules.clone());
 
-        let mut fastas = Vec::new();
-        let mut aln_stats = Vec::new();
-        for consensus in consensuses {
-            let fasta = consensus.get_consensus_seq().to_fasta();
-            fastas.push(fasta);
-            let stats = consensus.get_aln_stats().clone();
-            aln_stats.push(stats);
+        let aln_paths = samples.iter().map(|s| s.get_aln_path().clone()).collect::<Vec<String>>();
+        let ref_seqs = calculator.read_references(&args.ref_path, &aln_paths);
+
+        if !args.is_batch() {
+            let aln_path = samples[0].get_aln_path().clone();
+            let consensuses = calculator.calculate(ref_seqs, aln_path);
+            let qc_failures = Self::count_qc_failures(&consensuses);
+            if args.multiqc {
+                let mut multiqc = MultiQc::with_settings(args.aln_reqs.to_string());
+                multiqc.add_sample(samples[0].get_id(), &consensuses);
+                Self::output_multiqc(&multiqc, |name| Self::single_output_path(&args, name, name));
+            }
+            if args.html {
+                let mut html = HtmlReport::with_settings(args.aln_reqs.to_string());
+                html.add_sample(samples[0].get_id(), &consensuses);
+                write_file(&html.render(), &Self::single_output_path(&args, "html", "accord_report.html"));
+            }
+            Self::output_single(&args, samples[0].get_id(), consensuses);
+            if qc_failures > 0 {
+                eprintln!("{qc_failures} consensuses failed the quality control.");
+                process::exit(QC_FAIL_EXIT_CODE);
+            }
+            return;
         }
 
-        for i in 0..fastas.len() {
-            let fasta = fastas.get(i).unwrap();
-            let stats = aln_stats.get(i).unwrap();
+        let results = Self::calculate_outputs(&args, &calculator, &ref_seqs, &samples);
 
-            if args.out_path != "-" {
-                write_file(&fasta, args.out_path.as_str());
-            } else {
-                println!("{fasta}");
+        if let Err(e) = fs::create_dir_all(&args.out_dir) {
+            panic!("Failed to create output directory {}: {e}", args.out_dir);
+        }
+
+        let mut failed = 0;
+        let mut qc_failures = 0;
+        let mut multiqc = MultiQc::with_settings(args.aln_reqs.to_string());
+        let mut html = HtmlReport::with_settings(args.aln_reqs.to_string());
+        for (sample, result) in results {
+            match result {
+                Ok(outputs) => {
+                    for (id, consensuses) in outputs {
+                        qc_failures += Self::count_qc_failures(&consensuses);
+                        if args.multiqc {
+                            multiqc.add_sample(&id, &consensuses);
+                        }
+                        if args.html {
+                            html.add_sample(&id, &consensuses);
+                        }
+                        if let Err(e) = Self::output_sample(&args, &id, &consensuses) {
+                            eprintln!("Failed to write outputs of {id}: {e}");
+                            failed += 1;
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to process sample {}: {e}", sample.get_id());
+                    failed += 1;
+                }
+            }
+        }
+
+        if args.multiqc {
+            let out_dir = Path::new(&args.out_dir);
+            Self::output_multiqc(&multiqc, |name| out_dir.join(name).to_string_lossy().to_string());
+        }
+        if args.html {
+            let html_path = Path::new(&args.out_dir).join("accord_report.html");
+            write_file(&html.render(), &html_path.to_string_lossy());
+        }
+
+        if failed > 0 {
+            eprintln!("{failed} of {} samples failed.", samples.len());
+            process::exit(ERROR_EXIT_CODE);
+        }
+        if qc_failures > 0 {
+            eprintln!("{qc_failures} consensuses failed the quality control.");
+            process::exit(QC_FAIL_EXIT_CODE);
+        }
+    }
+
+    /// Write the MultiQC custom content files, at the paths derived from their names by `path_for`.
+    fn output_multiqc<F: Fn(&str) -> String>(multiqc: &MultiQc, path_for: F) {
+        write_file(&multiqc.general_stats_to_json(), &path_for("accord_general_stats_mqc.json"));
+        write_file(&multiqc.coverage_to_json(), &path_for("accord_coverage_mqc.json"));
+        for (name, content) in multiqc.distributions_to_json() {
+            write_file(&content, &path_for(&name));
+        }
+    }
+
+    /// Get the path of a file accompanying the consensus of a single sample: next to the out path with `suffix`,
+    /// or if the consensus is written to stdout, as `file_name` into the output directory.
+    fn single_output_path(args: &Args, suffix: &str, file_name: &str) -> String {
+        if args.out_path != "-" {
+            return change_suffix(&args.out_path, suffix);
+        }
+        if let Err(e) = fs::create_dir_all(&args.out_dir) {
+            panic!("Failed to create output directory {}: {e}", args.out_dir);
+        }
+        Path::new(&args.out_dir).join(file_name).to_string_lossy().to_string()
+    }
+
+    /// Count the consensuses with a `FAIL` verdict.
+    fn count_qc_failures(consensuses: &[Consensus]) -> usize {
+        consensuses
+            .iter()
+            .filter(|consensus| consensus.get_qc_stats().get_verdict() == QcVerdict::Fail)
+            .count()
+    }
+
+    /// Calculate the consensuses of all samples, paired with the IDs used for naming their output files.
+    /// If reads are grouped by tag, every group is named by its tag value. With multiple samples,
+    /// the group names are prefixed with the sample ID.
+    fn calculate_outputs(
+        args: &Args,
+        calculator: &Calculator,
+        ref_seqs: &[Seq],
+        samples: &[Sample],
+    ) -> BatchResults<Vec<(String, Vec<Consensus>)>> {
+        let tag = match &args.analysis_settings.group_tag {
+            Some(tag) => tag,
+            None => {
+                let results = calculator.calculate_batch(ref_seqs, samples, args.threads);
+                return results
+                    .into_iter()
+                    .map(|(sample, result)| {
+                        let id = sample.get_id().clone();
+                        (sample, result.map(|consensuses| vec![(id, consensuses)]))
+                    })
+                    .collect();
             }
+        };
+
+        let qualify = samples.len() > 1;
+        let results = calculator.calculate_batch_grouped(ref_seqs, samples, args.threads, tag);
+        results
+            .into_iter()
+            .map(|(sample, result)| {
+                let outputs = result.map(|groups| {
+                    groups
+                        .into_iter()
+                        .map(|(group, consensuses)| (sample.group_id(&group, qualify), consensuses))
+                        .collect()
+                });
+                (sample, outputs)
+            })
+            .collect()
+    }
 
+    /// Write consensuses of a single sample to the out path or stdout, and print alignment statistics.
+    /// Region statistics, and if requested minor variants and diversity tracks, are written next to the out path.
+    /// Amino acid mutations are written next to the out path, or printed after the statistics.
+    fn output_single(args: &Args, id: &str, consensuses: Vec<Consensus>) {
+        let fasta = Self::to_multi_fasta(&consensuses);
+        if args.out_path != "-" {
+            write_file(&fasta, args.out_path.as_str());
+        } else {
+            println!("{fasta}");
+        }
+
+        println!();
+        println!("Settings: {}", args.aln_reqs);
+        for consensus in &consensuses {
+            let stats = consensus.get_aln_stats();
             println!();
             println!("{stats:?}");
+            let accounting = consensus.get_read_accounting();
+            let reasons = accounting.get_rejected().iter().map(|(reason, count)| format!("{reason}: {count}")).collect::<Vec<String>>();
+            println!("Reads: {} accepted, {} rejected ({})", accounting.get_accepted(), accounting.total_rejected(), reasons.join(", "));
+            if let Some(family_stats) = consensus.get_family_stats() {
+                println!("{family_stats:?}");
+            }
+            let qc_stats = consensus.get_qc_stats();
+            match qc_stats.get_reasons().is_empty() {
+                true => println!("QC {}", qc_stats.get_verdict()),
+                false => println!("QC {}: {}", qc_stats.get_verdict(), qc_stats.get_reasons().join(", ")),
+            }
+            if args.analysis_settings.extend_ends {
+                let (start_extension, end_extension) = consensus.get_end_extensions();
+                println!("Extended consensus by {start_extension} bases at the start and {end_extension} bases at the end.");
+            }
         }
+
+        if args.out_path != "-" {
+            let report = Report::new(id, &consensuses).with_aln_reqs(&args.aln_reqs);
+            if args.minor_variants {
+                write_file(&report.minor_variants_to_tsv(), &change_suffix(&args.out_path, "minor_variants.tsv"));
+                write_file(&report.minor_variants_to_vcf(), &change_suffix(&args.out_path, "minor_variants.vcf"));
+            }
+            if args.analysis_settings.regions.is_some() {
+                write_file(&report.regions_to_tsv(), &change_suffix(&args.out_path, "regions.tsv"));
+            }
+            if args.diversity {
+                let windows = report.diversity_windows_to_tsv(args.diversity_window);
+                write_file(&report.diversity_to_tsv(), &change_suffix(&args.out_path, "diversity.tsv"));
+                write_file(&windows, &change_suffix(&args.out_path, "diversity_windows.tsv"));
+            }
+        }
+
+        if args.analysis_settings.cds_annotation.is_some() {
+            let aa_mutations = Self::render_aa_mutations(id, &consensuses, args.aa_mutations_format);
+            if args.out_path != "-" {
+                let suffix = format!("aa_mutations.{}", args.aa_mutations_format.extension());
+                write_file(&aa_mutations, &change_suffix(&args.out_path, &suffix));
+            } else {
+                println!();
+                println!("{aa_mutations}");
+            }
+        }
+    }
+
+    /// Write the consensus FASTA, report, region statistics, and if requested minor variants and diversity of a sample
+    /// into the output directory, named by sample ID. If coding sequences are annotated, the amino acid mutations are written as well.
+    /// Errors, e.g. because an output file already exists, are returned, so other samples are still written.
+    fn output_sample(args: &Args, id: &String, consensuses: &[Consensus]) -> Result<(), String> {
+        let out_dir = Path::new(&args.out_dir);
+
+        let fasta = Self::to_multi_fasta(consensuses);
+        let fasta_path = out_dir.join(format!("{id}.fasta"));
+        try_write_file(&fasta, &fasta_path.to_string_lossy())?;
+
+        let report = Report::new(id, consensuses).with_aln_reqs(&args.aln_reqs);
+        let report_path = out_dir.join(format!("{id}.report.tsv"));
+        try_write_file(&report.to_tsv(), &report_path.to_string_lossy())?;
+
+        if args.minor_variants {
+            let variants_path = out_dir.join(format!("{id}.minor_variants.tsv"));
+            try_write_file(&report.minor_variants_to_tsv(), &variants_path.to_string_lossy())?;
+            let vcf_path = out_dir.join(format!("{id}.minor_variants.vcf"));
+            try_write_file(&report.minor_variants_to_vcf(), &vcf_path.to_string_lossy())?;
+        }
+
+        if args.analysis_settings.regions.is_some() {
+            let regions_path = out_dir.join(format!("{id}.regions.tsv"));
+            try_write_file(&report.regions_to_tsv(), &regions_path.to_string_lossy())?;
+        }
+
+        if args.diversity {
+            let diversity_path = out_dir.join(format!("{id}.diversity.tsv"));
+            try_write_file(&report.diversity_to_tsv(), &diversity_path.to_string_lossy())?;
+            let windows = report.diversity_windows_to_tsv(args.diversity_window);
+            let windows_path = out_dir.join(format!("{id}.diversity_windows.tsv"));
+            try_write_file(&windows, &windows_path.to_string_lossy())?;
+        }
+
+        if args.analysis_settings.cds_annotation.is_some() {
+            let format = args.aa_mutations_format;
+            let aa_mutations = Self::render_aa_mutations(id, consensuses, format);
+            let aa_mutations_path = out_dir.join(format!("{id}.aa_mutations.{}", format.extension()));
+            try_write_file(&aa_mutations, &aa_mutations_path.to_string_lossy())?;
+        }
+
+        Ok(())
+    }
+
+    fn render_aa_mutations(id: &str, consensuses: &[Consensus], format: TableFormat) -> String {
+        let report = Report::new(id, consensuses);
+        match format {
+            TableFormat::Tsv => report.aa_mutations_to_tsv(),
+            TableFormat::Json => report.aa_mutations_to_json(),
+        }
+    }
+
+    fn to_multi_fasta(consensuses: &[Consensus]) -> String {
+        consensuses
+            .iter()
+            .map(|consensus| consensus.get_consensus_seq().to_fasta())
+            .collect::<Vec<String>>()
+            .join("")
     }
 }
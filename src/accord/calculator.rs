@@ -2,28 +2,53 @@
 
 use bam::index::{build, Type};
 use bam::pileup::Indel;
-use bam::{IndexedReader, Read};
+use bam::record::Aux;
+use bam::{HeaderView, IndexedReader, Read, Record};
 use counter::Counter;
 use itertools::Itertools;
-use log::{debug, info, warn};
-use pyo3::{pyclass, pymethods};
+use log::{debug, error, info, warn};
+use pyo3::{pyclass, pymethods, Python};
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
 use rust_htslib::bam;
 use rust_htslib::bam::pileup::Alignment;
+use std::any::Any;
 use std::cmp::Ordering;
-use std::collections::{HashSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::hash::Hash;
 use std::iter::Iterator;
+use std::panic::{self, AssertUnwindSafe};
 
 use super::data;
-use super::settings::AlnQualityReqs;
-use super::types::{BaseCounts, Coverage, InDelCounts};
+use super::settings::{AlnQualityReqs, AnalysisSettings, MateOverlap, OutsideRegions, QcRules};
+use super::tracking::{ReadDecisions, ReadTally, SeenKeys};
+use super::types::{BaseCounts, BatchResults, Coverage, GroupedConsensuses, InDelCounts};
 use crate::accord::utils::change_suffix;
+use data::annotation::Cds;
 use data::consensus::{AnalysisResult, Consensus};
-use data::indel::{Deletion, InDel, Insertion};
+use data::diversity::DiversityTrack;
+use data::indel::{Deletion, Frameshift, InDel, Insertion};
+use data::mutation::AaMutation;
+use data::qc::QcStats;
+use data::region::{Region, RegionStats};
+use data::sample::Sample;
+use data::seq::genetic_code::GeneticCode;
 use data::seq::Seq;
-use data::stats::{AlnData, AlnStats};
+use data::stats::{AlnData, AlnStats, FamilyStats};
+use data::variant::{MinorVariant, Substitution};
 
 const THREADS: u32 = 1;
 
+/// Quantiles reported in the statistics of distributions.
+const QUANTILE_FACTORS: [f64; 13] = [0.0, 0.1, 0.2, 0.25, 0.3, 0.4, 0.5, 0.6, 0.7, 0.75, 0.8, 0.9, 1.0];
+
+/// Key identifying a UMI family: the UMI, mapping position, mapping position of the mate and strand of its reads.
+type FamilyKey = (String, i64, i64, bool);
+
+/// Name of the single group of reads, when reads are not grouped by tag.
+const UNGROUPED: &str = "";
+
 /// A consensus calculator.
 #[derive(Debug)]
 #[pyclass]
@@ -32,13 +57,50 @@ pub struct Calculator {
     /// These determine which reads are considered in the consensus calculation.
     #[pyo3(get)]
     aln_quality_reqs: AlnQualityReqs,
+
+    /// Settings for analysing the aligned reads.
+    #[pyo3(get)]
+    analysis_settings: AnalysisSettings,
+
+    /// Rules for the quality control of the consensuses.
+    #[pyo3(get)]
+    qc_rules: QcRules,
+
+    /// Mapping from reference labels to contig names in the alignment files.
+    contig_aliases: HashMap<String, String>,
+
+    /// Annotated coding sequences, used for judging frameshifting indels.
+    cds_features: Vec<Cds>,
+
+    /// Regions the consensus calculation is restricted to. Empty, if the whole references are analysed.
+    regions: Vec<Region>,
 }
 
 #[pymethods]
 impl Calculator {
     #[new]
-    pub fn new(aln_quality_reqs: AlnQualityReqs) -> Self {
-        Self { aln_quality_reqs }
+    #[pyo3(signature = (aln_quality_reqs, analysis_settings = AnalysisSettings::default(), qc_rules = QcRules::default()))]
+    pub fn new(aln_quality_reqs: AlnQualityReqs, analysis_settings: AnalysisSettings, qc_rules: QcRules) -> Self {
+        let tags = [&analysis_settings.group_tag, &analysis_settings.umi_tag];
+        for tag in tags.into_iter().flatten() {
+            if tag.len() != 2 || !tag.is_ascii() {
+                panic!("Invalid SAM tag: '{tag}'. Tags consist of two characters, e.g. 'RG'.");
+            }
+        }
+        let contig_aliases = match &analysis_settings.contig_aliases {
+            Some(path) => Self::read_contig_aliases(path),
+            None => HashMap::new(),
+        };
+        let cds_features = match &analysis_settings.cds_annotation {
+            Some(path) => Cds::from_file(path),
+            None => Vec::new(),
+        };
+        let regions = match &analysis_settings.regions {
+            Some(spec) => Region::from_spec(spec),
+            None => Vec::new(),
+        };
+
+        Self { aln_quality_reqs, analysis_settings, qc_rules, contig_aliases, cds_features, regions }
     }
 
     #[pyo3(name = "calculate")]
@@ -49,9 +111,69 @@ impl Calculator {
         //! - `aln_path: String`: Path to a sorted BAM-file with aligned reads.
         //!
         //! Returns a `Consensus` struct.
-        let ref_seqs = Seq::from_file(&ref_path);
+        let ref_seqs = self.read_references(&ref_path, std::slice::from_ref(&aln_path));
         self.calculate(ref_seqs, aln_path)
     }
+
+    #[pyo3(name = "calculate_grouped", signature = (ref_path, aln_path, tag = None))]
+    pub fn py_calculate_grouped(
+        &self,
+        ref_path: String,
+        aln_path: String,
+        tag: Option<String>,
+    ) -> GroupedConsensuses {
+        //! Calculate consensuses per group of reads, for the passed reference and aligned reads.
+        //!
+        //! - `ref_path: String`: Path to the reference against which the reads were aligned.
+        //! - `aln_path: String`: Path to a sorted BAM-file with aligned reads.
+        //! - `tag: str | None`: SAM tag by which reads are grouped. Defaults to the `group_tag` of the analysis settings.
+        //!
+        //! Returns a dict mapping the tag values to the consensuses of the respective group.
+        let tag = match tag.or_else(|| self.analysis_settings.group_tag.clone()) {
+            Some(tag) => tag,
+            None => panic!("No SAM tag for grouping reads was passed, and none is set in the analysis settings."),
+        };
+        let ref_seqs = self.read_references(&ref_path, std::slice::from_ref(&aln_path));
+        self.calculate_grouped(ref_seqs, aln_path, &tag)
+    }
+
+    #[pyo3(name = "calculate_batch", signature = (ref_path, samples, threads = 0))]
+    pub fn py_calculate_batch(
+        &self,
+        py: Python<'_>,
+        ref_path: String,
+        samples: Vec<Sample>,
+        threads: usize,
+    ) -> (HashMap<String, Vec<Consensus>>, HashMap<String, String>) {
+        //! Calculate consensuses for multiple samples, that were aligned against the same reference.
+        //!
+        //! - `ref_path: String`: Path to the reference against which the reads were aligned.
+        //! - `samples: list[Sample]`: The samples to process.
+        //! - `threads: int`: Number of samples processed in parallel. Zero means one per CPU.
+        //!
+        //! Returns a tuple of two dicts, keyed by sample ID.
+        //! The first maps successfully processed samples to their consensuses,
+        //! the second maps failed samples to an error message.
+        let aln_paths = samples.iter().map(|s| s.get_aln_path().clone()).collect::<Vec<String>>();
+        let ref_seqs = self.read_references(&ref_path, &aln_paths);
+        let results = py.allow_threads(|| self.calculate_batch(&ref_seqs, &samples, threads));
+
+        let mut consensuses = HashMap::new();
+        let mut failures = HashMap::new();
+        for (sample, result) in results {
+            let id = sample.get_id().clone();
+            match result {
+                Ok(sample_consensuses) => {
+                    consensuses.insert(id, sample_consensuses);
+                }
+                Err(e) => {
+                    failures.insert(id, e);
+                }
+            }
+        }
+
+        (consensuses, failures)
+    }
 }
 
 impl Calculator {
@@ -64,19 +186,375 @@ impl Calculator {
         //! Returns a `Consensus` struct.
         info!("Calculating consensus for {aln_path}");
 
-        let mut aln_reader = Self::read_with_index(&aln_path);
-        let mut consensus_vec = Vec::new();
+        let mut groups = self.calculate_groups(ref_seqs, &aln_path, None);
+        groups.remove(UNGROUPED).unwrap_or_default()
+    }
+
+    pub fn calculate_grouped(&self, ref_seqs: Vec<Seq>, aln_path: String, tag: &str) -> GroupedConsensuses {
+        //! Calculate consensuses per group of reads, for the passed reference and aligned reads.
+        //! Reads are grouped by the value of a SAM tag, in a single pass over the alignment file.
+        //!
+        //! - `ref_seqs: Vec<Seq>`: The references against which the reads were aligned.
+        //! - `aln_path: String`: Path to a sorted BAM-file with aligned reads.
+        //! - `tag: &str`: Two-letter SAM tag, e.g. `RG`, `CB` or `BC`.
+        //!
+        //! Returns the consensuses of every group, keyed by tag value.
+        //! When grouping by `RG`, read groups declared in the header are included, even if they have no reads.
+        info!("Calculating consensuses for {aln_path}, grouped by tag {tag}");
+
+        self.calculate_groups(ref_seqs, &aln_path, Some(tag))
+    }
+
+    /// Calculate consensuses per group of reads. Without a tag, all reads belong to the same group.
+    fn calculate_groups(&self, ref_seqs: Vec<Seq>, aln_path: &String, tag: Option<&str>) -> GroupedConsensuses {
+        let mut aln_reader = Self::read_with_index(aln_path);
+        let header = aln_reader.header().clone();
+
+        // groups known beforehand get a consensus, even if they have no reads
+        let mut groups = match tag {
+            None => BTreeSet::from([String::from(UNGROUPED)]),
+            Some("RG") => Self::read_group_ids(&header),
+            Some(_) => BTreeSet::new(),
+        };
+
+        let mut contig_results = Vec::new();
+        let mut used_tids = HashSet::new();
         for ref_seq in ref_seqs {
-            let results = self.analyse_alignments(&ref_seq, &mut aln_reader);
+            let label = ref_seq.get_label();
+            let tid = match self.resolve_contig(&ref_seq, &header) {
+                Ok(Some(tid)) => tid,
+                Ok(None) => {
+                    warn!("Skipping reference '{label}', because {aln_path} has no matching contig.");
+                    continue;
+                }
+                Err(e) => panic!("{e} in {aln_path}."),
+            };
+
+            let contig_name = String::from_utf8_lossy(header.tid2name(tid)).to_string();
+            used_tids.insert(tid);
+
+            // without regions, the whole reference is analysed
+            let regions = self.regions_for(&ref_seq, &contig_name);
+            let intervals = if self.regions.is_empty() {
+                vec![(0, ref_seq.len())]
+            } else if regions.is_empty() {
+                info!("Skipping reference '{label}', because it overlaps none of the regions.");
+                continue;
+            } else {
+                Region::merge_intervals(&regions)
+            };
+
+            let results = self.analyse_alignments(&ref_seq, tid, &mut aln_reader, tag, &intervals);
+            groups.extend(results.keys().cloned());
+            contig_results.push((ref_seq, contig_name, regions, results));
+        }
+
+        for tid in 0..header.target_count() {
+            if !used_tids.contains(&tid) {
+                let contig_name = String::from_utf8_lossy(header.tid2name(tid));
+                warn!("Contig '{contig_name}' in {aln_path} has no corresponding reference sequence.");
+            }
+        }
+
+        let mut consensuses = BTreeMap::new();
+        for group in groups {
+            let group_consensuses = contig_results
+                .iter_mut()
+                .map(|(ref_seq, contig_name, regions, results)| {
+                    let result = results.remove(&group).unwrap_or_else(|| AnalysisResult::empty(ref_seq.len()));
+                    self.build_consensus(ref_seq.clone(), contig_name, regions, aln_path, result)
+                })
+                .collect();
+            consensuses.insert(group, group_consensuses);
+        }
+
+        consensuses
+    }
+
+    /// Compute the consensus and all derived results from the analysed reads of a reference sequence.
+    /// If the calculation is restricted to regions, the consensus is handled outside of them as configured.
+    fn build_consensus(
+        &self,
+        ref_seq: Seq,
+        contig_name: &str,
+        regions: &[Region],
+        aln_path: &str,
+        results: AnalysisResult,
+    ) -> Consensus {
+        let cds_features = self.cds_features_for(&ref_seq, contig_name);
+
+        // calculations
+        let (consensus_seq, indels) = self.compute_consensus(&ref_seq, &results, &cds_features);
+        let frameshifts = self.find_frameshifts(&ref_seq, &indels, &results, &cds_features);
+        let aa_mutations = Self::find_aa_mutations(&ref_seq, &consensus_seq, &indels, &cds_features);
+        let minor_variants = self.find_minor_variants(&ref_seq, &indels, &results);
+        let substitutions = self.find_substitutions(&ref_seq, &results);
+        let aln_stats = self.compute_aln_stats(&results);
+        let family_stats = self.compute_family_stats(&results);
+        let qc_stats = self.compute_qc_stats(&ref_seq, regions, &results);
+        let region_stats = self.compute_region_stats(regions, &results);
+        let diversity = self.compute_diversity(&ref_seq, &results);
+        let (consensus_seq, end_extensions) = self.extend_ends(consensus_seq, &results);
+        let consensus_seq = self.handle_outside_regions(consensus_seq, ref_seq.len(), &indels, regions, end_extensions);
+
+        Consensus::new(
+            ref_seq, aln_path.to_string(), consensus_seq, aln_stats, family_stats, qc_stats, region_stats, diversity, indels, frameshifts,
+            aa_mutations, minor_variants, substitutions, end_extensions, results,
+        )
+    }
+
+    /// Get the IDs of the read groups declared in the `@RG` lines of an alignment file header.
+    fn read_group_ids(header: &HeaderView) -> BTreeSet<String> {
+        let text = String::from_utf8_lossy(header.as_bytes());
+        text.lines()
+            .filter(|line| line.starts_with("@RG\t"))
+            .filter_map(|line| line.split('\t').find_map(|field| field.strip_prefix("ID:")))
+            .map(String::from)
+            .collect()
+    }
+
+    /// Get the value of a tag of a read as string. Returns `None` for reads without the tag,
+    /// or if the tag holds neither a string, a character nor an integer.
+    fn tag_value(record: &Record, tag: &str) -> Option<String> {
+        match record.aux(tag.as_bytes()) {
+            Ok(Aux::String(value)) => Some(value.to_string()),
+            Ok(Aux::Char(value)) => Some((value as char).to_string()),
+            Ok(Aux::I8(value)) => Some(value.to_string()),
+            Ok(Aux::U8(value)) => Some(value.to_string()),
+            Ok(Aux::I16(value)) => Some(value.to_string()),
+            Ok(Aux::U16(value)) => Some(value.to_string()),
+            Ok(Aux::I32(value)) => Some(value.to_string()),
+            Ok(Aux::U32(value)) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Get the key identifying the UMI family of a read, i.e. its UMI, mapping position, the mapping position
+    /// of its mate, and its strand. Returns `None` for reads without a UMI.
+    fn family_key(record: &Record, umi_tag: &str) -> Option<FamilyKey> {
+        let umi = Self::tag_value(record, umi_tag)?;
+        Some((umi, record.pos(), record.mpos(), record.is_reverse()))
+    }
+
+    pub fn read_references(&self, ref_path: &String, aln_paths: &[String]) -> Vec<Seq> {
+        //! Read the reference sequences, against which the reads in the alignment files were aligned.
+        //!
+        //! - `ref_path: &String`: Path to a FASTA file, which may be gzip or bgzip compressed.
+        //! - `aln_paths: &[String]`: Paths to alignment files.
+        //!
+        //! If the FASTA file has a `.fai` index, only the sequences for contigs present in the
+        //! headers of the alignment files are read. Otherwise, all sequences are read.
+        if !Seq::has_index(ref_path) {
+            return Seq::from_file(ref_path);
+        }
+
+        // collect contig names from all alignment files
+        let mut names = HashSet::new();
+        for aln_path in aln_paths {
+            let reader = match bam::Reader::from_path(aln_path) {
+                Ok(reader) => reader,
+                Err(e) => {
+                    warn!("Unable to read header of {aln_path}: {e}");
+                    continue;
+                }
+            };
+            for name in reader.header().target_names() {
+                names.insert(String::from_utf8_lossy(name).to_string());
+            }
+        }
+
+        // reference labels that are aliased to one of the contigs are needed as well
+        for (label, contig_name) in &self.contig_aliases {
+            if names.contains(contig_name) {
+                let first_part = label.split_whitespace().next().unwrap_or(label);
+                names.insert(first_part.to_string());
+            }
+        }
+
+        Seq::from_indexed_file(ref_path, &names)
+    }
+
+    /// Determine the ID of the contig in the alignment file, that corresponds to a reference sequence.
+    ///
+    /// An alias for the full label or its first word takes precedence. References read via a FASTA index
+    /// are labelled by the first word only, so an alias for a full label starting with it applies as well.
+    /// Otherwise, the full label, and then its first word are looked up in the alignment file header.
+    /// Returns an error naming the contig, if its length differs from the length of the reference sequence.
+    fn resolve_contig(&self, ref_seq: &Seq, header: &HeaderView) -> Result<Option<u32>, String> {
+        let label = ref_seq.get_label().as_str();
+        let first_part = label.split_whitespace().next().unwrap_or(label);
+
+        let alias = self.contig_aliases.get(label)
+            .or_else(|| self.contig_aliases.get(first_part))
+            .or_else(|| self.alias_by_first_word(label));
+        let candidates = match alias {
+            Some(contig_name) => vec![contig_name.as_str()],
+            None => vec![label, first_part],
+        };
+
+        let tid = match candidates.iter().find_map(|name| header.tid(name.as_bytes())) {
+            Some(tid) => tid,
+            None => return Ok(None),
+        };
+
+        let contig_len = header.target_len(tid).unwrap_or_default() as usize;
+        if contig_len != ref_seq.len() {
+            let contig_name = String::from_utf8_lossy(header.tid2name(tid));
+            return Err(format!(
+                "Length of reference '{label}' ({}) does not match length of contig '{contig_name}' ({contig_len})",
+                ref_seq.len()
+            ));
+        }
+
+        Ok(Some(tid))
+    }
+
+    /// Get the alias of a full label, whose first word is `name`.
+    /// Of several such labels, the alias of the lowest label is used, so the choice does not depend on the map order.
+    fn alias_by_first_word(&self, name: &str) -> Option<&String> {
+        self.contig_aliases
+            .iter()
+            .filter(|(label, _contig_name)| label.split_whitespace().next() == Some(name))
+            .min_by_key(|(label, _contig_name)| label.as_str())
+            .map(|(_label, contig_name)| contig_name)
+    }
+
+    /// Get the annotated coding sequences located on a reference sequence.
+    /// Features are matched by the name of the contig in the alignment file, the label of the reference,
+    /// or the first word of the label.
+    fn cds_features_for(&self, ref_seq: &Seq, contig_name: &str) -> Vec<&Cds> {
+        let names = Self::reference_names(ref_seq, contig_name);
+
+        self.cds_features
+            .iter()
+            .filter(|cds| names.contains(&cds.get_contig().as_str()))
+            .collect()
+    }
+
+    /// Get the regions located on a reference sequence, clipped to its length.
+    /// Regions are matched like annotated coding sequences.
+    fn regions_for(&self, ref_seq: &Seq, contig_name: &str) -> Vec<Region> {
+        let names = Self::reference_names(ref_seq, contig_name);
+
+        self.regions
+            .iter()
+            .filter(|region| names.contains(&region.get_contig().as_str()))
+            .filter_map(|region| region.clip(ref_seq.len()))
+            .collect()
+    }
+
+    /// Names by which features refer to a reference sequence: the name of the contig in the alignment file,
+    /// the label of the reference, and the first word of the label.
+    fn reference_names<'a>(ref_seq: &'a Seq, contig_name: &'a str) -> [&'a str; 3] {
+        let label = ref_seq.get_label().as_str();
+        let first_part = label.split_whitespace().next().unwrap_or(label);
+        [contig_name, label, first_part]
+    }
+
+    /// Read a file mapping reference labels to contig names, with one tab separated pair per line.
+    fn read_contig_aliases(path: &String) -> HashMap<String, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => panic!("Unable to read contig aliases from {path}: {e}"),
+        };
+        Self::parse_contig_aliases(&content, path)
+    }
+
+    /// Parse contig aliases from the content of an alias file, read from `path`.
+    /// Blank lines and lines starting with `#` are skipped.
+    fn parse_contig_aliases(content: &str, path: &str) -> HashMap<String, String> {
+        let mut aliases = HashMap::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim_end();
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line.split_once('\t') {
+                Some((label, contig_name)) => {
+                    aliases.insert(label.trim().to_string(), contig_name.trim().to_string());
+                }
+                None => panic!("Malformed contig alias in line {} of {path}: {line}", i + 1),
+            }
+        }
+
+        aliases
+    }
 
-            // calculations
-            let consensus_seq = self.compute_consensus(&ref_seq, &results);
-            let aln_stats = self.compute_aln_stats(&results);
+    pub fn calculate_batch(
+        &self,
+        ref_seqs: &[Seq],
+        samples: &[Sample],
+        threads: usize,
+    ) -> BatchResults<Vec<Consensus>> {
+        //! Calculate consensuses for multiple samples, that were aligned against the same reference.
+        //!
+        //! - `ref_seqs: &[Seq]`: The references against which the reads of all samples were aligned.
+        //! - `samples: &[Sample]`: The samples to process.
+        //! - `threads: usize`: Number of samples processed in parallel. Zero means one per CPU.
+        //!
+        //! Returns the samples in the passed order, each paired with either its consensuses,
+        //! or an error message. A failing sample does not abort the processing of other samples.
+        Self::run_batch(samples, threads, |aln_path| self.calculate(ref_seqs.to_vec(), aln_path))
+    }
 
-            let consensus = Consensus::new(ref_seq, aln_path.clone(), consensus_seq, aln_stats, results);
-            consensus_vec.push(consensus)
+    pub fn calculate_batch_grouped(
+        &self,
+        ref_seqs: &[Seq],
+        samples: &[Sample],
+        threads: usize,
+        tag: &str,
+    ) -> BatchResults<GroupedConsensuses> {
+        //! Calculate consensuses per group of reads for multiple samples, that were aligned against the same reference.
+        //!
+        //! - `ref_seqs: &[Seq]`: The references against which the reads of all samples were aligned.
+        //! - `samples: &[Sample]`: The samples to process.
+        //! - `threads: usize`: Number of samples processed in parallel. Zero means one per CPU.
+        //! - `tag: &str`: Two-letter SAM tag, e.g. `RG`, `CB` or `BC`.
+        //!
+        //! Returns the samples in the passed order, each paired with either the consensuses of its groups,
+        //! keyed by tag value, or an error message.
+        Self::run_batch(samples, threads, |aln_path| self.calculate_grouped(ref_seqs.to_vec(), aln_path, tag))
+    }
+
+    /// Process samples in parallel, catching panics of failing samples.
+    fn run_batch<T, F>(samples: &[Sample], threads: usize, process: F) -> BatchResults<T>
+    where
+        T: Send,
+        F: Fn(String) -> T + Sync,
+    {
+        let pool = match ThreadPoolBuilder::new().num_threads(threads).build() {
+            Ok(pool) => pool,
+            Err(e) => panic!("Unable to create thread pool: {e}"),
+        };
+
+        pool.install(|| {
+            samples
+                .par_iter()
+                .map(|sample| {
+                    let aln_path = sample.get_aln_path().clone();
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| process(aln_path)));
+
+                    let result = result.map_err(|payload| Self::panic_message(payload.as_ref()));
+                    if let Err(e) = &result {
+                        error!("Failed to calculate consensus for sample {}: {e}", sample.get_id());
+                    }
+
+                    (sample.clone(), result)
+                })
+                .collect()
+        })
+    }
+
+    /// Extract the message from the payload of a caught panic.
+    fn panic_message(payload: &(dyn Any + Send)) -> String {
+        if let Some(msg) = payload.downcast_ref::<&str>() {
+            msg.to_string()
+        } else if let Some(msg) = payload.downcast_ref::<String>() {
+            msg.clone()
+        } else {
+            String::from("Unknown error")
         }
-        consensus_vec
     }
 
     fn read_with_index(aln_path: &String) -> IndexedReader {
@@ -95,132 +573,572 @@ impl Calculator {
     }
 
     /// Compute the consensus sequence for the seen reads that satisfied the quality criteria.
-    fn compute_consensus(&self, ref_seq: &Seq, analysis_result: &AnalysisResult) -> Seq {
+    /// Returns the consensus sequence and the applied indels.
+    fn compute_consensus(&self, ref_seq: &Seq, analysis_result: &AnalysisResult, cds_features: &[&Cds]) -> (Seq, Vec<InDel>) {
         let label = ref_seq.get_label().clone();
         let base_calling_consensus = self.use_majority_bases(ref_seq, &analysis_result.base_counts);
-        let indel_consensus = self.apply_indels(&ref_seq, base_calling_consensus, &analysis_result);
+        let (indel_consensus, indels) = self.apply_indels(ref_seq, base_calling_consensus, analysis_result, cds_features);
+
+        (Seq::new(label, indel_consensus), indels)
+    }
+
+    /// Find the amino acid mutations of the consensus within the annotated coding sequences.
+    fn find_aa_mutations(ref_seq: &Seq, consensus_seq: &Seq, indels: &[InDel], cds_features: &[&Cds]) -> Vec<AaMutation> {
+        let code = GeneticCode::standard();
+        cds_features
+            .iter()
+            .flat_map(|cds| AaMutation::call(ref_seq, consensus_seq, indels, cds, &code))
+            .collect()
+    }
+
+    /// Find the alleles differing from the consensus, that suffice the minor variant thresholds.
+    fn find_minor_variants(&self, ref_seq: &Seq, indels: &[InDel], analysis_result: &AnalysisResult) -> Vec<MinorVariant> {
+        let called_bases = self.call_majority_bases(&analysis_result.base_counts);
+        MinorVariant::call(
+            ref_seq,
+            &called_bases,
+            indels,
+            analysis_result,
+            self.analysis_settings.min_variant_frequency,
+            self.analysis_settings.min_variant_count,
+        )
+    }
 
-        Seq::new(label, indel_consensus)
+    /// Find the positions, in which the consensus base differs from the reference base.
+    fn find_substitutions(&self, ref_seq: &Seq, analysis_result: &AnalysisResult) -> Vec<Substitution> {
+        let consensus_bases = self.use_majority_bases(ref_seq, &analysis_result.base_counts);
+        Substitution::call(ref_seq, &consensus_bases, analysis_result)
+    }
+
+    /// Flag the applied indels, that shift the reading frame of an annotated coding sequence.
+    fn find_frameshifts(
+        &self,
+        ref_seq: &Seq,
+        indels: &[InDel],
+        analysis_result: &AnalysisResult,
+        cds_features: &[&Cds],
+    ) -> Vec<Frameshift> {
+        let mut frameshifts = Vec::new();
+
+        for indel in indels {
+            let count = analysis_result.indel_counts[indel];
+            let support = indel.support(count, &analysis_result.coverage);
+
+            for cds in cds_features.iter().filter(|cds| cds.is_frameshifted_by(indel)) {
+                let (start, stop) = (indel.get_start(), indel.get_stop());
+                warn!(
+                    "Applied indel between positions {start} and {stop} of '{}' shifts the reading frame of CDS '{}'.",
+                    ref_seq.get_label(), cds.get_name()
+                );
+                frameshifts.push(Frameshift::new(indel.clone(), cds.get_name().clone(), count, support));
+            }
+        }
+
+        frameshifts
     }
 
     /// Compute alignment statistics for reads considered in the consensus calculation.
     fn compute_aln_stats(&self, analysis_result: &AnalysisResult) -> AlnStats {
-        let quantile_factors = vec![
-            0.0, 0.1, 0.2, 0.25, 0.3, 0.4, 0.5, 0.6, 0.7, 0.75, 0.8, 0.9, 1.0,
-        ];
-        let stats = AlnStats::from_data(&analysis_result.valid_alns, &quantile_factors);
+        let quantile_factors = QUANTILE_FACTORS.to_vec();
+        AlnStats::from_counts(&analysis_result.aln_data_counts, &quantile_factors)
+    }
 
-        stats
+    /// Compute the coverage statistics of a consensus, and judge them by the QC rules.
+    /// If the calculation is restricted to regions, only positions within the regions are considered.
+    fn compute_qc_stats(&self, ref_seq: &Seq, regions: &[Region], analysis_result: &AnalysisResult) -> QcStats {
+        let min_observations = self.aln_quality_reqs.min_observations;
+        let intervals = if regions.is_empty() { vec![(0, ref_seq.len())] } else { Region::merge_intervals(regions) };
+
+        let mut depth_thresholds = self.qc_rules.depth_thresholds.clone();
+        depth_thresholds.push(min_observations);
+        depth_thresholds.sort_unstable();
+        depth_thresholds.dedup();
+
+        let stats = QcStats::new(
+            &analysis_result.coverage, &analysis_result.base_counts, &intervals, min_observations, &depth_thresholds,
+        );
+        let (verdict, reasons) = self.qc_rules.judge(&stats);
+        stats.with_verdict(verdict, reasons)
     }
 
-    fn use_majority_bases(&self, ref_seq: &Seq, base_counts: &BaseCounts) -> Vec<u8> {
-        let mut consensus_seq: Vec<u8> = Vec::with_capacity(ref_seq.len());
+    /// Compute the diversity of the observed bases per position, relative to the consensus bases.
+    fn compute_diversity(&self, ref_seq: &Seq, analysis_result: &AnalysisResult) -> DiversityTrack {
+        let consensus_bases = self.use_majority_bases(ref_seq, &analysis_result.base_counts);
+        DiversityTrack::new(&analysis_result.base_counts, &consensus_bases)
+    }
 
-        for (ref_pos, base_counter) in base_counts.iter().enumerate() {
-            // get original base at `ref_pos`
-            let reference_base = ref_seq[ref_pos];
+    /// Compute coverage statistics for the regions located on a reference sequence.
+    fn compute_region_stats(&self, regions: &[Region], analysis_result: &AnalysisResult) -> Vec<RegionStats> {
+        let min_depth = self.aln_quality_reqs.min_observations;
+        regions
+            .iter()
+            .map(|region| RegionStats::new(region.clone(), &analysis_result.coverage, min_depth))
+            .collect()
+    }
 
-            // determine consensus by simple majority
-            let consensus_base;
-            if base_counter.is_empty() {
-                // no coverage -> use reference base
-                consensus_base = reference_base;
-            } else {
-                // has coverage
-                let (most_common, observations) = *base_counter.most_common().first().unwrap();
-                let sufficient_observations =
-                    observations >= self.aln_quality_reqs.min_observations;
+    /// Mask or trim the consensus outside the regions, depending on the analysis settings.
+    /// The regions are relative to the reference, so their positions in the consensus are shifted by the applied indels,
+    /// and by the bases extending the start of the consensus. The extensions beyond the ends of the reference
+    /// are kept, if the respective end is within a region.
+    fn handle_outside_regions(
+        &self,
+        consensus_seq: Seq,
+        ref_len: usize,
+        indels: &[InDel],
+        regions: &[Region],
+        end_extensions: (usize, usize),
+    ) -> Seq {
+        let mode = self.analysis_settings.outside_regions;
+        if regions.is_empty() || mode == OutsideRegions::Reference {
+            return consensus_seq;
+        }
 
-                consensus_base = if sufficient_observations {
-                    most_common
-                } else {
-                    reference_base
-                };
+        // position in the consensus corresponding to a position in the reference,
+        // where the ends of the reference include the extensions beyond them,
+        // and deleted positions correspond to the position following the deletion
+        let len = consensus_seq.len();
+        let consensus_pos = |ref_pos: usize| {
+            if ref_pos == 0 {
+                return 0;
             }
+            if ref_pos >= ref_len {
+                return len;
+            }
+            let shift = indels
+                .iter()
+                .map(|indel| (indel.get_seq().len(), indel.replaced_range()))
+                .filter(|(_inserted, replaced)| replaced.start <= ref_pos)
+                .map(|(inserted, replaced)| inserted as isize - (replaced.end.min(ref_pos) - replaced.start) as isize)
+                .sum::<isize>();
+            ((end_extensions.0 + ref_pos) as isize + shift).clamp(0, len as isize) as usize
+        };
 
-            consensus_seq.push(consensus_base);
+        let mut bases = consensus_seq.get_sequence().clone();
+        let intervals = Region::merge_intervals(regions);
+        let mut gap_start = 0;
+        for (start, end) in intervals.iter().copied().chain([(ref_len, ref_len)]) {
+            let gap = consensus_pos(gap_start)..consensus_pos(start);
+            bases[gap].fill(b'N');
+            gap_start = end;
         }
 
-        consensus_seq
+        if mode == OutsideRegions::Trim {
+            let span_end = consensus_pos(intervals[intervals.len() - 1].1);
+            let span_start = consensus_pos(intervals[0].0).min(span_end);
+            bases = bases[span_start..span_end].to_vec();
+        }
+
+        Seq::new(consensus_seq.get_label().clone(), bases)
     }
 
-    fn analyse_alignments(&self, ref_seq: &Seq, aln_reader: &mut IndexedReader) -> AnalysisResult {
-        //! Does a pileup over the passed `ref_seq` and generates an `AnalysisResult`.
-        //! This analysis result is later used to compute the consensus.
+    /// Extend the consensus beyond the ends of the reference by the consensus of the soft-clipped bases,
+    /// if enabled in the analysis settings. Returns the extended consensus and the number of bases added to either end.
+    fn extend_ends(&self, consensus_seq: Seq, analysis_result: &AnalysisResult) -> (Seq, (usize, usize)) {
+        if !self.analysis_settings.extend_ends {
+            return (consensus_seq, (0, 0));
+        }
 
-        // instantiate vectors for analysis
-        let mut coverage = vec![0; ref_seq.len()];
-        let mut base_counts = vec![Counter::new(); ref_seq.len()];
-        let mut reads_seen = HashSet::new();
-        let mut indel_counts = Counter::new();
-        let mut valid_alns = Vec::new();
+        let min_reads = self.analysis_settings.min_extension_reads;
+        let mut leading = Self::clip_consensus(&analysis_result.leading_clips, min_reads);
+        let trailing = Self::clip_consensus(&analysis_result.trailing_clips, min_reads);
+        let end_extensions = (leading.len(), trailing.len());
 
-        // define region for retrieving pileups, based on name of passed `ref_seq`
+        // leading clips are ordered outward from the start, i.e. reversed
+        leading.reverse();
+        let bases = [leading.as_slice(), consensus_seq.get_sequence(), trailing.as_slice()].concat();
+
+        (Seq::new(consensus_seq.get_label().clone(), bases), end_extensions)
+    }
+
+    /// Build the consensus of soft-clipped sequences, that are ordered outward from an end of the reference.
+    /// The consensus grows base by base, until fewer than `min_reads` reads agree on the next base,
+    /// or the agreeing reads are no majority among the reads reaching that far.
+    fn clip_consensus(clips: &[Vec<u8>], min_reads: usize) -> Vec<u8> {
+        let mut extension = Vec::new();
+
+        for offset in 0.. {
+            let bases = clips.iter().filter_map(|clip| clip.get(offset)).collect::<Counter<_>>();
+            let depth = bases.values().sum::<usize>();
+            let (base, count) = match bases.most_common().first() {
+                Some((base, count)) => (**base, *count),
+                None => break,
+            };
+
+            if base == b'N' || count < min_reads.max(1) || 2 * count <= depth {
+                break;
+            }
+            extension.push(base);
+        }
+
+        extension
+    }
+
+    /// Compute statistics of the UMI families, if reads were collapsed into families.
+    fn compute_family_stats(&self, analysis_result: &AnalysisResult) -> Option<FamilyStats> {
+        self.analysis_settings.umi_tag.as_ref()?;
+
+        let quantile_factors = QUANTILE_FACTORS.to_vec();
+        let sizes = analysis_result.family_sizes.clone();
+        Some(FamilyStats::from_sizes(sizes, self.analysis_settings.min_family_size, &quantile_factors))
+    }
+
+    fn use_majority_bases(&self, ref_seq: &Seq, base_counts: &BaseCounts) -> Vec<u8> {
+        // positions without sufficient observations keep the reference base
+        self.call_majority_bases(base_counts)
+            .into_iter()
+            .enumerate()
+            .map(|(ref_pos, called_base)| called_base.unwrap_or(ref_seq[ref_pos]))
+            .collect()
+    }
+
+    /// Determine the consensus base of every position by simple majority,
+    /// if the most common base has at least `min_observations` observations.
+    fn call_majority_bases(&self, base_counts: &BaseCounts) -> Vec<Option<u8>> {
+        base_counts
+            .iter()
+            .map(|base_counter| {
+                let (most_common, observations) = *base_counter.most_common().first()?;
+                (observations >= self.aln_quality_reqs.min_observations).then_some(most_common)
+            })
+            .collect()
+    }
+
+    fn analyse_alignments(
+        &self,
+        ref_seq: &Seq,
+        tid: u32,
+        aln_reader: &mut IndexedReader,
+        tag: Option<&str>,
+        intervals: &[(usize, usize)],
+    ) -> HashMap<String, AnalysisResult> {
+        //! Does a pileup over the passed intervals of `ref_seq` and generates an `AnalysisResult` per group of reads.
+        //! Reads are grouped by the value of the passed tag. Without a tag, all reads belong to the same group.
+        //! The intervals have to be disjoint, so no position is analysed twice.
+        //! These analysis results are later used to compute the consensuses.
+
+        let mut results: HashMap<String, AnalysisResult> = HashMap::new();
+        let mut decisions = ReadDecisions::default();
+        let mut tally = ReadTally::new(aln_reader.header().tid2name(tid));
+        let (mut untagged, mut untagged_alns) = (ReadDecisions::default(), 0);
+        let (mut umi_less, mut umi_less_alns) = (ReadDecisions::default(), 0);
+        let mut seen_families = SeenKeys::default();
+        let umi_tag = self.analysis_settings.umi_tag.as_deref();
+
+        // retrieve pileups for every interval on the contig matching the passed `ref_seq`
         // a "pileup" holds references to all reads that were aligned to a specific position
-        let stats = aln_reader.index_stats();
-        let label = ref_seq.get_label();
-        let first_part = label.split(" ").next().unwrap();
-        aln_reader.fetch(first_part).unwrap();
-        for p in aln_reader.pileup() {
-            let pileup = match p {
-                Ok(p) => p,
-                Err(_e) => {
-                    debug!("Unable to generate pileup: {_e}");
+        for (start, end) in intervals {
+            if let Err(e) = aln_reader.fetch((tid, *start as i64, *end as i64)) {
+                panic!("Unable to fetch alignments for '{}': {e}", ref_seq.get_label());
+            }
+            for p in aln_reader.pileup() {
+                let pileup = match p {
+                    Ok(p) => p,
+                    Err(_e) => {
+                        debug!("Unable to generate pileup: {_e}");
+                        continue;
+                    }
+                };
+
+                // pileups include all positions of the fetched reads, also those outside of the interval
+                let ref_pos = pileup.pos() as usize;
+                if !(*start..*end).contains(&ref_pos) {
                     continue;
                 }
-            };
+                debug!("Analysing pileup in position {ref_pos}.");
+                decisions.evict_before(ref_pos as i64);
+                untagged.evict_before(ref_pos as i64);
+                umi_less.evict_before(ref_pos as i64);
+                seen_families.evict_before(ref_pos as i64);
+                tally.advance(ref_pos as i64);
+
+                // the suitable alignments in this position, with the group of the respective read
+                let mut column = Vec::new();
+
+                for alignment in pileup.alignments() {
+                    // the SAM record of the aligned read
+                    let record = alignment.record();
+
+                    // reads are analysed separately per group
+                    let group = match tag {
+                        Some(tag) => Self::tag_value(&record, tag),
+                        None => Some(String::from(UNGROUPED)),
+                    };
+                    let group = match group {
+                        Some(group) => group,
+                        None => {
+                            untagged.decide(&record, |_record| untagged_alns += 1);
+                            continue;
+                        }
+                    };
+                    let result = results.entry(group.clone()).or_insert_with(|| AnalysisResult::empty(ref_seq.len()));
+
+                    // discard read alignments with insufficient quality, flags, etc.
+                    // every alignment is accounted for once, when it is first encountered,
+                    // so valid alignments are registered once, and not in every position they cover
+                    let rejection = decisions.decide(&record, |record| {
+                        let rejection = self.aln_quality_reqs.rejection(record);
+                        match rejection {
+                            Some(_reason) => {
+                                debug!("Skipped alignment of read {}: {_reason}", String::from_utf8_lossy(record.qname()));
+                            }
+                            None => result.aln_data_counts.add(&AlnData::from_record(record)),
+                        }
+                        tally.register(&group, record, rejection);
+                        rejection
+                    });
+                    if rejection.is_some() {
+                        continue;
+                    }
+
+                    column.push((group, alignment));
+                }
 
-            let ref_pos = pileup.pos() as usize;
-            debug!("Analysing pileup in position {ref_pos}.");
+                // parts of split reads may overlap, but every read is observed once per position
+                let column = Self::drop_split_duplicates(column);
 
-            for alignment in pileup.alignments() {
-                // the SAM record of the aligned read
-                let record = alignment.record();
+                // every fragment is counted once for the fragment coverage, even if both mates cover the position
+                Self::register_fragments(&column, &ref_pos, &mut results);
 
-                // register read as seen
-                let read_id = String::from_utf8(record.qname().to_vec()).unwrap();
-                reads_seen.insert(read_id);
+                // bases overhanging the ends of the reference are soft-clipped
+                if self.analysis_settings.extend_ends {
+                    Self::register_clips(&column, &ref_pos, ref_seq.len(), &mut results);
+                }
 
-                // discard read alignments with insufficient quality, flags, etc.
-                if !self.aln_quality_reqs.is_suitable(&record) {
-                    let read_name = String::from_utf8_lossy(record.qname());
-                    debug!("Skipped low quality alignment for read: {}", read_name);
-                    continue;
+                // overlapping mates are resolved to a single observation
+                let column = match self.analysis_settings.mate_overlap {
+                    MateOverlap::CountBoth => column,
+                    mode => Self::resolve_mate_overlaps(column, &ref_pos, mode),
+                };
+
+                let umi_tag = match umi_tag {
+                    Some(umi_tag) => umi_tag,
+                    None => {
+                        for (group, alignment) in column {
+                            let result = results.get_mut(&group).unwrap();
+                            let base = Self::observe_base(&alignment);
+                            let indel = Self::observe_indel(&alignment, &ref_pos);
+                            self.register_observation(result, &ref_pos, base, indel, alignment.record().is_reverse());
+                        }
+                        continue;
+                    }
+                };
+
+                // with UMIs, reads are collapsed into families, before their bases and indels are counted
+                let mut families: HashMap<(String, FamilyKey), Vec<Alignment>> = HashMap::new();
+                for (group, alignment) in column {
+                    match Self::family_key(&alignment.record(), umi_tag) {
+                        Some(key) => families.entry((group, key)).or_default().push(alignment),
+                        None => {
+                            umi_less.decide(&alignment.record(), |_record| umi_less_alns += 1);
+                        }
+                    }
                 }
 
-                // register valid alignment
-                let aln_data = AlnData::from_record(&record);
-                valid_alns.push(aln_data);
+                for ((group, key), members) in families {
+                    let is_reverse = key.3;
+                    let result = results.get_mut(&group).unwrap();
+
+                    // every family is counted once, in the first analysed position it covers,
+                    // which is its start, unless it starts before the analysed interval
+                    let family_end = || members.iter().map(|a| a.record().cigar().end_pos()).max().unwrap_or_default();
+                    if seen_families.first_seen((group.clone(), key), family_end) {
+                        result.family_sizes.push(members.len());
+                    }
+                    if members.len() < self.analysis_settings.min_family_size {
+                        continue;
+                    }
+
+                    let base = Self::family_consensus(members.iter().map(Self::observe_base)).flatten();
+                    let indel = Self::family_consensus(members.iter().map(|a| Self::observe_indel(a, &ref_pos))).flatten();
+                    self.register_observation(result, &ref_pos, base, indel, is_reverse);
+                }
+            }
+        }
+
+        if let Some(umi_tag) = umi_tag {
+            if umi_less_alns > 0 {
+                warn!(
+                    "Ignored {umi_less_alns} alignments to '{}', because they lack the {umi_tag} tag.",
+                    ref_seq.get_label()
+                );
+            }
+        }
+
+        if let Some(tag) = tag {
+            if untagged_alns > 0 {
+                warn!(
+                    "Ignored {untagged_alns} alignments to '{}', because they lack the {tag} tag.",
+                    ref_seq.get_label()
+                );
+            }
+        }
+
+        for (group, read_accounting) in tally.finish() {
+            results.get_mut(&group).unwrap().read_accounting = read_accounting;
+        }
+
+        results
+    }
+
+    /// Register the base and indel observed in a position for a read, or for a UMI family.
+    fn register_observation(
+        &self,
+        result: &mut AnalysisResult,
+        ref_pos: &usize,
+        base: Option<u8>,
+        indel: Option<InDel>,
+        is_reverse: bool,
+    ) {
+        // reads on the reverse strand are additionally counted separately
+        let strand_base_counts = if is_reverse { Some(&mut result.reverse_base_counts) } else { None };
+        let strand_indel_counts = if is_reverse { Some(&mut result.reverse_indel_counts) } else { None };
+
+        self.register_position(base, ref_pos, &mut result.base_counts, strand_base_counts, &mut result.coverage);
+        self.register_indels(indel, &mut result.indel_counts, strand_indel_counts);
+    }
+
+    /// Increment the fragment coverage of every group for the fragments with a base in the position.
+    /// Mates of a pair belong to the same fragment.
+    fn register_fragments(column: &[(String, Alignment)], ref_pos: &usize, results: &mut HashMap<String, AnalysisResult>) {
+        let fragments = column
+            .iter()
+            .filter(|(_group, alignment)| Self::observe_base(alignment).is_some())
+            .map(|(group, alignment)| (group, alignment.record().qname().to_vec()))
+            .collect::<HashSet<(&String, Vec<u8>)>>();
+
+        for (group, _read_name) in fragments {
+            results.get_mut(group).unwrap().fragment_coverage[*ref_pos] += 1;
+        }
+    }
 
-                self.register_position(&alignment, &ref_pos, &mut base_counts, &mut coverage);
-                self.register_indels(&alignment, &ref_pos, &mut indel_counts);
+    /// Keep a single alignment per read in the pileup position, preferring the primary alignment.
+    /// Supplementary and secondary alignments of a read may overlap its primary alignment, e.g. for split reads.
+    /// Mates of a pair are separate reads here, as their overlaps are resolved by `resolve_mate_overlaps`.
+    fn drop_split_duplicates<'a>(column: Vec<(String, Alignment<'a>)>) -> Vec<(String, Alignment<'a>)> {
+        let mut kept: Vec<(String, Alignment<'a>)> = Vec::with_capacity(column.len());
+        let mut read_indices: HashMap<(String, Vec<u8>, bool), usize> = HashMap::new();
+
+        for (group, alignment) in column {
+            let record = alignment.record();
+            let key = (group.clone(), record.qname().to_vec(), record.is_last_in_template());
+            match read_indices.get(&key) {
+                Some(i) => {
+                    let kept_record = kept[*i].1.record();
+                    let kept_primary = !kept_record.is_supplementary() && !kept_record.is_secondary();
+                    let is_primary = !record.is_supplementary() && !record.is_secondary();
+                    if is_primary && !kept_primary {
+                        kept[*i] = (group, alignment);
+                    }
+                }
+                None => {
+                    read_indices.insert(key, kept.len());
+                    kept.push((group, alignment));
+                }
             }
         }
 
-        AnalysisResult::new(coverage, base_counts, indel_counts, valid_alns, reads_seen)
+        kept
+    }
+
+    /// Collect the bases soft-clipped by reads aligned to the first or last position of the reference.
+    /// Clips at the start are stored reversed, so that the bases of all clips are ordered outward from the reference.
+    /// Reads with supplementary alignments are skipped, because their clipped bases are aligned elsewhere.
+    fn register_clips(column: &[(String, Alignment)], ref_pos: &usize, ref_len: usize, results: &mut HashMap<String, AnalysisResult>) {
+        if *ref_pos != 0 && *ref_pos + 1 != ref_len {
+            return;
+        }
+
+        for (group, alignment) in column {
+            let record = alignment.record();
+            if record.is_supplementary() || record.aux(b"SA").is_ok() {
+                continue;
+            }
+            let cigar = record.cigar();
+            let seq = record.seq().as_bytes();
+            let result = results.get_mut(group).unwrap();
+
+            let leading = cigar.leading_softclips() as usize;
+            if *ref_pos == 0 && record.pos() == 0 && leading > 0 {
+                result.leading_clips.push(seq[..leading].iter().rev().copied().collect());
+            }
+
+            let trailing = cigar.trailing_softclips() as usize;
+            if *ref_pos + 1 == ref_len && cigar.end_pos() as usize == ref_len && trailing > 0 {
+                result.trailing_clips.push(seq[seq.len() - trailing..].to_vec());
+            }
+        }
+    }
+
+    /// Resolve mates overlapping in the pileup position to a single alignment.
+    /// If the mates disagree on the base or indel in this position, the alignment with the higher base quality is kept
+    /// or the fragment is dropped, depending on `mode`. Mates without a base, i.e. with a deletion in this position,
+    /// have no base quality, so disagreeing with them always drops the fragment.
+    fn resolve_mate_overlaps<'a>(
+        column: Vec<(String, Alignment<'a>)>,
+        ref_pos: &usize,
+        mode: MateOverlap,
+    ) -> Vec<(String, Alignment<'a>)> {
+        let mut resolved = Vec::with_capacity(column.len());
+        let mut mates: HashMap<(String, Vec<u8>), Vec<Alignment>> = HashMap::new();
+        for (group, alignment) in column {
+            let record = alignment.record();
+            if record.is_paired() {
+                mates.entry((group, record.qname().to_vec())).or_default().push(alignment);
+            } else {
+                resolved.push((group, alignment));
+            }
+        }
+
+        for ((group, _read_name), mut alignments) in mates {
+            let is_pair = alignments.len() == 2
+                && alignments[0].record().is_first_in_template() != alignments[1].record().is_first_in_template();
+            if !is_pair {
+                resolved.extend(alignments.into_iter().map(|alignment| (group.clone(), alignment)));
+                continue;
+            }
+
+            let second = alignments.pop().unwrap();
+            let first = alignments.pop().unwrap();
+            let agree = Self::observe_base(&first) == Self::observe_base(&second)
+                && Self::observe_indel(&first, ref_pos) == Self::observe_indel(&second, ref_pos);
+
+            let kept = if agree {
+                Some(first)
+            } else {
+                match (mode, Self::observe_quality(&first), Self::observe_quality(&second)) {
+                    (MateOverlap::PreferQuality, Some(qual_first), Some(qual_second)) if qual_first > qual_second => Some(first),
+                    (MateOverlap::PreferQuality, Some(qual_first), Some(qual_second)) if qual_second > qual_first => Some(second),
+                    _ => {
+                        let read_name = String::from_utf8_lossy(first.record().qname()).to_string();
+                        debug!("Dropped discordant overlapping mates of {read_name} in position {ref_pos}.");
+                        None
+                    }
+                }
+            };
+            resolved.extend(kept.map(|alignment| (group, alignment)));
+        }
+
+        resolved
     }
 
     fn register_position(
         &self,
-        alignment: &Alignment,
+        base: Option<u8>,
         ref_pos: &usize,
         base_counts: &mut BaseCounts,
+        reverse_base_counts: Option<&mut BaseCounts>,
         coverage: &mut Coverage,
     ) {
-        //! Register alignment for position relative to the reference sequence, and update coverage and base counts.
-
-        let record = alignment.record();
-        let seq = record.seq();
+        //! Register the base observed in a position relative to the reference sequence, and update coverage and base counts.
+        //! Without a base, i.e. for deletions and reference skips, nothing is registered.
 
-        let has_read_pos = !alignment.is_refskip() && !alignment.is_del();
-        if has_read_pos {
-            // find position in read
-            let read_pos = alignment.qpos().unwrap();
-
-            // register the base of this read in this position
+        if let Some(base) = base {
+            // register the base in this position
             let bases = &mut base_counts[*ref_pos];
-            let base = seq[read_pos];
             bases[&base] += 1;
+            if let Some(reverse_base_counts) = reverse_base_counts {
+                reverse_base_counts[*ref_pos][&base] += 1;
+            }
 
             // increment coverage
             coverage[*ref_pos] += 1;
@@ -229,28 +1147,75 @@ impl Calculator {
 
     fn register_indels(
         &self,
-        alignment: &Alignment,
-        ref_pos: &usize,
+        indel: Option<InDel>,
         indel_counts: &mut InDelCounts,
+        reverse_indel_counts: Option<&mut InDelCounts>,
     ) {
+        let indel = match indel {
+            Some(indel) => indel,
+            None => return,
+        };
+        if let Some(reverse_indel_counts) = reverse_indel_counts {
+            reverse_indel_counts.update([indel.clone()]);
+        }
+        indel_counts.update([indel]);
+    }
+
+    /// Get the base of an aligned read in the pileup position, if it has one.
+    fn observe_base(alignment: &Alignment) -> Option<u8> {
+        let has_read_pos = !alignment.is_refskip() && !alignment.is_del();
+        if !has_read_pos {
+            return None;
+        }
+
+        // find position in read
+        let read_pos = alignment.qpos().unwrap();
+        Some(alignment.record().seq()[read_pos])
+    }
+
+    /// Get the quality of the base of an aligned read in the pileup position, if it has one.
+    fn observe_quality(alignment: &Alignment) -> Option<u8> {
+        let has_read_pos = !alignment.is_refskip() && !alignment.is_del();
+        if !has_read_pos {
+            return None;
+        }
+
+        let read_pos = alignment.qpos().unwrap();
+        Some(alignment.record().qual()[read_pos])
+    }
+
+    /// Get the indel following the pileup position in an aligned read, if there is one.
+    fn observe_indel(alignment: &Alignment, ref_pos: &usize) -> Option<InDel> {
         let record = alignment.record();
         let read_name = String::from_utf8_lossy(record.qname());
-        let indel = match alignment.indel() {
+        match alignment.indel() {
             Indel::Ins(len) => {
                 let ins = Self::compute_insertion(len, *ref_pos, alignment);
                 let start = ins.get_start();
                 debug!("{read_name} contains insertion of length {len} after {start}.");
-                ins
+                Some(ins)
             }
             Indel::Del(len) => {
                 let del = Self::compute_deletion(len, *ref_pos);
                 let (start, stop) = (del.get_start(), del.get_stop());
                 debug!("{read_name} contains deletion between positions {start} and {stop}.");
-                del
+                Some(del)
             }
-            Indel::None => return,
-        };
-        indel_counts.update([indel]);
+            Indel::None => None,
+        }
+    }
+
+    /// Determine the consensus of the observations of a UMI family's members.
+    /// Returns `None`, if no observation is more frequent than all others.
+    fn family_consensus<T: Clone + Eq + Hash>(observations: impl Iterator<Item = T>) -> Option<T> {
+        let counts = observations.collect::<Counter<T>>();
+        let ordered = counts.most_common();
+        match ordered.as_slice() {
+            [] => None,
+            [(only, _)] => Some(only.clone()),
+            [(first, first_count), (_, second_count), ..] if first_count > second_count => Some(first.clone()),
+            _ => None,
+        }
     }
 
     fn compute_insertion(len: u32, ref_pos: usize, alignment: &Alignment) -> InDel {
@@ -287,19 +1252,22 @@ impl Calculator {
         ref_seq: &Seq,
         seq_bytes: Vec<u8>,
         analysis_result: &AnalysisResult,
-    ) -> Vec<u8> {
+        cds_features: &[&Cds],
+    ) -> (Vec<u8>, Vec<InDel>) {
         let applicable_indels =
-            self.get_applicable_indels(&analysis_result.indel_counts, &analysis_result.coverage);
+            self.get_applicable_indels(ref_seq, &analysis_result.indel_counts, &analysis_result.coverage, cds_features);
         let ref_len = ref_seq.len();
 
         // we prepend string slices to this vector from which we later construct the consensus
         let mut vd: VecDeque<&[u8]> = VecDeque::new();
+        let mut applied_indels = VecDeque::new();
 
         // we get slices from the event stop to the start of the previous event
         // "previous" in the sense of previous iteration, but positionally next
         let mut prev_event_start = ref_len;
         for indel in applicable_indels {
-            let event_stop = indel.get_stop();
+            let replaced = indel.replaced_range();
+            let event_stop = replaced.end;
 
             // skip if this indel interferes with the last applied indel
             let interferes = prev_event_start < event_stop  // events overlap
@@ -319,9 +1287,10 @@ impl Calculator {
 
             // add event sequence
             vd.push_front(indel.get_seq());
+            applied_indels.push_front(indel.clone());
 
             // amend positional cutoff for next iteration
-            prev_event_start = indel.get_start();
+            prev_event_start = replaced.start;
         }
 
         // push sequence from absolute start to start of first event
@@ -336,13 +1305,15 @@ impl Calculator {
             }
         }
 
-        consensus
+        (consensus, applied_indels.into())
     }
 
     fn get_applicable_indels<'a>(
         &self,
+        ref_seq: &Seq,
         indel_counts: &'a InDelCounts,
         coverage: &Coverage,
+        cds_features: &[&Cds],
     ) -> VecDeque<&'a InDel> {
         //! Get a vector of indel references, where indels are filtered by whether they're
         //! applicable, and ordered from back to front, for easy insertion.
@@ -354,12 +1325,19 @@ impl Calculator {
 
             let has_min_obs = count > self.aln_quality_reqs.min_observations;
 
-            let indel_cov = &coverage[indel.range()];
-            let total_cov = indel_cov.iter().sum::<usize>() as f64;
-            let avg_cov = total_cov / indel_cov.len() as f64;
-
-            let required_cov = avg_cov * self.aln_quality_reqs.indel_cutoff;
-            let has_required_cov = required_cov <= count as f64;
+            // frameshifts within coding sequences need the stricter support of all spanning reads
+            // in long-read mode, indels within homopolymer runs need more support
+            let reqs = &self.aln_quality_reqs;
+            let is_frameshift = cds_features.iter().any(|cds| cds.is_frameshifted_by(indel));
+            let has_required_cov = if is_frameshift {
+                let cutoff = reqs.indel_cutoff_for(reqs.frameshift_cutoff, indel, ref_seq.get_sequence());
+                indel.support(count, coverage) >= cutoff
+            } else {
+                let cutoff = reqs.indel_cutoff_for(reqs.indel_cutoff, indel, ref_seq.get_sequence());
+                let avg_cov = indel.mean_coverage(coverage);
+                let required_cov = avg_cov * cutoff;
+                required_cov <= count as f64
+            };
 
             has_min_obs && has_required_cov
         });
@@ -416,3 +1394,460 @@ impl Calculator {
         indels.collect::<VecDeque<&InDel>>()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bam::header::{Header, HeaderRecord};
+    use bam::record::{Cigar, CigarString};
+    use bam::{Format, Writer};
+
+    fn init_calculator(aliases: &[(&str, &str)]) -> Calculator {
+        let mut calculator = Calculator::new(AlnQualityReqs::default(), AnalysisSettings::default(), QcRules::default());
+        calculator.contig_aliases =
+            aliases.iter().map(|(label, contig)| (label.to_string(), contig.to_string())).collect();
+        calculator
+    }
+
+    fn init_bam_header(contigs: &[(&str, u64)]) -> Header {
+        let mut header = Header::new();
+        for (name, len) in contigs {
+            header.push_record(HeaderRecord::new(b"SQ").push_tag(b"SN", name).push_tag(b"LN", len));
+        }
+        header
+    }
+
+    fn init_header(contigs: &[(&str, u64)]) -> HeaderView {
+        HeaderView::from_header(&init_bam_header(contigs))
+    }
+
+    fn init_alignment(name: &[u8], pos: i64, len: u32, flags: u16) -> Record {
+        init_clipped_alignment(name, pos, (0, len, 0), flags)
+    }
+
+    /// Like `init_alignment`, with the passed numbers of leading soft-clipped, aligned and trailing soft-clipped bases.
+    /// The aligned bases are `A`, the leading clipped bases `T`, and the trailing clipped bases `G`.
+    fn init_clipped_alignment(name: &[u8], pos: i64, (leading, len, trailing): (u32, u32, u32), flags: u16) -> Record {
+        let cigar = [Cigar::SoftClip(leading), Cigar::Match(len), Cigar::SoftClip(trailing)];
+        let cigar = CigarString(cigar.into_iter().filter(|op| !op.is_empty()).collect());
+        let seq = [vec![b'T'; leading as usize], vec![b'A'; len as usize], vec![b'G'; trailing as usize]].concat();
+        init_read(name, pos, &cigar, &seq, &vec![30; seq.len()], flags)
+    }
+
+    /// Like `init_alignment`, with the passed CIGAR, bases and qualities. The alignment score is the number of matches.
+    fn init_read(name: &[u8], pos: i64, cigar: &CigarString, seq: &[u8], qual: &[u8], flags: u16) -> Record {
+        let matches = cigar.iter().filter(|op| matches!(op, Cigar::Match(_))).map(|op| op.len()).sum::<u32>();
+        let mut rec = Record::new();
+        rec.set(name, Some(cigar), seq, qual);
+        rec.set_tid(0);
+        rec.set_pos(pos);
+        rec.set_mapq(60);
+        rec.set_flags(flags);
+        rec.push_aux(b"AS", Aux::U8(matches as u8)).unwrap();
+        rec.push_aux(b"NM", Aux::U8(0)).unwrap();
+        rec
+    }
+
+    /// Applies the indels with their counts to a reference of 40 bases, that is covered by 100 reads throughout.
+    fn apply_counted_indels(indels: &[(InDel, usize)]) -> (Vec<u8>, Vec<InDel>) {
+        let ref_seq = Seq::new(String::from("ref"), b"ACGT".repeat(10));
+        let mut result = AnalysisResult::empty(ref_seq.len());
+        result.coverage = vec![100; ref_seq.len()];
+        for (indel, count) in indels {
+            result.indel_counts.insert(indel.clone(), *count);
+        }
+        init_calculator(&[]).apply_indels(&ref_seq, ref_seq.get_sequence().clone(), &result, &[])
+    }
+
+    /// Writes the alignments, sorted by position, to a BAM file in the temporary directory, and returns its path.
+    fn write_bam(file_name: &str, header: &Header, alignments: &[Record]) -> String {
+        let path = std::env::temp_dir().join(format!("{}_{file_name}.bam", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        let mut writer = Writer::from_path(&path, header, Format::Bam).unwrap();
+        alignments.iter().sorted_by_key(|rec| rec.pos()).for_each(|rec| writer.write(rec).unwrap());
+        path
+    }
+
+    /// Writes the alignments, sorted by position, to an indexed BAM file,
+    /// and analyses the intervals of a reference of 4000 bases.
+    fn analyse(
+        calculator: &Calculator,
+        file_name: &str,
+        alignments: &[Record],
+        intervals: &[(usize, usize)],
+    ) -> AnalysisResult {
+        let ref_seq = Seq::new(String::from("ref"), b"ACGT".repeat(1000));
+        let path = write_bam(file_name, &init_bam_header(&[("ref", 4000)]), alignments);
+        let mut reader = Calculator::read_with_index(&path);
+        let mut results = calculator.analyse_alignments(&ref_seq, 0, &mut reader, None, intervals);
+        fs::remove_file(change_suffix(&path, "bai")).unwrap();
+        fs::remove_file(&path).unwrap();
+        results.remove(UNGROUPED).unwrap_or_else(|| AnalysisResult::empty(ref_seq.len()))
+    }
+
+    #[test]
+    fn groups_by_read_group() {
+        let mut header = init_bam_header(&[("ref", 4000)]);
+        for id in ["a", "b", "empty"] {
+            header.push_record(HeaderRecord::new(b"RG").push_tag(b"ID", id));
+        }
+        let read = |name: &str, read_group: Option<&str>| {
+            let mut rec = init_alignment(name.as_bytes(), 0, 20, 0);
+            if let Some(read_group) = read_group {
+                rec.push_aux(b"RG", Aux::String(read_group)).unwrap();
+            }
+            rec
+        };
+        let alignments = [read("a1", Some("a")), read("a2", Some("a")), read("b1", Some("b")), read("none", None)];
+        let path = write_bam("read_groups", &header, &alignments);
+
+        let ref_seqs = vec![Seq::new(String::from("ref"), b"ACGT".repeat(1000))];
+        let calculator = init_calculator(&[]);
+        let grouped = calculator.calculate_groups(ref_seqs.clone(), &path, Some("RG"));
+        let ungrouped = calculator.calculate_groups(ref_seqs, &path, None);
+        fs::remove_file(change_suffix(&path, "bai")).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // every declared read group has a consensus, and reads without read group belong to none of them
+        let total_reads = |groups: &GroupedConsensuses| {
+            groups.iter().map(|(group, consensuses)| (group.clone(), consensuses[0].get_total_reads())).collect_vec()
+        };
+        let expected = [("a", 2), ("b", 1), ("empty", 0)].map(|(group, reads)| (group.to_string(), reads));
+        assert_eq!(total_reads(&grouped), expected);
+
+        // without a tag, all reads belong to the same group
+        assert_eq!(total_reads(&ungrouped), [(String::from(UNGROUPED), 4)]);
+    }
+
+    #[test]
+    fn overlapping_mates() {
+        // the second mate overlaps the first in positions 10 to 19, and differs in three of them:
+        // with a higher quality in position 12, a lower one in 14, and the same one in 16
+        let first = init_read(b"pair", 0, &CigarString(vec![Cigar::Match(20)]), &[b'A'; 20], &[30; 20], 0x41);
+        let mut seq = [b'A'; 20];
+        let mut qual = [30; 20];
+        (seq[2], qual[2]) = (b'C', 40);
+        (seq[4], qual[4]) = (b'G', 20);
+        seq[6] = b'T';
+        let second = init_read(b"pair", 10, &CigarString(vec![Cigar::Match(20)]), &seq, &qual, 0x81);
+        let alignments = [first, second];
+
+        let analyse_mates = |mate_overlap: MateOverlap| {
+            let settings = AnalysisSettings { mate_overlap, ..AnalysisSettings::default() };
+            let calculator = Calculator::new(AlnQualityReqs::default(), settings, QcRules::default());
+            analyse(&calculator, "overlapping_mates", &alignments, &[(0, 4000)])
+        };
+        let bases = |result: &AnalysisResult, pos: usize| result.base_counts[pos].most_common_ordered();
+
+        // by default, both mates are counted
+        let both = analyse_mates(MateOverlap::CountBoth);
+        assert_eq!(both.coverage[11], 2);
+        assert_eq!(bases(&both, 12), vec![(b'A', 1), (b'C', 1)]);
+        assert_eq!(both.coverage.iter().sum::<usize>(), 40);
+
+        // otherwise, the fragment is counted once, where the mates overlap
+        let prefer_quality = analyse_mates(MateOverlap::PreferQuality);
+        let drop_discordant = analyse_mates(MateOverlap::DropDiscordant);
+        for result in [&prefer_quality, &drop_discordant] {
+            assert_eq!([5, 11, 25].map(|pos| result.coverage[pos]), [1, 1, 1]);
+            assert_eq!(result.fragment_coverage.iter().sum::<usize>(), 30);
+        }
+
+        // the mate with the higher quality wins, and mates with the same quality are dropped
+        assert_eq!(bases(&prefer_quality, 12), vec![(b'C', 1)]);
+        assert_eq!(bases(&prefer_quality, 14), vec![(b'A', 1)]);
+        assert_eq!(prefer_quality.coverage[16], 0);
+        assert_eq!(prefer_quality.coverage.iter().sum::<usize>(), 29);
+
+        // discordant mates are dropped
+        assert_eq!([12, 14, 16].map(|pos| drop_discordant.coverage[pos]), [0, 0, 0]);
+        assert_eq!(drop_discordant.coverage.iter().sum::<usize>(), 27);
+    }
+
+    #[test]
+    fn umi_families() {
+        let read = |name: &str, base: u8, umi: Option<&str>| {
+            let mut rec = init_read(name.as_bytes(), 0, &CigarString(vec![Cigar::Match(20)]), &[base; 20], &[30; 20], 0);
+            if let Some(umi) = umi {
+                rec.push_aux(b"RX", Aux::String(umi)).unwrap();
+            }
+            rec
+        };
+        // a family of three reads, a family of a single read, and a read without UMI
+        let alignments = [
+            read("a1", b'A', Some("AAA")),
+            read("a2", b'A', Some("AAA")),
+            read("a3", b'A', Some("AAA")),
+            read("c1", b'C', Some("CCC")),
+            read("g1", b'G', None),
+        ];
+
+        // every family is observed once, unless it is too small
+        for (min_family_size, bases) in [(1, vec![(b'A', 1), (b'C', 1)]), (2, vec![(b'A', 1)])] {
+            let umi_tag = Some(String::from("RX"));
+            let settings = AnalysisSettings { umi_tag, min_family_size, ..AnalysisSettings::default() };
+            let calculator = Calculator::new(AlnQualityReqs::default(), settings, QcRules::default());
+            let result = analyse(&calculator, "umi_families", &alignments, &[(0, 4000)]);
+
+            assert_eq!(result.family_sizes.iter().sorted().collect_vec(), [&1, &3]);
+            assert_eq!(result.base_counts[5].most_common_ordered(), bases);
+            assert_eq!(result.coverage[5], bases.len());
+        }
+    }
+
+    #[test]
+    fn ends_extended_by_soft_clips() {
+        let ref_seq = Seq::new(String::from("ref"), b"ACGT".repeat(1000));
+        let mut alignments = Vec::new();
+        for i in 0..5 {
+            // two reads reach further beyond the start, and two reads beyond the end
+            let leading = if i < 2 { 6 } else { 4 };
+            alignments.push(init_clipped_alignment(format!("start{i}").as_bytes(), 0, (leading, 20, 0), 0));
+            let trailing = if i < 2 { 3 } else { 0 };
+            alignments.push(init_clipped_alignment(format!("end{i}").as_bytes(), 3980, (0, 20, trailing), 0));
+        }
+        // clipped reads not aligned to an end of the reference are ignored
+        alignments.push(init_clipped_alignment(b"inner", 100, (10, 20, 10), 0));
+
+        // the bases are added, as long as enough reads agree
+        for (min_extension_reads, (leading, trailing)) in [(3, (4, 0)), (2, (6, 3))] {
+            let settings = AnalysisSettings { extend_ends: true, min_extension_reads, ..AnalysisSettings::default() };
+            let calculator = Calculator::new(AlnQualityReqs::default(), settings, QcRules::default());
+            let result = analyse(&calculator, "extended_ends", &alignments, &[(0, 4000)]);
+            let consensus = calculator.build_consensus(ref_seq.clone(), "ref", &[], "extended_ends.bam", result);
+
+            let expected = [vec![b'T'; leading], ref_seq.get_sequence().clone(), vec![b'G'; trailing]].concat();
+            assert_eq!(consensus.get_end_extensions(), (leading, trailing));
+            assert_eq!(consensus.get_consensus_seq().get_sequence(), &expected);
+        }
+    }
+
+    #[test]
+    fn regions_with_extended_ends() {
+        let settings = AnalysisSettings {
+            outside_regions: OutsideRegions::Trim,
+            extend_ends: true,
+            min_extension_reads: 3,
+            ..AnalysisSettings::default()
+        };
+        let calculator = Calculator::new(AlnQualityReqs::default(), settings, QcRules::default());
+        let ref_seq = Seq::new(String::from("ref"), b"ACGT".repeat(1000));
+        let alignments = (0..3)
+            .flat_map(|i| {
+                let (start, end) = (format!("start{i}"), format!("end{i}"));
+                [
+                    init_clipped_alignment(start.as_bytes(), 0, (4, 20, 0), 0),
+                    init_clipped_alignment(end.as_bytes(), 3980, (0, 20, 2), 0),
+                ]
+            })
+            .collect_vec();
+        let region = |start: usize, end: usize| Region::new(format!("{start}-{end}"), String::from("ref"), start, end);
+
+        // the extensions are kept with the ends of the reference, and the positions between the regions are masked
+        let regions = [region(0, 100), region(3900, 4000)];
+        let result = analyse(&calculator, "extended_regions", &alignments, &Region::merge_intervals(&regions));
+        let consensus = calculator.build_consensus(ref_seq.clone(), "ref", &regions, "extended_regions.bam", result);
+        let expected = [b"TTTT", &ref_seq[..100], &[b'N'; 3800], &ref_seq[3900..], b"GG"].concat();
+        assert_eq!(consensus.get_end_extensions(), (4, 2));
+        assert_eq!(consensus.get_consensus_seq().get_sequence(), &expected);
+
+        // without the ends in a region, the consensus is not extended
+        let regions = [region(100, 200)];
+        let result = analyse(&calculator, "inner_region", &alignments, &Region::merge_intervals(&regions));
+        let consensus = calculator.build_consensus(ref_seq.clone(), "ref", &regions, "inner_region.bam", result);
+        assert_eq!(consensus.get_end_extensions(), (0, 0));
+        assert_eq!(consensus.get_consensus_seq().get_sequence(), &ref_seq[100..200]);
+    }
+
+    #[test]
+    fn contig_aliases_parsing() {
+        let content = "# label\tcontig\n\nHXB2 full label\tK03455 \nref2\tchr2\n";
+        let aliases = Calculator::parse_contig_aliases(content, "aliases.tsv");
+
+        assert_eq!(aliases.len(), 2);
+        assert_eq!(aliases["HXB2 full label"], "K03455");
+        assert_eq!(aliases["ref2"], "chr2");
+    }
+
+    #[test]
+    #[should_panic(expected = "line 2 of aliases.tsv")]
+    fn contig_aliases_malformed() {
+        Calculator::parse_contig_aliases("ref1\tchr1\nref2 chr2\n", "aliases.tsv");
+    }
+
+    #[test]
+    fn contig_resolution_order() {
+        let header = init_header(&[("HXB2 full", 4), ("HXB2", 4), ("K03455", 4), ("other", 4)]);
+        let ref_seq = Seq::new(String::from("HXB2 full"), b"ACGT".to_vec());
+        let first_word = Seq::new(String::from("other description"), b"ACGT".to_vec());
+
+        // the full label precedes its first word
+        assert_eq!(init_calculator(&[]).resolve_contig(&ref_seq, &header), Ok(Some(0)));
+        assert_eq!(init_calculator(&[]).resolve_contig(&first_word, &header), Ok(Some(3)));
+
+        // an alias precedes the label, for the full label or its first word
+        assert_eq!(init_calculator(&[("HXB2 full", "K03455")]).resolve_contig(&ref_seq, &header), Ok(Some(2)));
+        assert_eq!(init_calculator(&[("HXB2", "K03455")]).resolve_contig(&ref_seq, &header), Ok(Some(2)));
+
+        // an alias to a missing contig does not fall back to the label
+        assert_eq!(init_calculator(&[("HXB2 full", "missing")]).resolve_contig(&ref_seq, &header), Ok(None));
+
+        // references read via an index are labelled by the first word, which an alias of the full label applies to
+        let indexed = Seq::new(String::from("HXB2"), b"ACGT".to_vec());
+        assert_eq!(init_calculator(&[]).resolve_contig(&indexed, &header), Ok(Some(1)));
+        assert_eq!(init_calculator(&[("HXB2 full", "K03455")]).resolve_contig(&indexed, &header), Ok(Some(2)));
+    }
+
+    #[test]
+    fn contig_length_mismatch() {
+        let header = init_header(&[("HXB2", 5)]);
+        let ref_seq = Seq::new(String::from("HXB2"), b"ACGT".to_vec());
+
+        let error = init_calculator(&[]).resolve_contig(&ref_seq, &header).unwrap_err();
+        assert!(error.contains("contig 'HXB2' (5)"));
+    }
+
+    #[test]
+    fn insertions_follow_their_reference_base() {
+        let ref_seq = b"ACGT".repeat(10);
+        let first = InDel::Ins(Insertion::new(1, b"TT".to_vec()));
+        let deletion = InDel::Del(Deletion::new(4, 6));
+        let last = InDel::Ins(Insertion::new(39, b"GG".to_vec()));
+
+        let indels = [(first.clone(), 80), (deletion.clone(), 80), (last.clone(), 80)];
+        let (consensus, applied) = apply_counted_indels(&indels);
+        let expected = [&ref_seq[..2], b"TT", &ref_seq[2..4], &ref_seq[6..], b"GG"].concat();
+        assert_eq!(consensus, expected);
+        assert_eq!(applied, vec![first, deletion.clone(), last]);
+
+        // an insertion after the base preceding a deletion is adjacent to it, so only the deletion is applied
+        let adjacent = InDel::Ins(Insertion::new(3, b"TT".to_vec()));
+        let (consensus, applied) = apply_counted_indels(&[(adjacent, 80), (deletion.clone(), 80)]);
+        assert_eq!(consensus, [&ref_seq[..4], &ref_seq[6..]].concat());
+        assert_eq!(applied, vec![deletion]);
+    }
+
+    #[test]
+    fn indel_interference() {
+        let ref_seq = b"ACGT".repeat(10);
+        let del = |start, stop| InDel::Del(Deletion::new(start, stop));
+        let ins = |position, seq: &[u8]| InDel::Ins(Insertion::new(position, seq.to_vec()));
+
+        // indels are applied from back to front, and skipped if they overlap or neighbour an applied indel,
+        // so of overlapping deletions, the one further back is applied, even with fewer observations
+        let indels = [
+            (del(20, 24), 80),
+            (del(18, 22), 90),
+            (del(12, 14), 80),
+            (del(10, 12), 80),
+            (ins(5, b"TT"), 80),
+            (ins(4, b"C"), 80),
+        ];
+        let (consensus, applied) = apply_counted_indels(&indels);
+
+        assert_eq!(applied, vec![ins(5, b"TT"), del(12, 14), del(20, 24)]);
+        let expected = [&ref_seq[..6], b"TT", &ref_seq[6..12], &ref_seq[14..20], &ref_seq[24..]].concat();
+        assert_eq!(consensus, expected);
+    }
+
+    #[test]
+    fn split_reads_are_observed_once() {
+        // overlapping reads without supplementary alignments are all observed
+        let calculator = init_calculator(&[]);
+        let unsplit = [init_alignment(b"a", 0, 20, 0), init_alignment(b"b", 5, 20, 0)];
+        let unsplit = analyse(&calculator, "unsplit", &unsplit, &[(0, 4000)]);
+        assert_eq!(unsplit.coverage[2], 1);
+        assert_eq!(unsplit.coverage[10], 2);
+        assert_eq!(unsplit.coverage[22], 1);
+
+        // the overlapping parts of a split read are a single observation
+        let split = analyse(
+            &calculator,
+            "split",
+            &[init_alignment(b"a", 0, 20, 0), init_alignment(b"s", 10, 20, 0), init_alignment(b"s", 15, 10, 0x800)],
+            &[(0, 4000)],
+        );
+        assert_eq!(split.coverage[12], 2);
+        assert_eq!(split.coverage[17], 2);
+        assert_eq!(split.coverage[27], 1);
+        assert_eq!(split.read_accounting.total(), 2);
+    }
+
+    #[test]
+    fn reads_are_counted_like_read_names() {
+        // 1-based `SA` entries for the parts of split reads
+        let split = |pos: i64| format!("ref,{},+,100M,60,0;", pos + 1);
+        let mut state: u64 = 7;
+        let mut random = |n: i64| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) as i64 % n
+        };
+        let mut alignments = Vec::new();
+        let mut add = |name: &str, pos: i64, flags: u16, mapq: u8, mate_pos: Option<i64>, split: Option<String>| {
+            let mut rec = init_alignment(name.as_bytes(), pos, 100, flags);
+            rec.set_mapq(mapq);
+            if let Some(mate_pos) = mate_pos {
+                rec.set_mtid(0);
+                rec.set_mpos(mate_pos);
+            }
+            if let Some(split) = split {
+                rec.push_aux(b"SA", Aux::String(&split)).unwrap();
+            }
+            alignments.push(rec);
+        };
+
+        // single reads, pairs, split reads and secondary alignments, with their other parts before or after them
+        for i in 0..600 {
+            let name = format!("read{i}");
+            let (pos, other_pos) = (random(3900), random(3900));
+            let (mapq, other_mapq) = ([5, 60][random(2) as usize], [5, 60][random(2) as usize]);
+            match i % 6 {
+                0 => add(&name, pos, 0, mapq, None, None),
+                1 => {
+                    add(&name, pos, 0x41, mapq, Some(other_pos), None);
+                    add(&name, other_pos, 0x91, other_mapq, Some(pos), None);
+                }
+                2 => {
+                    let split_pos = random(3900);
+                    add(&name, pos, 0x41, mapq, Some(other_pos), Some(split(split_pos)));
+                    add(&name, other_pos, 0x91, other_mapq, Some(pos), None);
+                    add(&name, split_pos, 0x941, 60, Some(other_pos), Some(split(pos)));
+                }
+                3 => {
+                    add(&name, pos, 0, mapq, None, Some(split(other_pos)));
+                    add(&name, other_pos, 0x800, other_mapq, None, Some(split(pos)));
+                }
+                4 => {
+                    add(&name, pos, 0, mapq, None, None);
+                    add(&name, other_pos, 0x100, other_mapq, None, None);
+                }
+                // the other parts are aligned to another contig
+                _ => {
+                    let flags = [0x100, 0x800][random(2) as usize];
+                    add(&name, pos, flags, mapq, None, Some(String::from("chr2,1,+,100M,60,0;")));
+                }
+            }
+        }
+
+        let calculator = Calculator::new(
+            AlnQualityReqs { min_mapq: 20, ..AlnQualityReqs::default() },
+            AnalysisSettings::default(),
+            QcRules::default(),
+        );
+        let regions = vec![(100, 1500), (1700, 1750), (2500, 4000)];
+        for (file_name, intervals) in [("whole", vec![(0, 4000)]), ("regions", regions)] {
+            // a read is accepted, if any of its primary or supplementary alignments within the intervals is accepted
+            let mut reads: HashMap<&[u8], bool> = HashMap::new();
+            for rec in alignments.iter().filter(|rec| !rec.is_secondary()) {
+                let (pos, end_pos) = (rec.pos() as usize, rec.cigar().end_pos() as usize);
+                if intervals.iter().any(|(start, end)| pos < *end && end_pos > *start) {
+                    *reads.entry(rec.qname()).or_default() |= rec.mapq() >= 20;
+                }
+            }
+
+            let read_accounting = analyse(&calculator, file_name, &alignments, &intervals).read_accounting;
+            assert_eq!(read_accounting.total(), reads.len());
+            assert_eq!(read_accounting.get_accepted(), reads.values().filter(|accepted| **accepted).count());
+        }
+    }
+}
@@ -1,28 +1,150 @@
 //! This module is responsible for parsing CLI arguments.
 
-use clap::Parser;
-use super::settings::AlnQualityReqs;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, ValueEnum};
+use super::data::sample::Sample;
+use super::settings::{AlnQualityReqs, AnalysisSettings, Preset, QcRules};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Args {
-    /// Path to a reference sequence.
+    /// Path to a reference sequence in FASTA format, which may be gzip or bgzip compressed.
+    /// If a `.fai` index exists, only sequences of contigs present in the alignment files are read.
     pub ref_path: String,
 
-    /// Path to a sorted SAM/BAM file containing reads that were aligned against the reference.
-    pub aln_path: String,
+    /// Paths to sorted SAM/BAM files containing reads that were aligned against the reference.
+    /// Every file is treated as a separate sample, named after the file stem.
+    #[arg(required_unless_present = "sample_sheet")]
+    pub aln_paths: Vec<String>,
 
-    /// Optionally, an out path. Defaults to stdout.
+    /// A sample sheet with a sample ID and a path to a sorted SAM/BAM file per line.
+    /// IDs and paths may be separated by tabs, commas or whitespace.
+    #[arg(short = 'S', long)]
+    pub sample_sheet: Option<String>,
+
+    /// Optionally, an out path. Defaults to stdout. Only used when processing a single sample,
+    /// without grouping reads by tag.
     #[arg(short, long, default_value_t = String::from("-"))]
     pub out_path: String,
 
+    /// Directory for per-sample consensus FASTAs and reports, when processing multiple samples,
+    /// or grouping reads by tag.
+    #[arg(short = 'd', long, default_value_t = String::from("."))]
+    pub out_dir: String,
+
+    /// Number of samples to process in parallel. Defaults to one per CPU.
+    #[arg(short, long, default_value_t = 0)]
+    pub threads: usize,
+
+    /// Format of the amino acid mutation tables, which are written if a CDS annotation is passed.
+    #[arg(long, value_enum, default_value_t = TableFormat::Tsv)]
+    pub aa_mutations_format: TableFormat,
+
+    /// Write the minor variants next to the consensus, as `<sample id>.minor_variants.tsv` and `<sample id>.minor_variants.vcf`.
+    /// Like the consensus, existing files are not overwritten.
+    #[arg(long)]
+    pub minor_variants: bool,
+
+    /// Write the diversity of the observed bases per position and per window next to the consensus,
+    /// as `<sample id>.diversity.tsv` and `<sample id>.diversity_windows.tsv`.
+    #[arg(long)]
+    pub diversity: bool,
+
+    /// Number of positions per window of the summarized diversity.
+    #[arg(long, default_value_t = 100)]
+    pub diversity_window: usize,
+
+    /// Write MultiQC custom content: a general statistics table, and line graphs of the coverage and alignment statistics.
+    /// Files are written into the output directory, or next to the out path when processing a single sample,
+    /// unless the consensus is written to stdout.
+    #[arg(long)]
+    pub multiqc: bool,
+
+    /// Write a self-contained HTML report of the run, with plots of the coverage and alignment statistics,
+    /// as `accord_report.html` into the output directory, or next to the out path when processing a single sample,
+    /// unless the consensus is written to stdout.
+    #[arg(long)]
+    pub html: bool,
+
+    /// A TOML file with alignment quality settings, with a key per setting, e.g. `min_mapq = 20`,
+    /// and optionally a `preset`. Settings passed as flags take precedence.
+    #[arg(long)]
+    pub config: Option<String>,
+
+    /// A named preset of alignment quality settings. Overrides a preset named in the config file.
+    /// Settings from the config file and flags take precedence.
+    #[arg(long, value_enum)]
+    pub preset: Option<Preset>,
+
     /// Alignment quality settings for consensus generation.
     #[command(flatten)]
     pub aln_reqs: AlnQualityReqs,
+
+    /// Settings for analysing aligned reads.
+    #[command(flatten)]
+    pub analysis_settings: AnalysisSettings,
+
+    /// Rules for the quality control of consensuses.
+    #[command(flatten)]
+    pub qc_rules: QcRules,
+}
+
+/// Output formats for tables.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    Tsv,
+    Json,
+}
+
+impl TableFormat {
+    /// File extension for the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TableFormat::Tsv => "tsv",
+            TableFormat::Json => "json",
+        }
+    }
 }
 
 impl Args {
     pub fn parse_args() -> Self {
-        Self::parse()
+        //! Parse the arguments. Alignment quality settings are taken from the defaults, the preset,
+        //! the config file, and the flags, in ascending order of precedence.
+        //! In long-read mode, `min_observations` is lowered to the long-read default,
+        //! unless it is set by a flag, the preset, or the config file.
+        let matches = Self::command().get_matches();
+        let mut args = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        let is_flag = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        let mut sets_min_observations = is_flag("min_observations");
+        if args.config.is_some() || args.preset.is_some() {
+            let (base, base_sets_min_observations) = AlnQualityReqs::load_settings(args.preset, args.config.as_deref());
+            args.aln_reqs = args.aln_reqs.merged_with(base, is_flag);
+            sets_min_observations |= base_sets_min_observations;
+        }
+        args.aln_reqs = args.aln_reqs.with_long_read_default(sets_min_observations);
+
+        args
     }
-}
\ No newline at end of file
+
+    /// Whether multiple samples are to be processed, and output should be written per sample.
+    /// Grouping reads by tag yields a sample per group.
+    pub fn is_batch(&self) -> bool {
+        self.sample_sheet.is_some() || self.aln_paths.len() > 1 || self.analysis_settings.group_tag.is_some()
+    }
+
+    /// Get the samples to process, from the sample sheet and the passed alignment paths.
+    pub fn samples(&self) -> Vec<Sample> {
+        let mut samples = match &self.sample_sheet {
+            Some(sheet_path) => Sample::from_sheet_file(sheet_path),
+            None => Vec::new(),
+        };
+
+        for aln_path in &self.aln_paths {
+            samples.push(Sample::from_path(aln_path.clone()));
+        }
+
+        Sample::check_unique_ids(&samples);
+        samples
+    }
+}
@@ -1,6 +1,14 @@
 //! The `data` module contains submodules for handling sequence data, etc.
 
+pub mod accounting;
+pub mod annotation;
 pub mod consensus;
+pub mod diversity;
 pub mod indel;
+pub mod mutation;
+pub mod qc;
+pub mod region;
+pub mod sample;
 pub mod seq;
 pub mod stats;
+pub mod variant;
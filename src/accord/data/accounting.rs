@@ -0,0 +1,133 @@
+//! This module provides the `ReadAccounting` struct, counting the reads accepted for the consensus calculation,
+//! and those rejected per reason.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use pyo3::{pyclass, pymethods};
+
+use super::super::flags::flag_names;
+
+/// Reason for rejecting an alignment. Reasons are checked in the order of the variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RejectionReason {
+    /// The alignment has a prohibited flag, i.e. the lowest one of those it has.
+    ProhibitedFlag(u16),
+
+    /// The alignment lacks a mandatory flag, i.e. the lowest one of those it lacks.
+    MissingFlag(u16),
+
+    /// The mapping quality is below the minimum.
+    LowMapq,
+
+    /// Too few bases of the read are aligned.
+    ShortAlignment,
+
+    /// The edit distance is too high, relative to the aligned bases, or unknown.
+    EditDistance,
+
+    /// The alignment score is too low, or unknown.
+    AlignmentScore,
+
+    /// Too many bases of the read are soft-clipped.
+    SoftClipping,
+
+    /// The read is not aligned as a proper pair.
+    ImproperPair,
+
+    /// The insert size is out of bounds, or unknown.
+    InsertSize,
+}
+
+impl Display for RejectionReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RejectionReason::ProhibitedFlag(flag) => write!(f, "prohibited_flag:{}", flag_names(*flag)),
+            RejectionReason::MissingFlag(flag) => write!(f, "missing_flag:{}", flag_names(*flag)),
+            RejectionReason::LowMapq => write!(f, "low_mapq"),
+            RejectionReason::ShortAlignment => write!(f, "short_alignment"),
+            RejectionReason::EditDistance => write!(f, "edit_distance"),
+            RejectionReason::AlignmentScore => write!(f, "alignment_score"),
+            RejectionReason::SoftClipping => write!(f, "soft_clipping"),
+            RejectionReason::ImproperPair => write!(f, "improper_pair"),
+            RejectionReason::InsertSize => write!(f, "insert_size"),
+        }
+    }
+}
+
+/// Number of distinct reads accepted for the consensus calculation, and rejected per reason.
+///
+/// A read is accepted, if any of its primary or supplementary alignments is accepted, e.g. one of its mates.
+/// Otherwise, it is rejected for the reason of the first of these alignments to be rejected. So every seen read
+/// is counted exactly once. Secondary alignments are not counted.
+/// The reads are counted while the pileup passes over them, without remembering their names.
+#[derive(Debug, Clone, Default)]
+#[pyclass]
+pub struct ReadAccounting {
+    /// Number of reads with an alignment suitable for the consensus calculation.
+    #[pyo3(get)]
+    accepted: usize,
+
+    /// Number of rejected reads per reason.
+    rejected: BTreeMap<RejectionReason, usize>,
+}
+
+impl ReadAccounting {
+    /// Count a read as accepted, or as rejected for the passed reason.
+    pub fn count(&mut self, rejection: Option<RejectionReason>) {
+        match rejection {
+            Some(reason) => *self.rejected.entry(reason).or_default() += 1,
+            None => self.accepted += 1,
+        }
+    }
+
+    /// Total number of counted reads.
+    pub fn total(&self) -> usize {
+        self.accepted + self.total_rejected()
+    }
+
+    pub fn get_accepted(&self) -> usize { self.accepted }
+    pub fn get_rejected(&self) -> &BTreeMap<RejectionReason, usize> { &self.rejected }
+}
+
+#[pymethods]
+impl ReadAccounting {
+    /// Total number of rejected reads.
+    #[getter]
+    pub fn total_rejected(&self) -> usize {
+        self.rejected.values().sum()
+    }
+
+    /// Number of rejected reads per reason, e.g. `low_mapq`, or `prohibited_flag:DUP`.
+    #[getter]
+    #[pyo3(name = "rejected")]
+    fn py_rejected(&self) -> BTreeMap<String, usize> {
+        self.rejected.iter().map(|(reason, count)| (reason.to_string(), *count)).collect()
+    }
+
+    fn __repr__(&self) -> String {
+        let rejected = self.rejected.iter().map(|(reason, count)| format!("{reason}={count}")).collect::<Vec<String>>();
+        format!("ReadAccounting(accepted={}, rejected={{{}}})", self.accepted, rejected.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_are_counted_per_reason() {
+        let mut accounting = ReadAccounting::default();
+        accounting.count(None);
+        accounting.count(Some(RejectionReason::ProhibitedFlag(0x400)));
+        accounting.count(Some(RejectionReason::ProhibitedFlag(0x4)));
+        accounting.count(Some(RejectionReason::LowMapq));
+        accounting.count(Some(RejectionReason::LowMapq));
+
+        assert_eq!(accounting.accepted, 1);
+        assert_eq!(accounting.total_rejected(), 4);
+        assert_eq!(accounting.total(), 5);
+        assert_eq!(accounting.rejected[&RejectionReason::LowMapq], 2);
+        assert_eq!(RejectionReason::ProhibitedFlag(0x400).to_string(), "prohibited_flag:DUP");
+    }
+}
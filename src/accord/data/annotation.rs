@@ -0,0 +1,352 @@
+//! This module provides the `Cds` struct, describing annotated coding sequences, and parsers for
+//! reading them from GFF3 and BED files.
+
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+
+use pyo3::types::PyType;
+use pyo3::{pyclass, pymethods, Bound, PyResult};
+
+use super::indel::InDel;
+use super::seq::ParseError;
+use crate::accord::utils::open_file;
+
+/// An annotated coding sequence (CDS) on a contig.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[pyclass(eq)]
+pub struct Cds {
+    /// Name of the CDS, e.g. the gene it encodes.
+    #[pyo3(get)]
+    name: String,
+
+    /// Name of the contig the CDS is located on.
+    #[pyo3(get)]
+    contig: String,
+
+    /// Zero-based, half-open `(start, end)` ranges of the CDS segments, in ascending order.
+    #[pyo3(get)]
+    segments: Vec<(usize, usize)>,
+
+    /// Whether the CDS is located on the reverse strand.
+    #[pyo3(get)]
+    reverse: bool,
+}
+
+impl Cds {
+    pub fn new(name: String, contig: String, mut segments: Vec<(usize, usize)>, reverse: bool) -> Self {
+        segments.sort();
+        Self { name, contig, segments, reverse }
+    }
+
+    /// Read CDS features from a GFF3 or BED file, depending on the file extension.
+    /// Files with a `.gz` suffix are decompressed transparently.
+    pub fn from_file(file: &String) -> Vec<Self> {
+        match Self::parse_file(file) {
+            Ok(features) => features,
+            Err(e) => panic!("Unable to parse annotation file {file}: {e}"),
+        }
+    }
+
+    fn parse_file(file: &String) -> Result<Vec<Self>, ParseError> {
+        // look at the extension in front of a compression suffix
+        let path = Path::new(file);
+        let path = match path.extension() {
+            Some(ext) if ext == "gz" => Path::new(path.file_stem().unwrap_or_default()),
+            _ => path,
+        };
+        let ext = path.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+
+        match ext.as_str() {
+            "gff" | "gff3" => Self::parse_gff3(open_file(file)),
+            "bed" => Self::parse_bed(open_file(file)),
+            _ => panic!("Unknown annotation format for {file}, expected a .gff, .gff3 or .bed file."),
+        }
+    }
+
+    /// Parse CDS features from GFF3 formatted data.
+    ///
+    /// Only features of type `CDS` are considered. Lines sharing an `ID` (or lacking one, a `Parent`)
+    /// on the same contig are joined into one CDS with multiple segments.
+    /// The name is taken from the `gene`, `Name`, `ID` or `Parent` attribute, in that order.
+    pub fn parse_gff3<R: BufRead>(reader: R) -> Result<Vec<Self>, ParseError> {
+        let mut features: Vec<Self> = Vec::new();
+        let mut feature_idx = HashMap::new();
+
+        for (i, line) in reader.lines().enumerate() {
+            let line_no = i + 1;
+            let line = line.map_err(|e| ParseError::new(line_no, format!("Unable to read line: {e}")))?;
+            let line = line.trim_end();
+
+            // sequence data may follow the annotations
+            if line.starts_with("##FASTA") {
+                break;
+            }
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields = line.split('\t').collect::<Vec<&str>>();
+            if fields.len() != 9 {
+                let msg = format!("Expected 9 tab separated columns, found {}", fields.len());
+                return Err(ParseError::new(line_no, msg));
+            }
+            if fields[2] != "CDS" {
+                continue;
+            }
+
+            let contig = fields[0].to_string();
+            let start = Self::parse_coordinate(fields[3], line_no)?;
+            let end = Self::parse_coordinate(fields[4], line_no)?;
+            if start == 0 || start > end {
+                let msg = format!("Invalid feature coordinates {start}-{end}");
+                return Err(ParseError::new(line_no, msg));
+            }
+            let reverse = fields[6] == "-";
+
+            let attributes = fields[8]
+                .split(';')
+                .filter_map(|attr| attr.split_once('='))
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .collect::<HashMap<&str, &str>>();
+            let name = ["gene", "Name", "ID", "Parent"]
+                .iter()
+                .find_map(|key| attributes.get(key))
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| format!("{contig}:{start}-{end}"));
+
+            // GFF3 coordinates are one-based and inclusive
+            let segment = (start - 1, end);
+            let group = attributes.get("ID").or_else(|| attributes.get("Parent"));
+            let key = group.map(|id| (contig.clone(), id.to_string()));
+            match key.as_ref().and_then(|key| feature_idx.get(key)) {
+                Some(&idx) => {
+                    let feature: &mut Self = &mut features[idx];
+                    feature.segments.push(segment);
+                    feature.segments.sort();
+                }
+                None => {
+                    if let Some(key) = key {
+                        feature_idx.insert(key, features.len());
+                    }
+                    features.push(Self::new(name, contig, vec![segment], reverse));
+                }
+            }
+        }
+
+        Ok(features)
+    }
+
+    /// Parse CDS features from BED formatted data.
+    ///
+    /// Every record is considered a CDS. For BED12 records, the coding segments are the blocks
+    /// within the thick range. Records without a name are named after their location.
+    pub fn parse_bed<R: BufRead>(reader: R) -> Result<Vec<Self>, ParseError> {
+        let mut features = Vec::new();
+
+        for (i, line) in reader.lines().enumerate() {
+            let line_no = i + 1;
+            let line = line.map_err(|e| ParseError::new(line_no, format!("Unable to read line: {e}")))?;
+            let line = line.trim_end();
+
+            let is_header = ["#", "track", "browser"].iter().any(|prefix| line.starts_with(prefix));
+            if line.trim().is_empty() || is_header {
+                continue;
+            }
+
+            let fields = line.split('\t').collect::<Vec<&str>>();
+            if fields.len() < 3 {
+                let msg = format!("Expected at least 3 tab separated columns, found {}", fields.len());
+                return Err(ParseError::new(line_no, msg));
+            }
+
+            let contig = fields[0].to_string();
+            let start = Self::parse_coordinate(fields[1], line_no)?;
+            let end = Self::parse_coordinate(fields[2], line_no)?;
+            if start >= end {
+                let msg = format!("Invalid feature coordinates {start}-{end}");
+                return Err(ParseError::new(line_no, msg));
+            }
+
+            let name = match fields.get(3) {
+                Some(name) if !name.is_empty() && *name != "." => name.to_string(),
+                _ => format!("{contig}:{}-{end}", start + 1),
+            };
+            let reverse = fields.get(5).is_some_and(|strand| *strand == "-");
+
+            let segments = if fields.len() >= 12 {
+                Self::parse_bed_blocks(&fields, start, line_no)?
+            } else {
+                vec![(start, end)]
+            };
+
+            features.push(Self::new(name, contig, segments, reverse));
+        }
+
+        Ok(features)
+    }
+
+    /// Compute the coding segments of a BED12 record, i.e. its blocks clipped to the thick range.
+    fn parse_bed_blocks(fields: &[&str], start: usize, line_no: usize) -> Result<Vec<(usize, usize)>, ParseError> {
+        let thick_start = Self::parse_coordinate(fields[6], line_no)?;
+        let thick_end = Self::parse_coordinate(fields[7], line_no)?;
+
+        let parse_list = |field: &str| {
+            field
+                .split(',')
+                .filter(|value| !value.is_empty())
+                .map(|value| Self::parse_coordinate(value, line_no))
+                .collect::<Result<Vec<usize>, ParseError>>()
+        };
+        let block_sizes = parse_list(fields[10])?;
+        let block_starts = parse_list(fields[11])?;
+        if block_sizes.len() != block_starts.len() {
+            return Err(ParseError::new(line_no, String::from("Number of block sizes and block starts differ")));
+        }
+
+        let segments = block_starts
+            .iter()
+            .zip(block_sizes.iter())
+            .map(|(block_start, block_size)| {
+                let block_start = start + block_start;
+                let block_end = block_start + block_size;
+                (block_start.max(thick_start), block_end.min(thick_end))
+            })
+            .filter(|(seg_start, seg_end)| seg_start < seg_end)
+            .collect();
+
+        Ok(segments)
+    }
+
+    fn parse_coordinate(field: &str, line_no: usize) -> Result<usize, ParseError> {
+        match field.trim().parse() {
+            Ok(coordinate) => Ok(coordinate),
+            Err(_) => Err(ParseError::new(line_no, format!("Invalid coordinate '{field}'"))),
+        }
+    }
+
+    /// Number of reference bases within the CDS, that are affected by an indel.
+    ///
+    /// For insertions this is the length of the inserted sequence, if it is located within the CDS.
+    /// For deletions, it is the number of deleted bases, that belong to the CDS.
+    pub fn affected_bases(&self, indel: &InDel) -> usize {
+        match indel {
+            InDel::Ins(_) => {
+                if self.contains(indel.get_start()) { indel.len() } else { 0 }
+            }
+            InDel::Del(_) => {
+                let (del_start, del_stop) = (indel.get_start(), indel.get_stop());
+                self.segments
+                    .iter()
+                    .map(|(start, end)| {
+                        let overlap_start = del_start.max(*start);
+                        let overlap_end = del_stop.min(*end);
+                        overlap_end.saturating_sub(overlap_start)
+                    })
+                    .sum()
+            }
+        }
+    }
+
+    pub fn get_name(&self) -> &String { &self.name }
+    pub fn get_contig(&self) -> &String { &self.contig }
+    pub fn get_segments(&self) -> &Vec<(usize, usize)> { &self.segments }
+    pub fn is_reverse(&self) -> bool { self.reverse }
+}
+
+#[pymethods]
+impl Cds {
+    #[new]
+    #[pyo3(signature = (name, contig, segments, reverse = false))]
+    fn py_new(name: String, contig: String, segments: Vec<(usize, usize)>, reverse: bool) -> Self {
+        Self::new(name, contig, segments, reverse)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "from_file")]
+    fn py_from_file(_cls: &Bound<'_, PyType>, file: String) -> PyResult<Vec<Self>> {
+        Ok(Self::parse_file(&file)?)
+    }
+
+    /// Whether a position on the contig belongs to the CDS.
+    pub fn contains(&self, pos: usize) -> bool {
+        self.segments.iter().any(|(start, end)| (*start..*end).contains(&pos))
+    }
+
+    /// Whether an indel shifts the reading frame of the CDS.
+    pub fn is_frameshifted_by(&self, indel: &InDel) -> bool {
+        !self.affected_bases(indel).is_multiple_of(3)
+    }
+
+    /// Number of coding bases, i.e. the summed length of all segments.
+    fn __len__(&self) -> usize {
+        self.segments.iter().map(|(start, end)| end - start).sum()
+    }
+
+    fn __repr__(&self) -> String {
+        let strand = if self.reverse { '-' } else { '+' };
+        format!("Cds(name='{}', contig='{}', segments={:?}, strand='{strand}')", self.name, self.contig, self.segments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accord::data::indel::{Deletion, Insertion};
+
+    #[test]
+    fn gff3_joins_segments() {
+        let gff = "##gff-version 3\n\
+            chr1\t.\tgene\t1\t90\t.\t+\t.\tID=gene1\n\
+            chr1\t.\tCDS\t61\t90\t.\t+\t0\tID=cds1;Parent=gene1;gene=gag\n\
+            chr1\t.\tCDS\t1\t30\t.\t+\t0\tID=cds1;Parent=gene1;gene=gag\n\
+            chr1\t.\tCDS\t100\t120\t.\t-\t0\tName=env\n\
+            ##FASTA\n>chr1\nACGT\n";
+        let features = Cds::parse_gff3(gff.as_bytes()).unwrap();
+
+        assert_eq!(features.len(), 2);
+        assert_eq!(features[0].name, "gag");
+        assert_eq!(features[0].segments, vec![(0, 30), (60, 90)]);
+        assert!(!features[0].reverse);
+        assert_eq!(features[1].name, "env");
+        assert!(features[1].reverse);
+    }
+
+    #[test]
+    fn gff3_malformed() {
+        let err = Cds::parse_gff3("chr1\t.\tCDS\t1\t30\n".as_bytes()).unwrap_err();
+        assert_eq!(err.get_line(), 1);
+    }
+
+    #[test]
+    fn bed_records() {
+        let bed = "track name=cds\n\
+            chr1\t0\t30\tgag\t0\t+\n\
+            chr1\t100\t200\n\
+            chr1\t0\t100\tpol\t0\t-\t10\t90\t0\t2\t20,30,\t0,70,\n";
+        let features = Cds::parse_bed(bed.as_bytes()).unwrap();
+
+        assert_eq!(features.len(), 3);
+        assert_eq!(features[0].segments, vec![(0, 30)]);
+        assert_eq!(features[1].name, "chr1:101-200");
+        assert_eq!(features[2].segments, vec![(10, 20), (70, 90)]);
+        assert!(features[2].reverse);
+    }
+
+    #[test]
+    fn cds_frameshifts() {
+        let cds = Cds::new("gag".into(), "chr1".into(), vec![(0, 30), (60, 90)], false);
+
+        let ins = InDel::Ins(Insertion::new(10, b"AC".to_vec()));
+        let in_frame_ins = InDel::Ins(Insertion::new(10, b"ACG".to_vec()));
+        let intergenic_ins = InDel::Ins(Insertion::new(40, b"AC".to_vec()));
+        assert!(cds.is_frameshifted_by(&ins));
+        assert!(!cds.is_frameshifted_by(&in_frame_ins));
+        assert!(!cds.is_frameshifted_by(&intergenic_ins));
+
+        // only two of the three deleted bases are coding
+        let boundary_del = InDel::Del(Deletion::new(28, 31));
+        assert_eq!(cds.affected_bases(&boundary_del), 2);
+        assert!(cds.is_frameshifted_by(&boundary_del));
+    }
+}
@@ -1,12 +1,20 @@
 //! This module provides the `Consensus` struct, summarizing the result of a consensus calculation.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
+use counter::Counter;
 use pyo3::{pyclass, pymethods};
 
 use super::super::types::{BaseCounts, InDelCounts, Coverage, ExpandedBaseCounts};
+use super::accounting::ReadAccounting;
+use super::diversity::DiversityTrack;
+use super::indel::{Frameshift, InDel};
+use super::mutation::AaMutation;
+use super::qc::QcStats;
+use super::region::RegionStats;
 use super::seq::Seq;
-use super::stats::{AlnData, AlnStats};
+use super::stats::{AlnDataCounts, AlnStats, FamilyStats};
+use super::variant::{MinorVariant, Substitution};
 
 /// Intermediary results of analysing aligned reads.
 #[derive(Debug, Clone)]
@@ -20,23 +28,84 @@ pub struct AnalysisResult {
     /// Vector with base counts relative to position in reference genome.
     pub base_counts: BaseCounts,
 
+    /// Vector with base counts of reads aligned to the reverse strand, relative to position in reference genome.
+    pub reverse_base_counts: BaseCounts,
+
     /// Map with indel counts.
     pub indel_counts: InDelCounts,
 
-    /// Vector containing data for alignments that were considered in consensus generation.
+    /// Map with indel counts of reads aligned to the reverse strand.
+    pub reverse_indel_counts: InDelCounts,
+
+    /// Counted data of alignments that were considered in consensus generation, one count per alignment record.
+    pub aln_data_counts: AlnDataCounts,
+
+    /// Number of seen reads, that were accepted for consensus generation, and rejected per reason.
     #[pyo3(get)]
-    pub valid_alns: Vec<AlnData>,
+    pub read_accounting: ReadAccounting,
 
-    /// Set of IDs of seen reads, regardless of quality.
-    /// Used for calculating total number of seen reads.
+    /// Vector containing the number of fragments covering the reference genome per base position.
+    /// Unlike `coverage`, overlapping mates of a pair count only once.
     #[pyo3(get)]
-    pub reads_seen: HashSet<String>,
+    pub fragment_coverage: Vec<usize>,
+
+    /// Sizes of the UMI families, that start on the reference sequence.
+    /// Only populated, if reads are collapsed into families.
+    #[pyo3(get)]
+    pub family_sizes: Vec<usize>,
+
+    /// Bases soft-clipped by reads aligned to the start of the reference sequence, ordered outward from the start.
+    /// Only populated, if the ends of the consensus are extended.
+    pub leading_clips: Vec<Vec<u8>>,
+
+    /// Bases soft-clipped by reads aligned to the end of the reference sequence, ordered outward from the end.
+    /// Only populated, if the ends of the consensus are extended.
+    pub trailing_clips: Vec<Vec<u8>>,
 }
 
 
 impl AnalysisResult {
-    pub fn new(coverage: Coverage, base_counts: BaseCounts, indel_counts: InDelCounts, valid_alns: Vec<AlnData>, reads_seen: HashSet<String>) -> Self {
-        Self { coverage, base_counts, indel_counts, valid_alns, reads_seen }
+    pub fn new(
+        coverage: Coverage,
+        base_counts: BaseCounts,
+        reverse_base_counts: BaseCounts,
+        indel_counts: InDelCounts,
+        reverse_indel_counts: InDelCounts,
+    ) -> Self {
+        let fragment_coverage = vec![0; coverage.len()];
+        Self {
+            coverage,
+            fragment_coverage,
+            base_counts,
+            reverse_base_counts,
+            indel_counts,
+            reverse_indel_counts,
+            aln_data_counts: AlnDataCounts::default(),
+            read_accounting: ReadAccounting::default(),
+            family_sizes: Vec::new(),
+            leading_clips: Vec::new(),
+            trailing_clips: Vec::new(),
+        }
+    }
+
+    /// Create a result without any observations, for a reference sequence of length `len`.
+    pub fn empty(len: usize) -> Self {
+        Self::new(
+            vec![0; len],
+            vec![Counter::new(); len],
+            vec![Counter::new(); len],
+            Counter::new(),
+            Counter::new(),
+        )
+    }
+}
+
+#[pymethods]
+impl AnalysisResult {
+    /// Number of seen reads, regardless of quality.
+    #[getter]
+    fn total_reads(&self) -> usize {
+        self.read_accounting.total()
     }
 }
 
@@ -60,58 +129,127 @@ pub struct Consensus {
     #[pyo3(get)]
     aln_stats: AlnStats,
 
+    /// Statistics of the UMI families, if reads were collapsed into families.
+    #[pyo3(get)]
+    family_stats: Option<FamilyStats>,
+
+    /// Coverage statistics and the verdict of the quality control.
+    #[pyo3(get)]
+    qc_stats: QcStats,
+
+    /// Coverage statistics of the regions, if the calculation was restricted to regions.
+    #[pyo3(get)]
+    region_stats: Vec<RegionStats>,
+
     /// Base coverage, relative to the reference sequence.
     #[pyo3(get)]
     coverage: Vec<usize>,
 
+    /// Number of fragments covering each position, relative to the reference sequence.
+    /// Overlapping mates of a pair count only once.
+    #[pyo3(get)]
+    fragment_coverage: Vec<usize>,
+
     /// A mapping from base characters to coverage for the respective base, relative to the reference sequence.
     #[pyo3(get)]
     base_counts: ExpandedBaseCounts,
 
-    // /// Vector containing the applied indels.
-    // #[pyo3(get)]
-    // indels: Vec<InDel>,
+    /// Diversity of the observed bases per position, relative to the reference sequence.
+    #[pyo3(get)]
+    diversity: DiversityTrack,
+
+    /// Vector containing the applied indels.
+    #[pyo3(get)]
+    indels: Vec<InDel>,
+
+    /// Applied indels, that shift the reading frame of an annotated coding sequence.
+    #[pyo3(get)]
+    frameshifts: Vec<Frameshift>,
+
+    /// Amino acid mutations within annotated coding sequences.
+    #[pyo3(get)]
+    aa_mutations: Vec<AaMutation>,
+
+    /// Alleles differing from the consensus, that suffice the minor variant thresholds.
+    #[pyo3(get)]
+    minor_variants: Vec<MinorVariant>,
+
+    /// Positions, in which the consensus base differs from the reference base.
+    #[pyo3(get)]
+    substitutions: Vec<Substitution>,
 
     /// Total number of seen reads, including those that were not considered for consensus generation.
     #[pyo3(get)]
     total_reads: usize,
+
+    /// Number of reads accepted for consensus generation, and rejected per reason.
+    #[pyo3(get)]
+    read_accounting: ReadAccounting,
+
+    /// Number of bases, by which the consensus was extended beyond the start and the end of the reference.
+    #[pyo3(get)]
+    end_extensions: (usize, usize),
 }
 
 #[pymethods]
 impl Consensus {
     #[new]
+    #[allow(clippy::too_many_arguments)]
     pub fn new(ref_seq: Seq,
                aln_path: String,
                consensus_seq: Seq,
                aln_stats: AlnStats,
-               // indels: Vec<InDel>,
+               family_stats: Option<FamilyStats>,
+               qc_stats: QcStats,
+               region_stats: Vec<RegionStats>,
+               diversity: DiversityTrack,
+               indels: Vec<InDel>,
+               frameshifts: Vec<Frameshift>,
+               aa_mutations: Vec<AaMutation>,
+               minor_variants: Vec<MinorVariant>,
+               substitutions: Vec<Substitution>,
+               end_extensions: (usize, usize),
                analysis_result: AnalysisResult) -> Self {
         let coverage = analysis_result.coverage;
+        let fragment_coverage = analysis_result.fragment_coverage;
         let base_counts = Self::expand_base_counts(&analysis_result.base_counts);
-        let total_reads = analysis_result.reads_seen.len();
+        let read_accounting = analysis_result.read_accounting;
+        let total_reads = read_accounting.total();
 
         Self {
             ref_seq,
             aln_path,
             consensus_seq,
             aln_stats,
+            family_stats,
+            qc_stats,
+            region_stats,
             coverage,
+            fragment_coverage,
             base_counts,
-            // indels,
+            diversity,
+            indels,
+            frameshifts,
+            aa_mutations,
+            minor_variants,
+            substitutions,
             total_reads,
+            read_accounting,
+            end_extensions,
         }
     }
 
     /// Number of reads considered in the consensus generation.
+    /// Mates of a pair count as one read, like in `total_reads`. The alignment statistics count every alignment.
     #[getter]
     pub fn valid_reads(&self) -> usize {
-        self.aln_stats.sample_size()
+        self.read_accounting.get_accepted()
     }
 
     /// Number of reads seen, that **were not** considered in the consensus generation.
     #[getter]
     pub fn invalid_reads(&self) -> usize {
-        self.valid_reads() - self.total_reads
+        self.read_accounting.total_rejected()
     }
 
     fn __repr__(&self) -> String {
@@ -143,7 +281,19 @@ impl Consensus {
     pub fn get_aln_path(&self) -> &String { &self.aln_path }
     pub fn get_consensus_seq(&self) -> &Seq { &self.consensus_seq }
     pub fn get_aln_stats(&self) -> &AlnStats{ &self.aln_stats }
+    pub fn get_family_stats(&self) -> Option<&FamilyStats> { self.family_stats.as_ref() }
+    pub fn get_qc_stats(&self) -> &QcStats { &self.qc_stats }
+    pub fn get_region_stats(&self) -> &Vec<RegionStats> { &self.region_stats }
     pub fn get_coverage(&self) -> &Coverage { &self.coverage }
+    pub fn get_fragment_coverage(&self) -> &Coverage { &self.fragment_coverage }
     pub fn get_base_counts(&self) -> &ExpandedBaseCounts { &self.base_counts }
+    pub fn get_diversity(&self) -> &DiversityTrack { &self.diversity }
+    pub fn get_indels(&self) -> &Vec<InDel> { &self.indels }
+    pub fn get_frameshifts(&self) -> &Vec<Frameshift> { &self.frameshifts }
+    pub fn get_aa_mutations(&self) -> &Vec<AaMutation> { &self.aa_mutations }
+    pub fn get_minor_variants(&self) -> &Vec<MinorVariant> { &self.minor_variants }
+    pub fn get_substitutions(&self) -> &Vec<Substitution> { &self.substitutions }
     pub fn get_total_reads(&self) -> usize { self.total_reads }
+    pub fn get_read_accounting(&self) -> &ReadAccounting { &self.read_accounting }
+    pub fn get_end_extensions(&self) -> (usize, usize) { self.end_extensions }
 }
@@ -0,0 +1,208 @@
+//! This module provides the `DiversityTrack` struct, describing the diversity of the aligned reads per position,
+//! and the `DiversityWindow` struct, summarizing the diversity within a window of positions.
+
+use pyo3::{pyclass, pymethods};
+
+use super::super::types::BaseCounts;
+
+/// Per-position diversity of the reads aligned to a reference sequence.
+#[derive(Debug, Clone)]
+#[pyclass]
+pub struct DiversityTrack {
+    /// Number of observed bases per position.
+    #[pyo3(get)]
+    depth: Vec<usize>,
+
+    /// Shannon entropy of the observed bases per position, in bits.
+    /// Ranges from zero for a single observed base to two for four equally frequent bases.
+    #[pyo3(get)]
+    entropy: Vec<f64>,
+
+    /// Frequency of the most frequent base other than the consensus base per position.
+    #[pyo3(get)]
+    minor_allele_frequency: Vec<f64>,
+
+    /// Fraction of observed bases per position, that agree with the consensus base.
+    #[pyo3(get)]
+    support: Vec<f64>,
+}
+
+impl DiversityTrack {
+    /// Compute the diversity per position. Uncovered positions have neither entropy, nor minor alleles or support.
+    ///
+    /// - `base_counts: &BaseCounts`: Base counts per position of the reference sequence.
+    /// - `consensus_bases: &[u8]`: The consensus base for every reference position, before applying indels.
+    pub fn new(base_counts: &BaseCounts, consensus_bases: &[u8]) -> Self {
+        let len = base_counts.len();
+        let mut depth = Vec::with_capacity(len);
+        let mut entropy = Vec::with_capacity(len);
+        let mut minor_allele_frequency = Vec::with_capacity(len);
+        let mut support = Vec::with_capacity(len);
+
+        for (base_counter, consensus_base) in base_counts.iter().zip(consensus_bases) {
+            let total = base_counter.values().sum::<usize>();
+            depth.push(total);
+            if total == 0 {
+                entropy.push(0.0);
+                minor_allele_frequency.push(0.0);
+                support.push(0.0);
+                continue;
+            }
+
+            let total = total as f64;
+            let position_entropy = base_counter
+                .values()
+                .filter(|count| **count > 0)
+                .map(|count| *count as f64 / total)
+                .map(|p| p * (1.0 / p).log2())
+                .sum::<f64>();
+            let minor_count = base_counter
+                .iter()
+                .filter(|(base, _)| *base != consensus_base)
+                .map(|(_, count)| *count)
+                .max()
+                .unwrap_or_default();
+
+            entropy.push(position_entropy);
+            minor_allele_frequency.push(minor_count as f64 / total);
+            support.push(base_counter[consensus_base] as f64 / total);
+        }
+
+        Self { depth, entropy, minor_allele_frequency, support }
+    }
+
+    /// Mean entropy of the covered positions.
+    pub fn mean_entropy(&self) -> f64 {
+        self.covered_mean(&self.entropy, 0..self.depth.len())
+    }
+
+    /// Mean minor allele frequency of the covered positions.
+    pub fn mean_minor_allele_frequency(&self) -> f64 {
+        self.covered_mean(&self.minor_allele_frequency, 0..self.depth.len())
+    }
+
+    /// Mean of a track over the covered positions within a range, or zero if none of them is covered.
+    fn covered_mean(&self, track: &[f64], range: std::ops::Range<usize>) -> f64 {
+        let values = range.filter(|pos| self.depth[*pos] > 0).map(|pos| track[pos]).collect::<Vec<f64>>();
+        if values.is_empty() {
+            return 0.0;
+        }
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+
+    pub fn get_depth(&self) -> &Vec<usize> { &self.depth }
+    pub fn get_entropy(&self) -> &Vec<f64> { &self.entropy }
+    pub fn get_minor_allele_frequency(&self) -> &Vec<f64> { &self.minor_allele_frequency }
+    pub fn get_support(&self) -> &Vec<f64> { &self.support }
+}
+
+#[pymethods]
+impl DiversityTrack {
+    /// Summarize the diversity within consecutive, non-overlapping windows of `size` positions.
+    /// The last window may be shorter.
+    pub fn windows(&self, size: usize) -> Vec<DiversityWindow> {
+        if size == 0 {
+            panic!("Diversity windows have to span at least one position.");
+        }
+
+        (0..self.depth.len())
+            .step_by(size)
+            .map(|start| {
+                let end = (start + size).min(self.depth.len());
+                let depths = &self.depth[start..end];
+                DiversityWindow {
+                    start,
+                    end,
+                    mean_depth: depths.iter().sum::<usize>() as f64 / depths.len() as f64,
+                    mean_entropy: self.covered_mean(&self.entropy, start..end),
+                    mean_minor_allele_frequency: self.covered_mean(&self.minor_allele_frequency, start..end),
+                    mean_support: self.covered_mean(&self.support, start..end),
+                }
+            })
+            .collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.depth.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "DiversityTrack(len={}, mean_entropy={:.4}, mean_minor_allele_frequency={:.4})",
+            self.depth.len(), self.mean_entropy(), self.mean_minor_allele_frequency()
+        )
+    }
+}
+
+/// Diversity within a window of positions. Means of the diversity tracks only consider covered positions.
+#[derive(Debug, Clone)]
+#[pyclass]
+pub struct DiversityWindow {
+    /// Zero-based start of the window.
+    #[pyo3(get)]
+    start: usize,
+
+    /// Zero-based, exclusive end of the window.
+    #[pyo3(get)]
+    end: usize,
+
+    /// Mean number of observed bases.
+    #[pyo3(get)]
+    mean_depth: f64,
+
+    /// Mean Shannon entropy, in bits.
+    #[pyo3(get)]
+    mean_entropy: f64,
+
+    /// Mean minor allele frequency.
+    #[pyo3(get)]
+    mean_minor_allele_frequency: f64,
+
+    /// Mean fraction of bases agreeing with the consensus.
+    #[pyo3(get)]
+    mean_support: f64,
+}
+
+impl DiversityWindow {
+    pub fn get_start(&self) -> usize { self.start }
+    pub fn get_end(&self) -> usize { self.end }
+    pub fn get_mean_depth(&self) -> f64 { self.mean_depth }
+    pub fn get_mean_entropy(&self) -> f64 { self.mean_entropy }
+    pub fn get_mean_minor_allele_frequency(&self) -> f64 { self.mean_minor_allele_frequency }
+    pub fn get_mean_support(&self) -> f64 { self.mean_support }
+}
+
+#[pymethods]
+impl DiversityWindow {
+    fn __repr__(&self) -> String {
+        format!(
+            "DiversityWindow(start={}, end={}, mean_depth={:.2}, mean_entropy={:.4}, mean_minor_allele_frequency={:.4}, mean_support={:.4})",
+            self.start, self.end, self.mean_depth, self.mean_entropy, self.mean_minor_allele_frequency, self.mean_support
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use counter::Counter;
+
+    #[test]
+    fn diversity_per_position_and_window() {
+        let mut base_counts = vec![Counter::new(); 3];
+        base_counts[0][&b'A'] = 10;
+        base_counts[1][&b'A'] = 5;
+        base_counts[1][&b'C'] = 5;
+        base_counts[2][&b'G'] = 8;
+        base_counts[2][&b'T'] = 2;
+
+        let track = DiversityTrack::new(&base_counts, b"AAG");
+        assert_eq!(track.entropy[..2], [0.0, 1.0]);
+        assert_eq!(track.minor_allele_frequency, vec![0.0, 0.5, 0.2]);
+        assert_eq!(track.support, vec![1.0, 0.5, 0.8]);
+
+        let windows = track.windows(2);
+        assert_eq!((windows[0].start, windows[0].end, windows[1].end), (0, 2, 3));
+        assert_eq!((windows[0].mean_entropy, windows[0].mean_support), (0.5, 0.75));
+    }
+}
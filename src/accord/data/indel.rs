@@ -2,7 +2,7 @@
 
 use pyo3::{pyclass, pymethods};
 use std::hash::Hash;
-use std::ops::RangeInclusive;
+use std::ops::{Range, RangeInclusive};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[pyclass]
@@ -62,6 +62,74 @@ impl InDel {
         self.get_start()..=self.get_stop()
     }
 
+    /// Reference positions, that are replaced by the event sequence when applying this indel.
+    /// For insertions, this is the empty range directly to the right of the insertion position.
+    pub fn replaced_range(&self) -> Range<usize> {
+        match self {
+            InDel::Ins(ins) => ins.position + 1..ins.position + 1,
+            InDel::Del(del) => del.start..del.stop,
+        }
+    }
+
+    /// Average coverage across the site of this indel.
+    pub fn mean_coverage(&self, coverage: &[usize]) -> f64 {
+        let indel_cov = &coverage[self.range()];
+        let total_cov = indel_cov.iter().sum::<usize>() as f64;
+        total_cov / indel_cov.len() as f64
+    }
+
+    /// Number of reads spanning the site of this indel, given the number of reads displaying it.
+    ///
+    /// Reads with a deletion do not cover the deleted bases, so they are added to the coverage
+    /// of deletion sites. Reads with an insertion cover the base left of it already.
+    pub fn spanning_reads(&self, count: usize, coverage: &[usize]) -> f64 {
+        let avg_cov = self.mean_coverage(coverage);
+        match self {
+            InDel::Ins(_) => avg_cov,
+            InDel::Del(_) => avg_cov + count as f64,
+        }
+    }
+
+    /// Fraction of reads spanning the site of this indel, that display it.
+    pub fn support(&self, count: usize, coverage: &[usize]) -> f64 {
+        let spanning_reads = self.spanning_reads(count, coverage);
+        if spanning_reads > 0.0 {
+            (count as f64 / spanning_reads).min(1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Length of the homopolymer run in `ref_seq`, that this indel shortens or lengthens.
+    ///
+    /// An indel changes a run, if all of its bases equal the bases of the run, e.g. deleting an `A` from `AAAA`,
+    /// or inserting `AA` next to it. Indels changing no run have a length of zero, and those changing a single base,
+    /// e.g. deleting the `C` from `ACG`, a length of one.
+    pub fn homopolymer_run(&self, ref_seq: &[u8]) -> usize {
+        let (base, left, right) = match self {
+            InDel::Ins(ins) => match ins.sequence.first() {
+                Some(base) => (*base, ins.position + 1, ins.position + 1),
+                None => return 0,
+            },
+            InDel::Del(del) => match ref_seq.get(del.start) {
+                Some(base) => (*base, del.start, del.stop.min(ref_seq.len())),
+                None => return 0,
+            },
+        };
+
+        let changed = match self {
+            InDel::Ins(ins) => &ins.sequence[..],
+            InDel::Del(_) => &ref_seq[left..right],
+        };
+        if changed.iter().any(|b| *b != base) {
+            return 0;
+        }
+
+        let left_run = ref_seq[..left.min(ref_seq.len())].iter().rev().take_while(|b| **b == base).count();
+        let right_run = ref_seq[right.min(ref_seq.len())..].iter().take_while(|b| **b == base).count();
+        left_run + (right - left) + right_run
+    }
+
     /// The length of this indel event. For Insertions, how long the inserted sequence is,
     /// and for deletions, how many bases are spanned by the deletion.
     pub fn len(&self) -> usize {
@@ -130,20 +198,97 @@ impl Deletion {
     }
 }
 
+/// An indel applied to a consensus, that shifts the reading frame of an annotated coding sequence.
+#[derive(Debug, Clone, PartialEq)]
+#[pyclass]
+pub struct Frameshift {
+    /// The applied indel.
+    #[pyo3(get)]
+    indel: InDel,
+
+    /// Name of the coding sequence, whose reading frame is shifted.
+    #[pyo3(get)]
+    cds: String,
+
+    /// Number of reads displaying the indel.
+    #[pyo3(get)]
+    count: usize,
+
+    /// Fraction of the coverage at the indel site, that displays the indel.
+    #[pyo3(get)]
+    support: f64,
+}
+
+impl Frameshift {
+    pub fn new(indel: InDel, cds: String, count: usize, support: f64) -> Self {
+        Self { indel, cds, count, support }
+    }
+
+    pub fn get_indel(&self) -> &InDel { &self.indel }
+    pub fn get_cds(&self) -> &String { &self.cds }
+    pub fn get_count(&self) -> usize { self.count }
+    pub fn get_support(&self) -> f64 { self.support }
+}
+
+#[pymethods]
+impl Frameshift {
+    fn __repr__(&self) -> String {
+        let indel = match &self.indel {
+            InDel::Ins(ins) => ins.__repr__(),
+            InDel::Del(del) => del.__repr__(),
+        };
+        format!(
+            "Frameshift(indel={indel}, cds='{}', count={}, support={:.3})",
+            self.cds, self.count, self.support
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
-    fn indel_test() {
-        todo!("Write Insertion and Deletion tests!")
+    fn indel_homopolymer_run() {
+        let ref_seq = b"ACAAAAGT";
+        assert_eq!(InDel::Del(Deletion::new(3, 4)).homopolymer_run(ref_seq), 4);
+        assert_eq!(InDel::Del(Deletion::new(2, 6)).homopolymer_run(ref_seq), 4);
+        assert_eq!(InDel::Del(Deletion::new(1, 2)).homopolymer_run(ref_seq), 1);
+        assert_eq!(InDel::Del(Deletion::new(5, 7)).homopolymer_run(ref_seq), 0);
+        assert_eq!(InDel::Ins(Insertion::new(5, b"AA".to_vec())).homopolymer_run(ref_seq), 4);
+        assert_eq!(InDel::Ins(Insertion::new(0, b"A".to_vec())).homopolymer_run(ref_seq), 1);
+        assert_eq!(InDel::Ins(Insertion::new(6, b"T".to_vec())).homopolymer_run(ref_seq), 1);
+        assert_eq!(InDel::Ins(Insertion::new(0, b"G".to_vec())).homopolymer_run(ref_seq), 0);
     }
 
     #[test]
-    fn indel_interference() {
-        todo!("Write Insertion and Deletion tests!")
+    fn indel_test() {
+        let ins = InDel::Ins(Insertion::new(5, b"ACG".to_vec()));
+        assert_eq!((ins.get_start(), ins.get_stop(), ins.len()), (5, 5, 3));
+        assert_eq!(ins.get_seq(), b"ACG");
+        assert!(ins.preserves_reading_frame());
+
+        let del = InDel::Del(Deletion::new(3, 5));
+        assert_eq!((del.get_start(), del.get_stop(), del.len()), (3, 5, 2));
+        assert_eq!(del.get_seq(), b"");
+        assert!(del.breaks_reading_frame());
     }
 
     #[test]
     fn indel_range() {
-        todo!("Write Insertion and Deletion tests!")
+        let ins = InDel::Ins(Insertion::new(2, b"AA".to_vec()));
+        assert_eq!(ins.range(), 2..=2);
+        assert_eq!(ins.replaced_range(), 3..3);
+
+        let del = InDel::Del(Deletion::new(1, 3));
+        assert_eq!(del.range(), 1..=3);
+        assert_eq!(del.replaced_range(), 1..3);
+
+        // reads with a deletion do not cover the deleted bases, so they are added to the spanning reads
+        let coverage = [10, 8, 6, 4];
+        assert_eq!(del.mean_coverage(&coverage), 6.0);
+        assert_eq!(del.spanning_reads(2, &coverage), 8.0);
+        assert_eq!(del.support(2, &coverage), 0.25);
+        assert_eq!(ins.support(3, &coverage), 0.5);
     }
 }
@@ -0,0 +1,397 @@
+//! This module provides the `AaMutation` struct, describing differences between consensus and reference
+//! on the amino acid level, within annotated coding sequences.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use pyo3::{pyclass, pymethods};
+use serde::Serialize;
+
+use super::annotation::Cds;
+use super::indel::InDel;
+use super::seq::alphabet::complement;
+use super::seq::genetic_code::{GeneticCode, STOP};
+use super::seq::Seq;
+
+/// The type of an amino acid mutation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[pyclass(eq, eq_int)]
+pub enum MutationKind {
+    Substitution,
+    Insertion,
+    Deletion,
+    Frameshift,
+    PrematureStop,
+}
+
+impl Display for MutationKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            MutationKind::Substitution => "substitution",
+            MutationKind::Insertion => "insertion",
+            MutationKind::Deletion => "deletion",
+            MutationKind::Frameshift => "frameshift",
+            MutationKind::PrematureStop => "premature_stop",
+        };
+        write!(f, "{kind}")
+    }
+}
+
+/// A difference between consensus and reference in the protein encoded by an annotated coding sequence.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[pyclass]
+pub struct AaMutation {
+    /// Name of the coding sequence.
+    #[pyo3(get)]
+    cds: String,
+
+    /// The type of the mutation.
+    #[pyo3(get)]
+    kind: MutationKind,
+
+    /// One-based position of the first affected codon within the coding sequence.
+    /// For insertions, this is the codon right of the inserted amino acids.
+    #[pyo3(get)]
+    codon_position: usize,
+
+    /// Zero-based position of the first base of the affected codon on the reference.
+    #[pyo3(get)]
+    ref_pos: usize,
+
+    /// Affected amino acids of the reference.
+    #[pyo3(get)]
+    ref_aa: String,
+
+    /// Amino acids of the consensus, that replace the affected reference amino acids.
+    #[pyo3(get)]
+    alt_aa: String,
+
+    /// Codons of the reference encoding the affected amino acids.
+    #[pyo3(get)]
+    ref_codons: String,
+
+    /// Codons of the consensus encoding the replacing amino acids.
+    #[pyo3(get)]
+    alt_codons: String,
+
+    /// The mutation in protein notation, prefixed by the name of the coding sequence, e.g. `RT:K103N`.
+    #[pyo3(get)]
+    notation: String,
+}
+
+/// Coding positions of a CDS, with the aligned bases of reference and consensus in coding direction.
+struct CodingAlignment {
+    /// Reference positions of the coding bases.
+    ref_positions: Vec<usize>,
+
+    /// Reference bases of the coding positions.
+    ref_bases: Vec<u8>,
+
+    /// Consensus bases aligned to each coding position, including inserted bases following it.
+    /// Empty for deleted positions.
+    alt_bases: Vec<Vec<u8>>,
+}
+
+impl AaMutation {
+    pub fn get_cds(&self) -> &String { &self.cds }
+    pub fn get_kind(&self) -> MutationKind { self.kind }
+    pub fn get_codon_position(&self) -> usize { self.codon_position }
+    pub fn get_ref_pos(&self) -> usize { self.ref_pos }
+    pub fn get_ref_aa(&self) -> &String { &self.ref_aa }
+    pub fn get_alt_aa(&self) -> &String { &self.alt_aa }
+    pub fn get_ref_codons(&self) -> &String { &self.ref_codons }
+    pub fn get_alt_codons(&self) -> &String { &self.alt_codons }
+    pub fn get_notation(&self) -> &String { &self.notation }
+
+    /// Find the amino acid mutations of a consensus within an annotated coding sequence.
+    ///
+    /// - `ref_seq: &Seq`: The reference sequence the CDS is annotated on.
+    /// - `consensus_seq: &Seq`: The consensus sequence, resulting from applying `indels` to the reference.
+    /// - `indels: &[InDel]`: The indels applied to the consensus, ordered by position.
+    /// - `cds: &Cds`: The coding sequence to analyse.
+    /// - `code: &GeneticCode`: The genetic code used for translation.
+    ///
+    /// Codons are compared one at a time, while the reading frame is intact.
+    /// Indels are described as insertions or deletions of whole amino acids, once the reading frame is restored.
+    /// An indel that is not compensated until the end of the CDS is reported as frameshift,
+    /// and ends the analysis of the CDS.
+    pub fn call(ref_seq: &Seq, consensus_seq: &Seq, indels: &[InDel], cds: &Cds, code: &GeneticCode) -> Vec<Self> {
+        let aln = CodingAlignment::new(ref_seq, consensus_seq, indels, cds);
+        let codon_count = aln.ref_bases.len() / 3;
+
+        let mut mutations = Vec::new();
+        let mut codon = 0;
+        while codon < codon_count {
+            // extend the block of codons until the consensus is back in frame
+            let first = codon;
+            let mut alt = aln.alt_codon(codon);
+            codon += 1;
+            while !alt.len().is_multiple_of(3) && codon < codon_count {
+                alt.extend(aln.alt_codon(codon));
+                codon += 1;
+            }
+
+            let ref_codons = &aln.ref_bases[first * 3..codon * 3];
+            if !alt.len().is_multiple_of(3) {
+                mutations.push(Self::frameshift(cds, &aln, first, ref_codons, &alt, code));
+                break;
+            }
+
+            if let Some(mutation) = Self::compare_block(cds, &aln, first, ref_codons, &alt, code) {
+                mutations.push(mutation);
+            }
+        }
+
+        mutations
+    }
+
+    /// Describe the differences between a block of reference codons and the in-frame consensus bases replacing them.
+    fn compare_block(cds: &Cds, aln: &CodingAlignment, first: usize, ref_codons: &[u8], alt: &[u8], code: &GeneticCode) -> Option<Self> {
+        let ref_aas = code.translate(ref_codons);
+        let alt_aas = code.translate(alt);
+
+        // trim unchanged amino acids on both ends
+        let mut prefix = ref_aas.iter().zip(alt_aas.iter()).take_while(|(r, a)| r == a).count();
+        let max_suffix = ref_aas.len().min(alt_aas.len()) - prefix;
+        let mut suffix = ref_aas.iter().rev().zip(alt_aas.iter().rev())
+            .take(max_suffix)
+            .take_while(|(r, a)| r == a)
+            .count();
+
+        // insertions need amino acids on both sides, otherwise they replace the adjacent amino acid
+        let codon_count = aln.ref_bases.len() / 3;
+        let is_insertion = prefix + suffix == ref_aas.len() && alt_aas.len() > ref_aas.len();
+        if is_insertion && (first + prefix == 0 || first + prefix >= codon_count) {
+            if prefix > 0 { prefix -= 1 } else { suffix -= 1 }
+        }
+
+        let ref_changed = &ref_aas[prefix..ref_aas.len() - suffix];
+        let alt_changed = &alt_aas[prefix..alt_aas.len() - suffix];
+        if ref_changed.is_empty() && alt_changed.is_empty() {
+            return None;
+        }
+
+        let kind = if ref_changed.len() == alt_changed.len() {
+            let is_stop = alt_changed.contains(&STOP) && !ref_changed.contains(&STOP);
+            if is_stop { MutationKind::PrematureStop } else { MutationKind::Substitution }
+        } else if ref_changed.len() < alt_changed.len() {
+            MutationKind::Insertion
+        } else {
+            MutationKind::Deletion
+        };
+
+        let start = first + prefix; // zero-based index of the first changed codon
+        let ref_aa = String::from_utf8_lossy(ref_changed).to_string();
+        let alt_aa = String::from_utf8_lossy(alt_changed).to_string();
+        let notation = if ref_changed.is_empty() {
+            // pure insertion between two amino acids
+            let before = Self::aa_label(aln, start - 1, code);
+            let after = Self::aa_label(aln, start, code);
+            format!("{before}_{after}ins{alt_aa}")
+        } else {
+            let range = if ref_changed.len() == 1 {
+                Self::aa_label(aln, start, code)
+            } else {
+                let last = start + ref_changed.len() - 1;
+                format!("{}_{}", Self::aa_label(aln, start, code), Self::aa_label(aln, last, code))
+            };
+            match kind {
+                MutationKind::Substitution | MutationKind::PrematureStop if ref_changed.len() == 1 => format!("{range}{alt_aa}"),
+                _ if alt_changed.is_empty() => format!("{range}del"),
+                _ => format!("{range}delins{alt_aa}"),
+            }
+        };
+
+        let ref_codons = String::from_utf8_lossy(&ref_codons[prefix * 3..(ref_aas.len() - suffix) * 3]).to_string();
+        let alt_codons = String::from_utf8_lossy(&alt[prefix * 3..(alt_aas.len() - suffix) * 3]).to_string();
+
+        Some(Self {
+            cds: cds.get_name().clone(),
+            kind,
+            codon_position: start + 1,
+            ref_pos: aln.codon_ref_pos(start),
+            ref_aa,
+            alt_aa,
+            ref_codons,
+            alt_codons,
+            notation: format!("{}:{notation}", cds.get_name()),
+        })
+    }
+
+    /// Describe an indel, that shifts the reading frame until the end of the CDS.
+    fn frameshift(cds: &Cds, aln: &CodingAlignment, first: usize, ref_codons: &[u8], alt: &[u8], code: &GeneticCode) -> Self {
+        let ref_codon = &ref_codons[..3];
+        let alt_codon = &alt[..alt.len().min(3)];
+        let label = Self::aa_label(aln, first, code);
+
+        Self {
+            cds: cds.get_name().clone(),
+            kind: MutationKind::Frameshift,
+            codon_position: first + 1,
+            ref_pos: aln.codon_ref_pos(first),
+            ref_aa: String::from_utf8_lossy(&code.translate(ref_codon)).to_string(),
+            alt_aa: String::from_utf8_lossy(&code.translate(alt_codon)).to_string(),
+            ref_codons: String::from_utf8_lossy(ref_codon).to_string(),
+            alt_codons: String::from_utf8_lossy(alt_codon).to_string(),
+            notation: format!("{}:{label}fs", cds.get_name()),
+        }
+    }
+
+    /// Label of a reference codon in protein notation, e.g. `K103`.
+    fn aa_label(aln: &CodingAlignment, codon: usize, code: &GeneticCode) -> String {
+        let aa = code.translate_codon(&aln.ref_bases[codon * 3..codon * 3 + 3]) as char;
+        format!("{aa}{}", codon + 1)
+    }
+}
+
+#[pymethods]
+impl AaMutation {
+    fn __repr__(&self) -> String {
+        format!("AaMutation({}, kind={})", self.notation, self.kind)
+    }
+}
+
+impl CodingAlignment {
+    fn new(ref_seq: &Seq, consensus_seq: &Seq, indels: &[InDel], cds: &Cds) -> Self {
+        let (aligned, insertions) = Self::align_consensus(ref_seq.len(), consensus_seq, indels);
+        let orient = |base: u8| if cds.is_reverse() { complement(base) } else { base };
+
+        // collect coding positions in forward direction
+        let mut ref_positions = Vec::new();
+        let mut ref_bases = Vec::new();
+        let mut alt_bases = Vec::new();
+        for (start, end) in cds.get_segments() {
+            let end = (*end).min(ref_seq.len());
+            for pos in *start..end {
+                ref_positions.push(pos);
+                ref_bases.push(orient(ref_seq[pos]));
+                alt_bases.push(aligned[pos].map(orient).into_iter().collect::<Vec<u8>>());
+            }
+        }
+
+        // attach insertions within a segment to the coding position preceding them in coding direction
+        for (i, pos) in ref_positions.iter().enumerate() {
+            let Some(ins_seq) = insertions.get(pos) else { continue };
+            let is_internal = ref_positions.get(i + 1).is_some_and(|next| *next == pos + 1);
+            if !is_internal {
+                continue;
+            }
+            if cds.is_reverse() {
+                alt_bases[i + 1].extend(ins_seq.iter().rev().map(|base| complement(*base)));
+            } else {
+                alt_bases[i].extend(ins_seq);
+            }
+        }
+
+        if cds.is_reverse() {
+            ref_positions.reverse();
+            ref_bases.reverse();
+            alt_bases.reverse();
+        }
+
+        ref_bases.make_ascii_uppercase();
+        for bases in alt_bases.iter_mut() {
+            bases.make_ascii_uppercase();
+        }
+
+        Self { ref_positions, ref_bases, alt_bases }
+    }
+
+    /// Align the consensus to the reference, by replaying the applied indels.
+    /// Returns the consensus base aligned to each reference position (`None` if deleted),
+    /// and a mapping from reference positions to the bases inserted to the right of them.
+    fn align_consensus(ref_len: usize, consensus_seq: &Seq, indels: &[InDel]) -> (Vec<Option<u8>>, HashMap<usize, Vec<u8>>) {
+        let consensus = consensus_seq.get_sequence();
+        let mut aligned = vec![None; ref_len];
+        let mut insertions = HashMap::new();
+
+        let mut indels = indels.iter().peekable();
+        let mut cons_pos = 0;
+        let mut ref_pos = 0;
+        while ref_pos < ref_len {
+            // deleted positions have no consensus base
+            if let Some(del @ InDel::Del(_)) = indels.peek() {
+                if del.get_start() == ref_pos {
+                    ref_pos = del.get_stop();
+                    indels.next();
+                    continue;
+                }
+            }
+
+            aligned[ref_pos] = consensus.get(cons_pos).copied();
+            cons_pos += 1;
+
+            // inserted bases follow the base left of them
+            if let Some(ins @ InDel::Ins(_)) = indels.peek() {
+                if ins.get_start() == ref_pos {
+                    insertions.insert(ref_pos, ins.get_seq().to_vec());
+                    cons_pos += ins.len();
+                    indels.next();
+                }
+            }
+
+            ref_pos += 1;
+        }
+
+        (aligned, insertions)
+    }
+
+    /// Consensus bases aligned to a reference codon, given by its zero-based index.
+    fn alt_codon(&self, codon: usize) -> Vec<u8> {
+        self.alt_bases[codon * 3..codon * 3 + 3].concat()
+    }
+
+    /// Reference position of the first base of a codon, in coding direction.
+    fn codon_ref_pos(&self, codon: usize) -> usize {
+        self.ref_positions[codon * 3]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accord::data::indel::{Deletion, Insertion};
+
+    // encodes M K P *
+    const REF: &str = "ATGAAACCCTAA";
+
+    fn notations(ref_seq: &str, consensus: &str, indels: &[InDel], reverse: bool) -> Vec<String> {
+        let ref_seq = Seq::new(String::from("ref"), ref_seq.as_bytes().to_vec());
+        let consensus_seq = Seq::new(String::from("cons"), consensus.as_bytes().to_vec());
+        let cds = Cds::new("g".into(), "ref".into(), vec![(0, ref_seq.len())], reverse);
+
+        AaMutation::call(&ref_seq, &consensus_seq, indels, &cds, &GeneticCode::standard())
+            .iter()
+            .map(|mutation| mutation.notation.clone())
+            .collect()
+    }
+
+    #[test]
+    fn aa_substitutions() {
+        assert_eq!(notations(REF, REF, &[], false), Vec::<String>::new());
+        assert_eq!(notations(REF, "ATGAACCCCTAA", &[], false), vec!["g:K2N"]);
+        assert_eq!(notations(REF, "ATGTAACCCTAA", &[], false), vec!["g:K2*"]);
+    }
+
+    #[test]
+    fn aa_indels() {
+        let del = InDel::Del(Deletion::new(3, 6));
+        assert_eq!(notations(REF, "ATGCCCTAA", &[del], false), vec!["g:K2del"]);
+
+        let ins = InDel::Ins(Insertion::new(5, b"GGG".to_vec()));
+        assert_eq!(notations(REF, "ATGAAAGGGCCCTAA", &[ins], false), vec!["g:K2_P3insG"]);
+
+        let frameshift = InDel::Del(Deletion::new(4, 5));
+        assert_eq!(notations(REF, "ATGAACCCTAA", &[frameshift], false), vec!["g:K2fs"]);
+    }
+
+    #[test]
+    fn aa_reverse_strand() {
+        let ref_seq = Seq::new(String::from("ref"), REF.as_bytes().to_vec()).reverse_complement();
+        let consensus = Seq::new(String::from("cons"), b"ATGAACCCCTAA".to_vec()).reverse_complement();
+
+        let mutations = notations(&ref_seq.get_sequence_as_string(), &consensus.get_sequence_as_string(), &[], true);
+        assert_eq!(mutations, vec!["g:K2N"]);
+    }
+}
@@ -0,0 +1,154 @@
+//! This module provides the `QcStats` struct, summarizing the coverage of a consensus,
+//! and the `QcVerdict` enum, judging whether the consensus is trustworthy.
+
+use std::fmt::{Display, Formatter};
+
+use pyo3::{pyclass, pymethods};
+
+use super::super::types::BaseCounts;
+
+/// Verdict of the quality control of a consensus. Verdicts are ordered by severity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[pyclass(eq, eq_int)]
+pub enum QcVerdict {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Display for QcVerdict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let verdict = match self {
+            QcVerdict::Pass => "PASS",
+            QcVerdict::Warn => "WARN",
+            QcVerdict::Fail => "FAIL",
+        };
+        write!(f, "{verdict}")
+    }
+}
+
+/// Coverage statistics of a consensus, and the verdict of its quality control.
+#[derive(Debug, Clone)]
+#[pyclass]
+pub struct QcStats {
+    /// Fraction of positions with at least the respective depth, as pairs of depth threshold and breadth.
+    #[pyo3(get)]
+    breadth: Vec<(usize, f64)>,
+
+    /// Mean depth of coverage.
+    #[pyo3(get)]
+    mean_depth: f64,
+
+    /// Median depth of coverage.
+    #[pyo3(get)]
+    median_depth: f64,
+
+    /// Fraction of positions, in which the consensus base was called from the reads.
+    /// In the remaining positions, the consensus falls back to the reference.
+    #[pyo3(get)]
+    called_fraction: f64,
+
+    /// The verdict of the quality control.
+    #[pyo3(get)]
+    verdict: QcVerdict,
+
+    /// Reasons for a verdict other than `Pass`.
+    #[pyo3(get)]
+    reasons: Vec<String>,
+}
+
+impl QcStats {
+    /// Summarize the coverage of a consensus. The verdict is `Pass`, until set with `with_verdict`.
+    ///
+    /// - `coverage: &[usize]`: Coverage per position of the reference sequence.
+    /// - `base_counts: &BaseCounts`: Base counts per position of the reference sequence.
+    /// - `intervals: &[(usize, usize)]`: Disjoint intervals of the reference sequence, that were analysed.
+    /// - `min_observations: usize`: Minimum number of observations of a base, for calling it from the reads.
+    /// - `depth_thresholds: &[usize]`: Depths, for which the breadth of coverage is computed.
+    pub fn new(
+        coverage: &[usize],
+        base_counts: &BaseCounts,
+        intervals: &[(usize, usize)],
+        min_observations: usize,
+        depth_thresholds: &[usize],
+    ) -> Self {
+        let positions = intervals.iter().flat_map(|(start, end)| *start..*end).collect::<Vec<usize>>();
+        let len = positions.len().max(1) as f64;
+
+        let mut depths = positions.iter().map(|pos| coverage[*pos]).collect::<Vec<usize>>();
+        depths.sort_unstable();
+
+        let breadth = depth_thresholds
+            .iter()
+            .map(|threshold| (*threshold, depths.iter().filter(|depth| *depth >= threshold).count() as f64 / len))
+            .collect();
+        let mean_depth = depths.iter().sum::<usize>() as f64 / len;
+        let median_depth = Self::median(&depths);
+
+        // mirrors the fallback to the reference base, when calling the consensus by majority
+        let called = positions
+            .iter()
+            .filter(|pos| base_counts[**pos].values().max().is_some_and(|count| *count >= min_observations))
+            .count();
+        let called_fraction = called as f64 / len;
+
+        Self { breadth, mean_depth, median_depth, called_fraction, verdict: QcVerdict::Pass, reasons: Vec::new() }
+    }
+
+    /// Set the verdict of the quality control, and the reasons for it.
+    pub fn with_verdict(self, verdict: QcVerdict, reasons: Vec<String>) -> Self {
+        Self { verdict, reasons, ..self }
+    }
+
+    /// Median of sorted values, or zero if there are none.
+    fn median(sorted: &[usize]) -> f64 {
+        let mid = sorted.len() / 2;
+        match sorted.len() {
+            0 => 0.0,
+            len if len % 2 == 0 => (sorted[mid - 1] + sorted[mid]) as f64 / 2.0,
+            _ => sorted[mid] as f64,
+        }
+    }
+
+    pub fn get_breadth(&self) -> &Vec<(usize, f64)> { &self.breadth }
+    pub fn get_mean_depth(&self) -> f64 { self.mean_depth }
+    pub fn get_median_depth(&self) -> f64 { self.median_depth }
+    pub fn get_called_fraction(&self) -> f64 { self.called_fraction }
+    pub fn get_verdict(&self) -> QcVerdict { self.verdict }
+    pub fn get_reasons(&self) -> &Vec<String> { &self.reasons }
+}
+
+#[pymethods]
+impl QcStats {
+    fn __repr__(&self) -> String {
+        format!(
+            "QcStats(verdict={}, mean_depth={:.2}, median_depth={:.1}, called_fraction={:.4}, breadth={:?})",
+            self.verdict, self.mean_depth, self.median_depth, self.called_fraction, self.breadth
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use counter::Counter;
+
+    #[test]
+    fn qc_coverage_stats() {
+        let coverage = [0, 5, 10, 20, 100, 0];
+        let mut base_counts = vec![Counter::new(); coverage.len()];
+        for (pos, depth) in coverage.iter().enumerate() {
+            base_counts[pos][&b'A'] = *depth;
+        }
+
+        let stats = QcStats::new(&coverage, &base_counts, &[(0, 5)], 10, &[1, 10]);
+        assert_eq!(stats.breadth, vec![(1, 0.8), (10, 0.6)]);
+        assert_eq!(stats.mean_depth, 27.0);
+        assert_eq!(stats.median_depth, 10.0);
+        assert_eq!(stats.called_fraction, 0.6);
+        assert_eq!(stats.verdict, QcVerdict::Pass);
+
+        let stats = QcStats::new(&coverage, &base_counts, &[(0, 2), (4, 6)], 10, &[]);
+        assert_eq!(stats.median_depth, 2.5);
+    }
+}
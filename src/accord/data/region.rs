@@ -0,0 +1,283 @@
+//! This module provides the `Region` struct, restricting the consensus calculation to intervals of a contig,
+//! and the `RegionStats` struct, summarizing the coverage within a region.
+
+use std::io::BufRead;
+use std::path::Path;
+
+use pyo3::types::PyType;
+use pyo3::{pyclass, pymethods, Bound, PyResult};
+
+use super::seq::ParseError;
+use crate::accord::utils::open_file;
+
+/// An interval on a contig.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[pyclass(eq)]
+pub struct Region {
+    /// Name of the region. Defaults to its location, e.g. `chr1:101-200`.
+    #[pyo3(get)]
+    name: String,
+
+    /// Name of the contig the region is located on.
+    #[pyo3(get)]
+    contig: String,
+
+    /// Zero-based start of the region.
+    #[pyo3(get)]
+    start: usize,
+
+    /// Zero-based, exclusive end of the region.
+    #[pyo3(get)]
+    end: usize,
+}
+
+impl Region {
+    pub fn new(name: String, contig: String, start: usize, end: usize) -> Self {
+        Self { name, contig, start, end }
+    }
+
+    /// Read regions from a region string or a BED file.
+    ///
+    /// If `spec` is a path to an existing file, it is parsed as BED file. Otherwise, it is parsed as region string.
+    pub fn from_spec(spec: &String) -> Vec<Self> {
+        if Path::new(spec).is_file() {
+            return match Self::parse_bed(open_file(spec)) {
+                Ok(regions) => regions,
+                Err(e) => panic!("Unable to parse regions from {spec}: {e}"),
+            };
+        }
+
+        match Self::parse(spec) {
+            Some(region) => vec![region],
+            None => panic!("Invalid region '{spec}', expected 'contig:start-end' or a path to a BED file."),
+        }
+    }
+
+    /// Parse a region string like `chr1:101-200`, with one-based, inclusive coordinates.
+    /// Thousands separators in the coordinates are allowed. A contig name alone denotes the whole contig.
+    ///
+    /// Returns `None`, if the string is malformed.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.trim();
+        if spec.is_empty() {
+            return None;
+        }
+
+        // contig names may contain colons themselves, so only the last one separates the coordinates
+        let (contig, coordinates) = match spec.rsplit_once(':') {
+            Some((contig, coordinates)) if coordinates.contains('-') => (contig, coordinates),
+            _ => return Some(Self::new(spec.to_string(), spec.to_string(), 0, usize::MAX)),
+        };
+
+        let parse_coordinate = |value: &str| value.trim().replace(',', "").parse::<usize>().ok();
+        let (start, end) = coordinates.split_once('-')?;
+        let (start, end) = (parse_coordinate(start)?, parse_coordinate(end)?);
+        if contig.is_empty() || start == 0 || start > end {
+            return None;
+        }
+
+        Some(Self::new(spec.to_string(), contig.to_string(), start - 1, end))
+    }
+
+    /// Parse regions from BED formatted data. Records without a name are named after their location.
+    pub fn parse_bed<R: BufRead>(reader: R) -> Result<Vec<Self>, ParseError> {
+        let mut regions = Vec::new();
+
+        for (i, line) in reader.lines().enumerate() {
+            let line_no = i + 1;
+            let line = line.map_err(|e| ParseError::new(line_no, format!("Unable to read line: {e}")))?;
+            let line = line.trim_end();
+
+            let is_header = ["#", "track", "browser"].iter().any(|prefix| line.starts_with(prefix));
+            if line.trim().is_empty() || is_header {
+                continue;
+            }
+
+            let fields = line.split('\t').collect::<Vec<&str>>();
+            if fields.len() < 3 {
+                let msg = format!("Expected at least 3 tab separated columns, found {}", fields.len());
+                return Err(ParseError::new(line_no, msg));
+            }
+
+            let parse_coordinate = |field: &str| match field.trim().parse::<usize>() {
+                Ok(coordinate) => Ok(coordinate),
+                Err(_) => Err(ParseError::new(line_no, format!("Invalid coordinate '{field}'"))),
+            };
+            let contig = fields[0].to_string();
+            let start = parse_coordinate(fields[1])?;
+            let end = parse_coordinate(fields[2])?;
+            if start >= end {
+                let msg = format!("Invalid region coordinates {start}-{end}");
+                return Err(ParseError::new(line_no, msg));
+            }
+
+            let name = match fields.get(3) {
+                Some(name) if !name.is_empty() && *name != "." => name.to_string(),
+                _ => format!("{contig}:{}-{end}", start + 1),
+            };
+            regions.push(Self::new(name, contig, start, end));
+        }
+
+        Ok(regions)
+    }
+
+    /// Clip the region to a contig of length `len`. Returns `None`, if the region lies beyond the contig.
+    pub fn clip(&self, len: usize) -> Option<Self> {
+        let end = self.end.min(len);
+        if self.start >= end {
+            return None;
+        }
+        Some(Self::new(self.name.clone(), self.contig.clone(), self.start, end))
+    }
+
+    /// Merge overlapping and adjacent regions into sorted, disjoint `(start, end)` intervals.
+    pub fn merge_intervals(regions: &[Self]) -> Vec<(usize, usize)> {
+        let mut intervals = regions.iter().map(|region| (region.start, region.end)).collect::<Vec<_>>();
+        intervals.sort();
+
+        let mut merged: Vec<(usize, usize)> = Vec::with_capacity(intervals.len());
+        for (start, end) in intervals {
+            match merged.last_mut() {
+                Some((_, last_end)) if start <= *last_end => *last_end = (*last_end).max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        merged
+    }
+
+    pub fn get_name(&self) -> &String { &self.name }
+    pub fn get_contig(&self) -> &String { &self.contig }
+    pub fn get_start(&self) -> usize { self.start }
+    pub fn get_end(&self) -> usize { self.end }
+}
+
+#[pymethods]
+impl Region {
+    #[new]
+    #[pyo3(signature = (contig, start, end, name = None))]
+    fn py_new(contig: String, start: usize, end: usize, name: Option<String>) -> Self {
+        let name = name.unwrap_or_else(|| format!("{contig}:{}-{end}", start + 1));
+        Self::new(name, contig, start, end)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "from_bed")]
+    fn py_from_bed(_cls: &Bound<'_, PyType>, file: String) -> PyResult<Vec<Self>> {
+        Ok(Self::parse_bed(open_file(&file))?)
+    }
+
+    /// Whether a position on the contig belongs to the region.
+    pub fn contains(&self, pos: usize) -> bool {
+        (self.start..self.end).contains(&pos)
+    }
+
+    fn __len__(&self) -> usize {
+        self.end - self.start
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Region(name='{}', contig='{}', start={}, end={})", self.name, self.contig, self.start, self.end)
+    }
+}
+
+/// Coverage statistics of a region.
+#[derive(Debug, Clone)]
+#[pyclass]
+pub struct RegionStats {
+    /// The region, clipped to the reference sequence.
+    #[pyo3(get)]
+    region: Region,
+
+    /// Mean coverage of the positions in the region.
+    #[pyo3(get)]
+    mean_coverage: f64,
+
+    /// Lowest coverage of any position in the region.
+    #[pyo3(get)]
+    min_coverage: usize,
+
+    /// Highest coverage of any position in the region.
+    #[pyo3(get)]
+    max_coverage: usize,
+
+    /// Fraction of positions in the region with a coverage of at least `min_depth`.
+    #[pyo3(get)]
+    breadth: f64,
+}
+
+impl RegionStats {
+    /// Summarize the coverage within a region.
+    ///
+    /// - `region: Region`: The region, which has to lie within the covered sequence.
+    /// - `coverage: &[usize]`: Coverage per position of the sequence the region is located on.
+    /// - `min_depth: usize`: Minimum coverage for a position to count towards the breadth.
+    pub fn new(region: Region, coverage: &[usize], min_depth: usize) -> Self {
+        let covered = &coverage[region.start..region.end];
+        let len = covered.len().max(1) as f64;
+
+        let mean_coverage = covered.iter().sum::<usize>() as f64 / len;
+        let min_coverage = covered.iter().copied().min().unwrap_or_default();
+        let max_coverage = covered.iter().copied().max().unwrap_or_default();
+        let breadth = covered.iter().filter(|depth| **depth >= min_depth).count() as f64 / len;
+
+        Self { region, mean_coverage, min_coverage, max_coverage, breadth }
+    }
+
+    pub fn get_region(&self) -> &Region { &self.region }
+    pub fn get_mean_coverage(&self) -> f64 { self.mean_coverage }
+    pub fn get_min_coverage(&self) -> usize { self.min_coverage }
+    pub fn get_max_coverage(&self) -> usize { self.max_coverage }
+    pub fn get_breadth(&self) -> f64 { self.breadth }
+}
+
+#[pymethods]
+impl RegionStats {
+    fn __repr__(&self) -> String {
+        format!(
+            "RegionStats(region='{}', mean_coverage={:.2}, min_coverage={}, max_coverage={}, breadth={:.4})",
+            self.region.name, self.mean_coverage, self.min_coverage, self.max_coverage, self.breadth
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_strings() {
+        let region = Region::parse("chr1:1,001-2,000").unwrap();
+        assert_eq!((region.contig.as_str(), region.start, region.end), ("chr1", 1000, 2000));
+
+        let region = Region::parse("HLA-A*01:01:1-10").unwrap();
+        assert_eq!((region.contig.as_str(), region.start, region.end), ("HLA-A*01:01", 0, 10));
+
+        let whole = Region::parse("chr2").unwrap();
+        assert_eq!(whole.clip(500).unwrap().end, 500);
+
+        assert!(Region::parse("chr1:0-10").is_none());
+        assert!(Region::parse("chr1:20-10").is_none());
+    }
+
+    #[test]
+    fn region_bed_and_merging() {
+        let bed = "track name=amplicons\nchr1\t0\t100\tamp1\nchr1\t80\t150\nchr1\t150\t160\nchr1\t300\t400\n";
+        let regions = Region::parse_bed(bed.as_bytes()).unwrap();
+
+        assert_eq!(regions.len(), 4);
+        assert_eq!(regions[0].name, "amp1");
+        assert_eq!(regions[1].name, "chr1:81-150");
+        assert_eq!(Region::merge_intervals(&regions), vec![(0, 160), (300, 400)]);
+    }
+
+    #[test]
+    fn region_coverage_stats() {
+        let region = Region::new("amp".into(), "chr1".into(), 1, 5);
+        let stats = RegionStats::new(region, &[0, 10, 20, 0, 50, 100], 10);
+
+        assert_eq!(stats.mean_coverage, 20.0);
+        assert_eq!((stats.min_coverage, stats.max_coverage), (0, 50));
+        assert_eq!(stats.breadth, 0.75);
+    }
+}
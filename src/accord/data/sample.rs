@@ -0,0 +1,197 @@
+//! This module provides the `Sample` struct, which associates a sample ID with a file of aligned reads.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use pyo3::types::PyType;
+use pyo3::{pyclass, pymethods, Bound};
+
+/// A sample, i.e. a set of aligned reads identified by a sample ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[pyclass]
+pub struct Sample {
+    /// The sample ID. Used for naming output files.
+    #[pyo3(get)]
+    id: String,
+
+    /// Path to a sorted BAM file with the aligned reads of this sample.
+    #[pyo3(get)]
+    aln_path: String,
+}
+
+impl Sample {
+    pub fn new(id: String, aln_path: String) -> Self {
+        Self { id, aln_path }
+    }
+
+    /// Create a sample from a path to aligned reads, using the file stem as sample ID.
+    /// E.g., the sample ID for `/path/to/S01.sorted.bam` is `S01.sorted`.
+    pub fn from_path(aln_path: String) -> Self {
+        let id = match Path::new(&aln_path).file_stem() {
+            Some(stem) => stem.to_string_lossy().to_string(),
+            None => panic!("Unable to derive a sample ID from {aln_path}"),
+        };
+        Self::new(id, aln_path)
+    }
+
+    /// Parse samples from the content of a sample sheet.
+    ///
+    /// Every non-empty line that does not start with `#` holds a sample ID and a path to aligned reads,
+    /// separated by a tab, a comma, or whitespace.
+    /// Relative paths are resolved relative to `base_dir`, if one is passed.
+    pub fn from_sheet(sheet: String, base_dir: Option<&Path>) -> Vec<Self> {
+        let mut samples = Vec::new();
+        let mut seen_ids = HashSet::new();
+
+        for (i, line) in sheet.lines().enumerate() {
+            let line_no = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // prefer explicit separators, so paths may contain spaces
+            let fields = if line.contains('\t') {
+                line.split('\t').map(str::trim).collect::<Vec<&str>>()
+            } else if line.contains(',') {
+                line.split(',').map(str::trim).collect::<Vec<&str>>()
+            } else {
+                line.split_whitespace().collect::<Vec<&str>>()
+            };
+
+            let (id, path) = match fields.as_slice() {
+                [id, path] if !id.is_empty() && !path.is_empty() => (*id, *path),
+                _ => panic!("Malformed sample sheet entry in line {line_no}, expected a sample ID and a path: {line}"),
+            };
+
+            if !seen_ids.insert(id.to_string()) {
+                panic!("Duplicate sample ID '{id}' in line {line_no} of sample sheet.");
+            }
+
+            let aln_path = match base_dir {
+                Some(dir) if Path::new(path).is_relative() => dir.join(path).to_string_lossy().to_string(),
+                _ => path.to_string(),
+            };
+
+            samples.push(Self::new(id.to_string(), aln_path));
+        }
+
+        samples
+    }
+
+    /// Read samples from a sample sheet file. Relative paths are resolved relative to the sheet location.
+    pub fn from_sheet_file(path: &String) -> Vec<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => panic!("Unable to read sample sheet {path}: {e}"),
+        };
+        let base_dir = Path::new(path).parent();
+        Self::from_sheet(content, base_dir)
+    }
+
+    /// Make sure no two samples share an ID, as their output files would be named the same.
+    /// E.g., alignment files with the same file stem in different directories, or a sheet row and a passed path.
+    pub fn check_unique_ids(samples: &[Self]) {
+        let mut seen_ids = HashSet::new();
+        for sample in samples {
+            if !seen_ids.insert(&sample.id) {
+                panic!("Duplicate sample ID '{}' for {}.", sample.id, sample.aln_path);
+            }
+        }
+    }
+
+    /// Derive an ID for a group of reads within this sample, e.g. a read group or a cell barcode.
+    /// Characters other than alphanumerics, `-`, `_` and `.` are replaced with underscores,
+    /// so the ID may be used for naming output files.
+    /// If `qualify` is set, the ID is prefixed with the sample ID, to tell apart groups of different samples.
+    pub fn group_id(&self, group: &str, qualify: bool) -> String {
+        let safe_group = group
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || "-_.".contains(c) { c } else { '_' })
+            .collect::<String>();
+
+        if qualify {
+            format!("{}.{safe_group}", self.id)
+        } else {
+            safe_group
+        }
+    }
+
+    pub fn get_id(&self) -> &String { &self.id }
+    pub fn get_aln_path(&self) -> &String { &self.aln_path }
+}
+
+#[pymethods]
+impl Sample {
+    #[new]
+    fn py_new(id: String, aln_path: String) -> Self {
+        Self::new(id, aln_path)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "from_sheet")]
+    fn py_from_sheet(_cls: &Bound<'_, PyType>, path: String) -> Vec<Self> {
+        Self::from_sheet_file(&path)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Sample(id='{}', aln_path='{}')", self.id, self.aln_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_from_path() {
+        let sample = Sample::from_path(String::from("/data/plate1/S01.bam"));
+        assert_eq!(sample.id, "S01");
+        assert_eq!(sample.aln_path, "/data/plate1/S01.bam");
+    }
+
+    #[test]
+    fn sample_sheet_separators() {
+        let sheet = String::from("# id\tpath\nS01\tS01.bam\n\nS02,/abs/S02.bam\nS03 S03.bam\n");
+        let samples = Sample::from_sheet(sheet, Some(Path::new("/data")));
+
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples[0], Sample::new("S01".into(), "/data/S01.bam".into()));
+        assert_eq!(samples[1], Sample::new("S02".into(), "/abs/S02.bam".into()));
+        assert_eq!(samples[2], Sample::new("S03".into(), "/data/S03.bam".into()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate sample ID 'S01'")]
+    fn sample_ids_unique_across_sources() {
+        let mut samples = Sample::from_sheet(String::from("S01\ta.bam\n"), None);
+        samples.push(Sample::from_path(String::from("/data/plate2/S02.bam")));
+        Sample::check_unique_ids(&samples);
+
+        samples.push(Sample::from_path(String::from("/data/plate2/S01.bam")));
+        Sample::check_unique_ids(&samples);
+    }
+
+    #[test]
+    fn sample_group_ids() {
+        let sample = Sample::from_path(String::from("/data/plate1/pool.bam"));
+        assert_eq!(sample.group_id("S01", false), "S01");
+        assert_eq!(sample.group_id("S01", true), "pool.S01");
+        assert_eq!(sample.group_id("ACGT-1/a b", false), "ACGT-1_a_b");
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate sample ID")]
+    fn sample_sheet_duplicate_ids() {
+        let sheet = String::from("S01\ta.bam\nS01\tb.bam\n");
+        Sample::from_sheet(sheet, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "line 2")]
+    fn sample_sheet_malformed() {
+        let sheet = String::from("S01\ta.bam\nS02\n");
+        Sample::from_sheet(sheet, None);
+    }
+}
@@ -1,16 +1,63 @@
 //! This module provides a `Seq` struct for working with sequence data.
 
-use itertools::Itertools;
-use pyo3::types::PyType;
-use pyo3::{pyclass, pymethods, Bound};
+pub mod alphabet;
+pub mod fasta;
+pub mod fastq;
+pub mod genetic_code;
+
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::types::{PyAnyMethods, PySlice, PySliceMethods, PyType};
+use pyo3::{pyclass, pymethods, Bound, IntoPyObjectExt, PyAny, PyErr, PyResult, Python};
+use rust_htslib::faidx;
 use std::cmp::min;
+use std::collections::HashSet;
+use std::error::Error;
 use std::fmt::{Display, Formatter};
-use std::fs;
+use std::io::{BufRead, Write};
 use std::ops::Index;
+use std::path::Path;
 use std::slice::SliceIndex;
 
-#[derive(Debug, Clone)]
-#[pyclass]
+use crate::accord::utils::{create_file, open_file};
+use fasta::FastaReader;
+use fastq::{FastqReader, MAX_PHRED, PHRED_OFFSET};
+use genetic_code::GeneticCode;
+
+/// An error encountered while parsing sequence data, with the number of the offending line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Number of the offending line, starting at one.
+    line: usize,
+
+    /// Description of the error.
+    message: String,
+}
+
+impl ParseError {
+    pub fn new(line: usize, message: String) -> Self {
+        Self { line, message }
+    }
+
+    pub fn get_line(&self) -> usize { self.line }
+    pub fn get_message(&self) -> &String { &self.message }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+impl From<ParseError> for PyErr {
+    fn from(e: ParseError) -> Self {
+        PyValueError::new_err(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[pyclass(eq, hash, frozen)]
 pub struct Seq {
     /// The sequence name.
     #[pyo3(get)]
@@ -18,11 +65,26 @@ pub struct Seq {
 
     /// The sequence bytes as a vector name.
     sequence: Vec<u8>, // `Vec<u8>` allows for O(1) indexing, as opposed to `String`.
+
+    /// Optional Phred quality scores, one per base.
+    qualities: Option<Vec<u8>>,
 }
 
 impl Seq {
     pub fn new(label: String, sequence: Vec<u8>) -> Self {
-        Self { label, sequence }
+        Self { label, sequence, qualities: None }
+    }
+
+    /// Create a `Seq` with Phred quality scores, one per base.
+    pub fn with_qualities(label: String, sequence: Vec<u8>, qualities: Vec<u8>) -> Self {
+        if qualities.len() != sequence.len() {
+            panic!(
+                "Number of qualities ({}) does not match sequence length ({}) for '{label}'.",
+                qualities.len(),
+                sequence.len()
+            );
+        }
+        Self { label, sequence, qualities: Some(qualities) }
     }
 
     pub fn from_string(label: String, seq_string: String) -> Self {
@@ -31,7 +93,7 @@ impl Seq {
     }
 
     pub fn label_str(&self) -> &str {
-        &self.label.as_str()
+        self.label.as_str()
     }
 
     /// Get the sequence length.
@@ -41,49 +103,106 @@ impl Seq {
 
     /// Parse `Seq`s from a FASTA string.
     pub fn from_fasta(fasta: String) -> Vec<Self> {
-        // vector to collect all sequences in this fasta
-        let mut seqs = Vec::new();
+        match Self::parse_fasta(fasta.as_bytes()) {
+            Ok(seqs) => seqs,
+            Err(e) => panic!("Unable to parse FASTA: {e}"),
+        }
+    }
+
+    /// Read `Seq`s from a FASTA file. Gzip and bgzip compressed files are decompressed transparently.
+    pub fn from_file(file: &String) -> Vec<Self> {
+        match Self::parse_fasta(open_file(file)) {
+            Ok(seqs) => seqs,
+            Err(e) => panic!("Unable to parse FASTA file {file}: {e}"),
+        }
+    }
 
-        // defined outside the loop, so we can reference it between iterations
-        let mut label_string = String::new();
-
-        // we group lines by wether they are labels, i.e., start with `>`, or not
-        let groups = &fasta.lines().into_iter().chunk_by(|l| l.starts_with(">"));
-        for (is_label, lines_group) in groups {
-            // collect the actual lines into a vector
-            let lines = lines_group.collect::<Vec<&str>>();
-            if is_label {
-                // this is a label group
-                if lines.len() > 1 {
-                    // throw if the fasta has more than one label line
-                    panic!("More than one label line:\n{:?}", lines)
-                }
-
-                // remove the leading `>` and trim start and end
-                let clean_label = lines[0][1..].trim();
-                // set the label string
-                label_string = String::from(clean_label);
-            } else {
-                // this group contains sequence data
-                // clone the label, so we can move it
-                let label = label_string.clone();
-                // get the sequence data as bytes
-                let sequence = Vec::from(lines.join("").as_bytes());
-
-                // construct a Seq and push it into the sequence vector
-                seqs.push(Self { label, sequence });
+    /// Parse `Seq`s from a reader of FASTA formatted data.
+    /// Returns an error with the offending line number, if the data is malformed.
+    pub fn parse_fasta<R: BufRead>(reader: R) -> Result<Vec<Self>, ParseError> {
+        FastaReader::new(reader).collect()
+    }
+
+    /// Parse `Seq`s with qualities from a FASTQ string.
+    pub fn from_fastq(fastq: String) -> Vec<Self> {
+        match Self::parse_fastq(fastq.as_bytes()) {
+            Ok(seqs) => seqs,
+            Err(e) => panic!("Unable to parse FASTQ: {e}"),
+        }
+    }
+
+    /// Read `Seq`s with qualities from a FASTQ file. Gzip compressed files are decompressed transparently.
+    pub fn from_fastq_file(file: &String) -> Vec<Self> {
+        match Self::parse_fastq(open_file(file)) {
+            Ok(seqs) => seqs,
+            Err(e) => panic!("Unable to parse FASTQ file {file}: {e}"),
+        }
+    }
+
+    /// Parse `Seq`s with qualities from a reader of FASTQ formatted data.
+    /// Returns an error with the offending line number, if the data is malformed.
+    pub fn parse_fastq<R: BufRead>(reader: R) -> Result<Vec<Self>, ParseError> {
+        FastqReader::new(reader).collect()
+    }
+
+    /// Write `Seq`s with qualities into a new FASTQ file. Files with a `.gz` suffix are gzip compressed.
+    pub fn write_fastq(seqs: &[Self], file: &String) {
+        let mut writer = create_file(file);
+        for seq in seqs {
+            if let Err(e) = writer.write_all(seq.to_fastq().as_bytes()) {
+                panic!("Failed to write {file}: {e}");
             }
         }
+        if let Err(e) = writer.flush() {
+            panic!("Failed to write {file}: {e}");
+        }
+    }
 
-        seqs
+    /// Whether a FASTA file has a `.fai` index, allowing for random access.
+    pub fn has_index(file: &String) -> bool {
+        Path::new(&format!("{file}.fai")).exists()
     }
 
-    pub fn from_file(file: &String) -> Vec<Self> {
-        let content = match fs::read_to_string(file) {
-            Ok(content) => content,
-            Err(e) => panic!("{e}"),
+    /// Read the `Seq`s with the passed names from an indexed FASTA file, skipping all others.
+    ///
+    /// Needs a `.fai` index, and additionally a `.gzi` index for bgzip compressed files.
+    /// The index only holds sequence names, i.e. the first word of the FASTA labels,
+    /// so these are used as labels of the returned `Seq`s.
+    /// Like in `from_file`, the sequences are validated against the IUPAC nucleotide alphabet.
+    pub fn from_indexed_file(file: &String, names: &HashSet<String>) -> Vec<Self> {
+        let reader = match faidx::Reader::from_path(file) {
+            Ok(reader) => reader,
+            Err(e) => panic!("Unable to open indexed FASTA file {file}: {e}"),
         };
-        Self::from_fasta(content)
+
+        let mut seqs = Vec::new();
+        for i in 0..reader.n_seqs() {
+            let name = match reader.seq_name(i as i32) {
+                Ok(name) => name,
+                Err(e) => panic!("Malformed index for FASTA file {file}: {e}"),
+            };
+            if !names.contains(&name) {
+                continue;
+            }
+
+            let len = reader.fetch_seq_len(&name) as usize;
+            let sequence = match reader.fetch_seq(&name, 0, len.saturating_sub(1)) {
+                Ok(bytes) if len > 0 => bytes.to_vec(),
+                Ok(_) => Vec::new(),
+                Err(e) => panic!("Unable to fetch sequence '{name}' from {file}: {e}"),
+            };
+            if let Some(pos) = sequence.iter().position(|base| !alphabet::is_iupac_nucleotide(*base)) {
+                panic!(
+                    "Unable to parse FASTA file {file}: Invalid nucleotide '{}' in position {} of '{name}'",
+                    String::from_utf8_lossy(&sequence[pos..=pos]),
+                    pos + 1
+                );
+            }
+
+            seqs.push(Self::new(name, sequence));
+        }
+
+        seqs
     }
 
     /// Get the label of the `Seq`.
@@ -94,22 +213,129 @@ impl Seq {
     pub fn get_sequence(&self) -> &Vec<u8> {
         &self.sequence
     }
+    pub fn get_qualities(&self) -> Option<&Vec<u8>> {
+        self.qualities.as_ref()
+    }
     pub fn get_sequence_as_string(&self) -> String {
         String::from_utf8(self.sequence.clone()).unwrap()
     }
+
+    /// Get the reverse complement, with IUPAC ambiguity codes complemented, and qualities reversed.
+    pub fn reverse_complement(&self) -> Self {
+        let sequence = self.sequence.iter().rev().map(|base| alphabet::complement(*base)).collect();
+        let qualities = self.qualities.as_ref().map(|q| q.iter().rev().copied().collect());
+        Self { label: self.label.clone(), sequence, qualities }
+    }
+
+    /// Get the subsequence between `start` (inclusive) and `end` (exclusive), keeping the label.
+    pub fn subseq(&self, start: usize, end: usize) -> Self {
+        if start > end || end > self.len() {
+            panic!("Invalid range {start}..{end} for '{}' of length {}.", self.label, self.len());
+        }
+        let sequence = self.sequence[start..end].to_vec();
+        let qualities = self.qualities.as_ref().map(|q| q[start..end].to_vec());
+        Self { label: self.label.clone(), sequence, qualities }
+    }
+
+    /// Get a `Seq` consisting of the bases (and qualities) at the passed positions, keeping the label.
+    fn select<I: Iterator<Item = usize> + Clone>(&self, positions: I) -> Self {
+        let sequence = positions.clone().map(|i| self.sequence[i]).collect();
+        let qualities = self.qualities.as_ref().map(|q| positions.map(|i| q[i]).collect());
+        Self { label: self.label.clone(), sequence, qualities }
+    }
+
+    /// Translate the sequence into amino acids, starting at the first base.
+    /// Stop codons are translated to `*`, and codons that can't be translated unambiguously to `X`.
+    pub fn translate(&self, code: &GeneticCode) -> String {
+        let amino_acids = code.translate(&self.sequence);
+        String::from_utf8(amino_acids).unwrap()
+    }
+
+    /// Fraction of `G`, `C` and `S` bases, relative to all bases that are not `N` or gaps.
+    pub fn gc_content(&self) -> f64 {
+        let mut gc = 0;
+        let mut total = 0;
+        for base in &self.sequence {
+            match base.to_ascii_uppercase() {
+                b'G' | b'C' | b'S' => gc += 1,
+                b'N' | b'-' => continue,
+                _ => {}
+            }
+            total += 1;
+        }
+
+        if total == 0 {
+            return 0.0;
+        }
+        gc as f64 / total as f64
+    }
+
+    /// Number of `N` bases.
+    pub fn n_count(&self) -> usize {
+        self.sequence.iter().filter(|base| base.eq_ignore_ascii_case(&b'N')).count()
+    }
+
+    /// Number of bases with an IUPAC ambiguity code, including `N`.
+    pub fn ambiguity_count(&self) -> usize {
+        self.sequence.iter().filter(|base| alphabet::is_ambiguous(**base)).count()
+    }
+
+    /// Get a copy with all bases in upper case.
+    pub fn to_uppercase(&self) -> Self {
+        let mut seq = self.clone();
+        seq.sequence.make_ascii_uppercase();
+        seq
+    }
+
+    /// Get a copy with all bases in lower case.
+    pub fn to_lowercase(&self) -> Self {
+        let mut seq = self.clone();
+        seq.sequence.make_ascii_lowercase();
+        seq
+    }
+
+    /// Convert a `Seq` into a FASTQ string. Panics if the `Seq` has no qualities.
+    pub fn to_fastq(&self) -> String {
+        let qualities = match &self.qualities {
+            Some(qualities) => qualities,
+            None => panic!("'{}' has no qualities and can't be converted to FASTQ.", self.label),
+        };
+
+        let mut fastq = String::with_capacity(self.label.len() + 2 * self.len() + 6);
+        fastq.push('@');
+        fastq.push_str(self.label.as_str());
+        fastq.push('\n');
+        fastq.push_str(String::from_utf8_lossy(&self.sequence).as_ref());
+        fastq.push_str("\n+\n");
+        for quality in qualities {
+            // clamp, so qualities stay printable
+            fastq.push((quality.min(&MAX_PHRED) + PHRED_OFFSET) as char);
+        }
+        fastq.push('\n');
+
+        fastq
+    }
 }
 
 #[pymethods]
 impl Seq {
     #[new]
-    fn py_new(label: String, sequence: String) -> Self {
-        Self::from_string(label, sequence)
+    #[pyo3(signature = (label, sequence, qualities = None))]
+    fn py_new(label: String, sequence: String, qualities: Option<Vec<u8>>) -> PyResult<Self> {
+        let seq = Self::from_string(label, sequence);
+        match qualities {
+            Some(qualities) if qualities.len() != seq.len() => Err(PyValueError::new_err(
+                "Number of qualities does not match sequence length.",
+            )),
+            Some(qualities) => Ok(Self::with_qualities(seq.label, seq.sequence, qualities)),
+            None => Ok(seq),
+        }
     }
 
     #[classmethod]
     #[pyo3(name = "from_fasta")]
-    fn py_from_fasta(_cls: &Bound<'_, PyType>, fasta: String) -> Vec<Self> {
-        Self::from_fasta(fasta)
+    fn py_from_fasta(_cls: &Bound<'_, PyType>, fasta: String) -> PyResult<Vec<Self>> {
+        Ok(Self::parse_fasta(fasta.as_bytes())?)
     }
 
     /// Get the sequence data as a string, not a vector.
@@ -120,10 +346,47 @@ impl Seq {
         String::from_utf8(seq_bytes).unwrap()
     }
 
+    /// Get the Phred quality scores as a list of integers, if there are any.
+    #[getter]
+    #[pyo3(name = "qualities")]
+    fn py_qualities(&self) -> Option<Vec<u16>> {
+        let qualities = self.qualities.as_ref()?;
+        Some(qualities.iter().map(|q| *q as u16).collect())
+    }
+
     #[classmethod]
     #[pyo3(name = "from_file")]
-    pub fn py_from_file(_cls: &Bound<'_, PyType>, file: String) -> Vec<Self> {
-        Self::from_file(&file)
+    pub fn py_from_file(_cls: &Bound<'_, PyType>, file: String) -> PyResult<Vec<Self>> {
+        Ok(Self::parse_fasta(open_file(&file))?)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "from_fastq")]
+    fn py_from_fastq(_cls: &Bound<'_, PyType>, fastq: String) -> PyResult<Vec<Self>> {
+        Ok(Self::parse_fastq(fastq.as_bytes())?)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "from_fastq_file")]
+    fn py_from_fastq_file(_cls: &Bound<'_, PyType>, file: String) -> PyResult<Vec<Self>> {
+        Ok(Self::parse_fastq(open_file(&file))?)
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "write_fastq")]
+    fn py_write_fastq(seqs: Vec<Self>, file: String) -> PyResult<()> {
+        if let Some(seq) = seqs.iter().find(|seq| seq.qualities.is_none()) {
+            let msg = format!("'{}' has no qualities and can't be written as FASTQ.", seq.label);
+            return Err(PyValueError::new_err(msg));
+        }
+        Self::write_fastq(&seqs, &file);
+        Ok(())
+    }
+
+    #[classmethod]
+    #[pyo3(name = "from_indexed_file")]
+    pub fn py_from_indexed_file(_cls: &Bound<'_, PyType>, file: String, names: HashSet<String>) -> Vec<Self> {
+        Self::from_indexed_file(&file, &names)
     }
 
     /// Convert a `Seq` into a FASTA string.
@@ -157,6 +420,87 @@ impl Seq {
         fasta
     }
 
+    /// Convert a `Seq` with qualities into a FASTQ string.
+    #[pyo3(name = "to_fastq")]
+    fn py_to_fastq(&self) -> PyResult<String> {
+        match self.qualities {
+            Some(_) => Ok(self.to_fastq()),
+            None => Err(PyValueError::new_err("Seq has no qualities and can't be converted to FASTQ.")),
+        }
+    }
+
+    #[pyo3(name = "reverse_complement")]
+    fn py_reverse_complement(&self) -> Self {
+        self.reverse_complement()
+    }
+
+    /// Get the subsequence between `start` (inclusive) and `end` (exclusive).
+    #[pyo3(name = "subseq")]
+    fn py_subseq(&self, start: usize, end: usize) -> PyResult<Self> {
+        if start > end || end > self.len() {
+            return Err(PyIndexError::new_err(format!("Invalid range {start}..{end} for sequence of length {}.", self.len())));
+        }
+        Ok(self.subseq(start, end))
+    }
+
+    /// Translate the sequence into amino acids, using the genetic code of the passed NCBI translation table.
+    #[pyo3(name = "translate", signature = (table = 1))]
+    fn py_translate(&self, table: u8) -> PyResult<String> {
+        match GeneticCode::from_id(table) {
+            Some(code) => Ok(self.translate(&code)),
+            None => Err(PyValueError::new_err(format!(
+                "Unknown translation table {table}, known tables are: {:?}",
+                GeneticCode::known_ids()
+            ))),
+        }
+    }
+
+    #[pyo3(name = "gc_content")]
+    fn py_gc_content(&self) -> f64 {
+        self.gc_content()
+    }
+
+    #[pyo3(name = "n_count")]
+    fn py_n_count(&self) -> usize {
+        self.n_count()
+    }
+
+    #[pyo3(name = "ambiguity_count")]
+    fn py_ambiguity_count(&self) -> usize {
+        self.ambiguity_count()
+    }
+
+    /// Get a copy with all bases in upper case.
+    fn upper(&self) -> Self {
+        self.to_uppercase()
+    }
+
+    /// Get a copy with all bases in lower case.
+    fn lower(&self) -> Self {
+        self.to_lowercase()
+    }
+
+    /// Python dunder method for indexing.
+    /// Integer indices return the base as a string, slices return a `Seq`.
+    fn __getitem__<'py>(&self, py: Python<'py>, index: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyAny>> {
+        if let Ok(slice) = index.downcast::<PySlice>() {
+            let indices = slice.indices(self.len() as isize)?;
+            let positions = (0..indices.slicelength)
+                .map(move |i| (indices.start + i as isize * indices.step) as usize);
+            return self.select(positions).into_bound_py_any(py);
+        }
+
+        let i: isize = index.extract()?;
+        let len = self.len() as isize;
+        let i = if i < 0 { i + len } else { i };
+        if i < 0 || i >= len {
+            return Err(PyIndexError::new_err("Seq index out of range"));
+        }
+
+        let base = (self.sequence[i as usize] as char).to_string();
+        base.into_bound_py_any(py)
+    }
+
     /// Python dunder method for `len`.
     /// Returns sequence length, ignoring the label.
     fn __len__(&self) -> usize {
@@ -237,4 +581,106 @@ mod tests {
         assert!(ref_seq_string.starts_with("TGGAAGGGCTAATTCACTCCCAACGAAGACAAGATATCC"));
         assert!(ref_seq_string.ends_with("CCCTCAGACCCTTTTAGTCAGTGTGGAAAATCTCTAGCA"));
     }
+
+    #[test]
+    fn test_seq_from_gzipped_file() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let path = std::env::temp_dir().join("accord_test_seq.fasta.gz");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b">first\nGATT\nACA\n>second\nTTT\n").unwrap();
+        std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+        let seqs = Seq::from_file(&path.to_string_lossy().to_string());
+        assert_eq!(seqs.len(), 2);
+        assert_eq!(seqs[0].label, "first");
+        assert_eq!(seqs[0].get_sequence_as_string(), "GATTACA");
+        assert_eq!(seqs[1].get_sequence_as_string(), "TTT");
+    }
+
+    /// Write a FASTA file with a `.fai` index into the temporary directory, and get its path.
+    fn init_indexed_file(file_name: &str, fasta: &str) -> String {
+        let path = std::env::temp_dir().join(format!("{}_{file_name}", std::process::id()));
+        std::fs::write(&path, fasta).unwrap();
+        faidx::build(&path).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_seq_from_indexed_file() {
+        let path = init_indexed_file("indexed.fasta", ">first label\nGATT\nACA\n>second\nTTT\n");
+        let names = HashSet::from([String::from("first")]);
+        let seqs = Seq::from_indexed_file(&path, &names);
+        std::fs::remove_file(format!("{path}.fai")).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(seqs, vec![Seq::from_string(String::from("first"), String::from("GATTACA"))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid nucleotide 'J' in position 3 of 'second'")]
+    fn test_seq_from_indexed_file_invalid() {
+        let path = init_indexed_file("invalid.fasta", ">first\nGATT\n>second\nTTJ\n");
+        let names = HashSet::from([String::from("second")]);
+        let seqs = std::panic::catch_unwind(|| Seq::from_indexed_file(&path, &names));
+        std::fs::remove_file(format!("{path}.fai")).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::panic::resume_unwind(seqs.unwrap_err());
+    }
+
+    #[test]
+    fn test_seq_fastq_gzipped_round_trip() {
+        let path = std::env::temp_dir().join("accord_test_seq.fastq.gz");
+        let path = path.to_string_lossy().to_string();
+        let _ = std::fs::remove_file(&path);
+
+        let seqs = vec![
+            Seq::with_qualities(String::from("read1"), b"GATTACA".to_vec(), vec![40, 30, 20, 10, 0, 1, 2]),
+            Seq::with_qualities(String::from("read2"), b"AC".to_vec(), vec![5, 6]),
+        ];
+        Seq::write_fastq(&seqs, &path);
+
+        let read = Seq::from_fastq_file(&path);
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].get_sequence_as_string(), "GATTACA");
+        assert_eq!(read[0].get_qualities(), seqs[0].get_qualities());
+        assert_eq!(read[1].get_qualities(), Some(&vec![5, 6]));
+    }
+
+    #[test]
+    fn test_seq_reverse_complement() {
+        let seq = Seq::with_qualities(String::from("read"), b"GATTRCAn".to_vec(), vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        let rev_comp = seq.reverse_complement();
+
+        assert_eq!(rev_comp.get_sequence_as_string(), "nTGYAATC");
+        assert_eq!(rev_comp.get_qualities(), Some(&vec![8, 7, 6, 5, 4, 3, 2, 1]));
+        assert_eq!(rev_comp.reverse_complement(), seq);
+    }
+
+    #[test]
+    fn test_seq_subseq() {
+        let seq = init_seq();
+        assert_eq!(seq.subseq(1, 4).get_sequence_as_string(), "ATT");
+        assert_eq!(seq.subseq(7, 7).len(), 0);
+        assert_eq!(seq.select([6, 4, 2, 0].into_iter()).get_sequence_as_string(), "AATG");
+    }
+
+    #[test]
+    fn test_seq_metrics() {
+        let seq = Seq::from_string(String::from("metrics"), String::from("GGCCsATNNRY-"));
+        assert_eq!(seq.n_count(), 2);
+        assert_eq!(seq.ambiguity_count(), 5);
+        assert_eq!(seq.gc_content(), 5.0 / 9.0);
+        assert_eq!(seq.to_lowercase().get_sequence_as_string(), "ggccsatnnry-");
+        assert_eq!(seq.to_uppercase().get_sequence_as_string(), "GGCCSATNNRY-");
+    }
+
+    #[test]
+    fn test_seq_translate() {
+        let seq = Seq::from_string(String::from("orf"), String::from("ATGTGAAGA"));
+        assert_eq!(seq.translate(&GeneticCode::standard()), "M*R");
+        assert_eq!(seq.translate(&GeneticCode::from_id(2).unwrap()), "MW*");
+    }
 }
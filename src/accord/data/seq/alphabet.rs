@@ -0,0 +1,85 @@
+//! Helpers for working with the IUPAC nucleotide alphabet.
+
+/// Characters of the IUPAC nucleotide alphabet, including the gap character `-`.
+pub const IUPAC_NUCLEOTIDES: &[u8] = b"ACGTURYSWKMBDHVN-";
+
+/// Whether a byte is part of the IUPAC nucleotide alphabet, ignoring case.
+pub fn is_iupac_nucleotide(byte: u8) -> bool {
+    IUPAC_NUCLEOTIDES.contains(&byte.to_ascii_uppercase())
+}
+
+/// Whether a nucleotide is an ambiguity code, i.e. stands for more than one base, ignoring case.
+/// This includes `N`.
+pub fn is_ambiguous(byte: u8) -> bool {
+    expand(byte).len() > 1
+}
+
+/// Get the complement of an IUPAC nucleotide, preserving case.
+/// Both `T` and `U` are complemented to `A`. Unknown characters are returned unchanged.
+pub fn complement(byte: u8) -> u8 {
+    let complement = match byte.to_ascii_uppercase() {
+        b'A' => b'T',
+        b'C' => b'G',
+        b'G' => b'C',
+        b'T' | b'U' => b'A',
+        b'R' => b'Y',
+        b'Y' => b'R',
+        b'K' => b'M',
+        b'M' => b'K',
+        b'B' => b'V',
+        b'V' => b'B',
+        b'D' => b'H',
+        b'H' => b'D',
+        // `S`, `W`, `N` and the gap character are their own complement
+        _ => return byte,
+    };
+
+    if byte.is_ascii_lowercase() {
+        complement.to_ascii_lowercase()
+    } else {
+        complement
+    }
+}
+
+/// Get the unambiguous bases an IUPAC nucleotide stands for, ignoring case.
+/// E.g. `R` expands to `AG`, and `U` to `T`. Gaps and unknown characters expand to nothing.
+pub fn expand(byte: u8) -> &'static [u8] {
+    match byte.to_ascii_uppercase() {
+        b'A' => b"A",
+        b'C' => b"C",
+        b'G' => b"G",
+        b'T' | b'U' => b"T",
+        b'R' => b"AG",
+        b'Y' => b"CT",
+        b'S' => b"CG",
+        b'W' => b"AT",
+        b'K' => b"GT",
+        b'M' => b"AC",
+        b'B' => b"CGT",
+        b'D' => b"AGT",
+        b'H' => b"ACT",
+        b'V' => b"ACG",
+        b'N' => b"ACGT",
+        _ => b"",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alphabet_complement() {
+        let complemented = b"ACGTURYKMBVDHSWN-acgtn".map(complement);
+        assert_eq!(&complemented, b"TGCAAYRMKVBHDSWN-tgcan");
+    }
+
+    #[test]
+    fn alphabet_ambiguity() {
+        assert!(is_ambiguous(b'N'));
+        assert!(is_ambiguous(b'r'));
+        assert!(!is_ambiguous(b'A'));
+        assert!(!is_ambiguous(b'U'));
+        assert!(!is_ambiguous(b'-'));
+    }
+}
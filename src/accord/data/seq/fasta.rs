@@ -0,0 +1,160 @@
+//! Streaming parser for FASTA formatted sequence data.
+
+use std::io::BufRead;
+use std::mem;
+
+use super::alphabet::is_iupac_nucleotide;
+use super::{ParseError, Seq};
+
+/// An iterator over the records of FASTA formatted data, which parses records one at a time.
+///
+/// Handles `\n` and `\r\n` line endings, blank lines, `;` comment lines, whitespace within
+/// sequence lines, and records without sequence data.
+/// Sequence data is validated against the IUPAC nucleotide alphabet.
+pub struct FastaReader<R: BufRead> {
+    /// The underlying reader.
+    reader: R,
+
+    /// Number of the last line that was read, starting at one.
+    line_no: usize,
+
+    /// Label of the record that is currently being parsed.
+    label: Option<String>,
+
+    /// Sequence data of the record that is currently being parsed.
+    sequence: Vec<u8>,
+
+    /// Buffer for the current line.
+    buf: Vec<u8>,
+
+    /// Whether the end of the data, or an error, was encountered.
+    done: bool,
+}
+
+impl<R: BufRead> FastaReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader, line_no: 0, label: None, sequence: Vec::new(), buf: Vec::new(), done: false }
+    }
+
+    /// Finish the current record, if there is one.
+    fn take_record(&mut self) -> Option<Seq> {
+        let label = self.label.take()?;
+        let sequence = mem::take(&mut self.sequence);
+        Some(Seq::new(label, sequence))
+    }
+
+    fn error(&mut self, message: String) -> Option<Result<Seq, ParseError>> {
+        self.done = true;
+        Some(Err(ParseError::new(self.line_no, message)))
+    }
+}
+
+impl<R: BufRead> Iterator for FastaReader<R> {
+    type Item = Result<Seq, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        loop {
+            self.buf.clear();
+            match self.reader.read_until(b'\n', &mut self.buf) {
+                Ok(0) => {
+                    // end of data, emit the last record
+                    self.done = true;
+                    return self.take_record().map(Ok);
+                }
+                Ok(_) => self.line_no += 1,
+                Err(e) => return self.error(format!("Unable to read line: {e}")),
+            }
+
+            // strip line endings and surrounding whitespace, then skip blank and comment lines
+            let line = self.buf.trim_ascii().to_vec();
+            if line.is_empty() || line.starts_with(b";") {
+                continue;
+            }
+
+            if let Some(label) = line.strip_prefix(b">") {
+                let label = String::from_utf8_lossy(label).trim().to_string();
+                if label.is_empty() {
+                    return self.error(String::from("Empty label"));
+                }
+
+                // a header finishes the previous record, if there is one
+                let previous = self.take_record();
+                self.label = Some(label);
+                match previous {
+                    Some(seq) => return Some(Ok(seq)),
+                    None => continue,
+                }
+            }
+
+            if self.label.is_none() {
+                return self.error(String::from("Sequence data before the first label"));
+            }
+
+            for (i, byte) in line.into_iter().enumerate() {
+                if byte.is_ascii_whitespace() {
+                    continue;
+                }
+                if !is_iupac_nucleotide(byte) {
+                    let column = i + 1;
+                    let character = String::from_utf8_lossy(&[byte]).to_string();
+                    return self.error(format!("Invalid nucleotide '{character}' in column {column}"));
+                }
+                self.sequence.push(byte);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(fasta: &str) -> Result<Vec<Seq>, ParseError> {
+        FastaReader::new(fasta.as_bytes()).collect()
+    }
+
+    #[test]
+    fn fasta_crlf_blank_lines_and_comments() {
+        let fasta = "; a comment\r\n>first record\r\nGATT\r\n\r\n  ACA \r\n;another comment\n>second\nTT T\n";
+        let seqs = parse(fasta).unwrap();
+
+        assert_eq!(seqs.len(), 2);
+        assert_eq!(seqs[0].get_label(), "first record");
+        assert_eq!(seqs[0].get_sequence_as_string(), "GATTACA");
+        assert_eq!(seqs[1].get_label(), "second");
+        assert_eq!(seqs[1].get_sequence_as_string(), "TTT");
+    }
+
+    #[test]
+    fn fasta_empty_records() {
+        let seqs = parse(">empty\n>full\nACGT\n>trailing\n").unwrap();
+
+        assert_eq!(seqs.len(), 3);
+        assert_eq!(seqs[0].len(), 0);
+        assert_eq!(seqs[1].get_sequence_as_string(), "ACGT");
+        assert_eq!(seqs[2].len(), 0);
+    }
+
+    #[test]
+    fn fasta_sequence_before_label() {
+        let err = parse("\nACGT\n>label\nACGT\n").unwrap_err();
+        assert_eq!(err.get_line(), 2);
+    }
+
+    #[test]
+    fn fasta_empty_label() {
+        let err = parse(">label\nACGT\n>  \nACGT\n").unwrap_err();
+        assert_eq!(err.get_line(), 3);
+    }
+
+    #[test]
+    fn fasta_invalid_nucleotide() {
+        let err = parse(">label\nACGT\nacgtnRYKM\nACGTJ\n").unwrap_err();
+        assert_eq!(err.get_line(), 4);
+        assert!(err.to_string().contains("'J' in column 5"));
+    }
+}
@@ -0,0 +1,174 @@
+//! Streaming parser for FASTQ formatted sequence data.
+
+use std::io::BufRead;
+
+use super::alphabet::is_iupac_nucleotide;
+use super::{ParseError, Seq};
+
+/// Offset of Phred quality scores in their ASCII representation (Sanger/Illumina 1.8+).
+pub const PHRED_OFFSET: u8 = 33;
+
+/// Highest Phred quality score, that can be represented in FASTQ.
+pub const MAX_PHRED: u8 = b'~' - PHRED_OFFSET;
+
+/// An iterator over the records of FASTQ formatted data, which parses records one at a time.
+///
+/// Records consist of four lines: a `@` header, the sequence, a `+` separator and the qualities.
+/// Handles `\n` and `\r\n` line endings and blank lines between records.
+/// Sequence data is validated against the IUPAC nucleotide alphabet.
+pub struct FastqReader<R: BufRead> {
+    /// The underlying reader.
+    reader: R,
+
+    /// Number of the last line that was read, starting at one.
+    line_no: usize,
+
+    /// Buffer for the current line.
+    buf: Vec<u8>,
+
+    /// Whether the end of the data, or an error, was encountered.
+    done: bool,
+}
+
+impl<R: BufRead> FastqReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader, line_no: 0, buf: Vec::new(), done: false }
+    }
+
+    /// Read the next line with line endings and surrounding whitespace stripped.
+    /// Returns `Ok(None)` at the end of the data.
+    fn read_line(&mut self) -> Result<Option<Vec<u8>>, ParseError> {
+        self.buf.clear();
+        match self.reader.read_until(b'\n', &mut self.buf) {
+            Ok(0) => Ok(None),
+            Ok(_) => {
+                self.line_no += 1;
+                Ok(Some(self.buf.trim_ascii().to_vec()))
+            }
+            Err(e) => Err(self.error(format!("Unable to read line: {e}"))),
+        }
+    }
+
+    /// Read the next line of a record, treating the end of the data as an error.
+    fn expect_line(&mut self, expected: &str) -> Result<Vec<u8>, ParseError> {
+        match self.read_line()? {
+            Some(line) => Ok(line),
+            None => Err(self.error(format!("Unexpected end of data, expected {expected}"))),
+        }
+    }
+
+    fn error(&self, message: String) -> ParseError {
+        ParseError::new(self.line_no, message)
+    }
+
+    fn parse_record(&mut self) -> Result<Option<Seq>, ParseError> {
+        // skip blank lines between records
+        let header = loop {
+            match self.read_line()? {
+                None => return Ok(None),
+                Some(line) if line.is_empty() => continue,
+                Some(line) => break line,
+            }
+        };
+
+        let label = match header.strip_prefix(b"@") {
+            Some(label) => String::from_utf8_lossy(label).trim().to_string(),
+            None => return Err(self.error(String::from("Expected a header starting with '@'"))),
+        };
+        if label.is_empty() {
+            return Err(self.error(String::from("Empty label")));
+        }
+
+        let sequence = self.expect_line("sequence data")?;
+        if let Some(i) = sequence.iter().position(|byte| !is_iupac_nucleotide(*byte)) {
+            let character = String::from_utf8_lossy(&sequence[i..=i]).to_string();
+            return Err(self.error(format!("Invalid nucleotide '{character}' in column {}", i + 1)));
+        }
+
+        let separator = self.expect_line("a separator starting with '+'")?;
+        if !separator.starts_with(b"+") {
+            return Err(self.error(String::from("Expected a separator starting with '+'")));
+        }
+
+        let quality_chars = self.expect_line("quality data")?;
+        if quality_chars.len() != sequence.len() {
+            return Err(self.error(format!(
+                "Number of qualities ({}) does not match sequence length ({})",
+                quality_chars.len(),
+                sequence.len()
+            )));
+        }
+
+        let mut qualities = Vec::with_capacity(quality_chars.len());
+        for (i, char) in quality_chars.into_iter().enumerate() {
+            if !(PHRED_OFFSET..=b'~').contains(&char) {
+                return Err(self.error(format!("Invalid quality character in column {}", i + 1)));
+            }
+            qualities.push(char - PHRED_OFFSET);
+        }
+
+        Ok(Some(Seq::with_qualities(label, sequence, qualities)))
+    }
+}
+
+impl<R: BufRead> Iterator for FastqReader<R> {
+    type Item = Result<Seq, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result = self.parse_record();
+        if !matches!(result, Ok(Some(_))) {
+            self.done = true;
+        }
+        result.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(fastq: &str) -> Result<Vec<Seq>, ParseError> {
+        FastqReader::new(fastq.as_bytes()).collect()
+    }
+
+    #[test]
+    fn fastq_records() {
+        let fastq = "@read1 extra\r\nGATTACA\r\n+\r\nII5+!~I\r\n\n@read2\nAC\n+read2\n##\n";
+        let seqs = parse(fastq).unwrap();
+
+        assert_eq!(seqs.len(), 2);
+        assert_eq!(seqs[0].get_label(), "read1 extra");
+        assert_eq!(seqs[0].get_sequence_as_string(), "GATTACA");
+        assert_eq!(seqs[0].get_qualities(), Some(&vec![40, 40, 20, 10, 0, MAX_PHRED, 40]));
+        assert_eq!(seqs[1].get_qualities(), Some(&vec![2, 2]));
+    }
+
+    #[test]
+    fn fastq_round_trip() {
+        let fastq = "@read1\nGATTACA\n+\nII5+!~I\n";
+        let seqs = parse(fastq).unwrap();
+        assert_eq!(seqs[0].to_fastq(), fastq);
+    }
+
+    #[test]
+    fn fastq_quality_length_mismatch() {
+        let err = parse("@read1\nGATTACA\n+\nIII\n").unwrap_err();
+        assert_eq!(err.get_line(), 4);
+    }
+
+    #[test]
+    fn fastq_truncated_record() {
+        let err = parse("@read1\nGATTACA\n").unwrap_err();
+        assert_eq!(err.get_line(), 2);
+    }
+
+    #[test]
+    fn fastq_missing_separator() {
+        let err = parse("@read1\nGATTACA\nIIIIIII\nIIIIIII\n").unwrap_err();
+        assert_eq!(err.get_line(), 3);
+    }
+}
@@ -0,0 +1,134 @@
+//! Genetic codes for translating nucleotide sequences into amino acid sequences.
+
+use super::alphabet::expand;
+
+/// NCBI translation tables as triples of ID, name and the amino acids encoded by all 64 codons.
+/// Codons are ordered by their bases, with `TCAG` order for each position, i.e. `TTT`, `TTC`, `TTA`, ..., `GGG`.
+const TRANSLATION_TABLES: [(u8, &str, &str); 12] = [
+    (1, "Standard", "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (2, "Vertebrate Mitochondrial", "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG"),
+    (3, "Yeast Mitochondrial", "FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (4, "Mold, Protozoan, and Coelenterate Mitochondrial", "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (5, "Invertebrate Mitochondrial", "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG"),
+    (6, "Ciliate, Dasycladacean and Hexamita Nuclear", "FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (9, "Echinoderm and Flatworm Mitochondrial", "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG"),
+    (10, "Euplotid Nuclear", "FFLLSSSSYY**CCCWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (11, "Bacterial, Archaeal and Plant Plastid", "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (12, "Alternative Yeast Nuclear", "FFLLSSSSYY**CC*WLLLSPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"),
+    (13, "Ascidian Mitochondrial", "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSGGVVVVAAAADDEEGGGG"),
+    (14, "Alternative Flatworm Mitochondrial", "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG"),
+];
+
+/// Character for codons that can't be translated unambiguously.
+pub const UNKNOWN: u8 = b'X';
+
+/// Character for stop codons.
+pub const STOP: u8 = b'*';
+
+/// A genetic code, mapping codons to amino acids.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneticCode {
+    /// ID of the corresponding NCBI translation table.
+    id: u8,
+
+    /// Name of the genetic code.
+    name: &'static str,
+
+    /// Amino acids encoded by all 64 codons, in `TCAG` order.
+    amino_acids: &'static [u8],
+}
+
+impl GeneticCode {
+    /// Get the genetic code for an NCBI translation table ID, if it is known.
+    pub fn from_id(id: u8) -> Option<Self> {
+        TRANSLATION_TABLES
+            .iter()
+            .find(|(table_id, _, _)| *table_id == id)
+            .map(|(id, name, amino_acids)| Self { id: *id, name, amino_acids: amino_acids.as_bytes() })
+    }
+
+    /// The standard genetic code.
+    pub fn standard() -> Self {
+        Self::from_id(1).unwrap()
+    }
+
+    /// IDs of all known translation tables.
+    pub fn known_ids() -> Vec<u8> {
+        TRANSLATION_TABLES.iter().map(|(id, _, _)| *id).collect()
+    }
+
+    /// Translate a codon, which may contain ambiguous nucleotides.
+    /// If the possible codons encode different amino acids, or the codon contains gaps, `X` is returned.
+    pub fn translate_codon(&self, codon: &[u8]) -> u8 {
+        let (first, second, third) = match codon {
+            [first, second, third] => (expand(*first), expand(*second), expand(*third)),
+            _ => return UNKNOWN,
+        };
+
+        let mut amino_acid = None;
+        for b1 in first {
+            for b2 in second {
+                for b3 in third {
+                    let index = 16 * Self::base_index(*b1) + 4 * Self::base_index(*b2) + Self::base_index(*b3);
+                    let encoded = self.amino_acids[index];
+                    match amino_acid {
+                        None => amino_acid = Some(encoded),
+                        Some(aa) if aa != encoded => return UNKNOWN,
+                        Some(_) => {}
+                    }
+                }
+            }
+        }
+
+        amino_acid.unwrap_or(UNKNOWN)
+    }
+
+    /// Translate a nucleotide sequence codon by codon. Trailing bases of an incomplete codon are ignored.
+    pub fn translate(&self, sequence: &[u8]) -> Vec<u8> {
+        sequence.chunks_exact(3).map(|codon| self.translate_codon(codon)).collect()
+    }
+
+    /// Position of an unambiguous base in `TCAG` order.
+    fn base_index(base: u8) -> usize {
+        match base {
+            b'T' => 0,
+            b'C' => 1,
+            b'A' => 2,
+            b'G' => 3,
+            _ => unreachable!("Only expanded, unambiguous bases can be indexed."),
+        }
+    }
+
+    pub fn get_id(&self) -> u8 { self.id }
+    pub fn get_name(&self) -> &'static str { self.name }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn genetic_code_standard() {
+        let code = GeneticCode::standard();
+        assert_eq!(code.translate(b"ATGGCCTAAGG"), b"MA*");
+        assert_eq!(code.translate(b"augugg"), b"MW");
+    }
+
+    #[test]
+    fn genetic_code_ambiguity() {
+        let code = GeneticCode::standard();
+        // all four codons encode alanine
+        assert_eq!(code.translate_codon(b"GCN"), b'A');
+        // `GAR` encodes glutamate, `GAY` aspartate
+        assert_eq!(code.translate_codon(b"GAR"), b'E');
+        assert_eq!(code.translate_codon(b"GAN"), UNKNOWN);
+        assert_eq!(code.translate_codon(b"G-A"), UNKNOWN);
+    }
+
+    #[test]
+    fn genetic_code_alternative() {
+        let mito = GeneticCode::from_id(2).unwrap();
+        assert_eq!(mito.translate(b"TGAATAAGA"), b"WM*");
+        assert!(GeneticCode::from_id(7).is_none());
+    }
+}
@@ -1,6 +1,7 @@
 //! This module provides structs for working with, and summarizing statistical data concerning aligned reads.
 
-use itertools::Itertools;
+use std::collections::BTreeMap;
+
 use log::warn;
 use pyo3::types::PyType;
 use pyo3::{pyclass, pymethods, Bound};
@@ -72,6 +73,29 @@ impl AlnData {
     }
 }
 
+/// Number of occurrences per value of the relevant data for aligned reads, from which `AlnStats` are computed.
+///
+/// Unlike a list of `AlnData`, it grows with the number of distinct values, not with the number of alignments.
+/// E.g., mapping qualities take at most 256 distinct values, and read lengths at most as many as there are reads
+/// of different length.
+#[derive(Debug, Clone, Default)]
+pub struct AlnDataCounts {
+    lengths: BTreeMap<usize, usize>,
+    qualities: BTreeMap<usize, usize>,
+    scores: BTreeMap<usize, usize>,
+    distances: BTreeMap<usize, usize>,
+}
+
+impl AlnDataCounts {
+    /// Count the data of an aligned read.
+    pub fn add(&mut self, data: &AlnData) {
+        *self.lengths.entry(data.length).or_default() += 1;
+        *self.qualities.entry(data.mapq as usize).or_default() += 1;
+        *self.scores.entry(data.score).or_default() += 1;
+        *self.distances.entry(data.distance).or_default() += 1;
+    }
+}
+
 /// Struct for quantile data of unsigned integral values.
 ///
 /// Combines a quantile factor and the quantile value.
@@ -89,6 +113,11 @@ pub struct Quantile {
     value: usize,
 }
 
+impl Quantile {
+    pub fn get_factor(&self) -> f64 { self.factor }
+    pub fn get_value(&self) -> usize { self.value }
+}
+
 #[pymethods]
 impl Quantile {
     fn __repr__(&self) -> String {
@@ -120,30 +149,38 @@ pub struct DistStats {
 impl DistStats {
     /// Determine the distribution of some numbers.
     pub fn from_numbers(numbers: Vec<usize>, quantile_factors: &Vec<f64>) -> Self {
-        let quantiles = Self::calculate_quants(&numbers, quantile_factors);
-        let sample_size = numbers.len();
+        let mut counts = BTreeMap::new();
+        for num in numbers {
+            *counts.entry(num).or_default() += 1;
+        }
+        Self::from_counts(&counts, quantile_factors)
+    }
+
+    /// Determine the distribution of numbers, given as the number of occurrences per value.
+    pub fn from_counts(counts: &BTreeMap<usize, usize>, quantile_factors: &Vec<f64>) -> Self {
+        let quantiles = Self::calculate_quants(counts, quantile_factors);
+        let sample_size = counts.values().sum::<usize>();
 
-        let total = numbers.iter().sum::<usize>();
+        let total = counts.iter().map(|(num, count)| num * count).sum::<usize>();
         let mean = total as f64 / sample_size as f64;
 
-        let sum_of_squares = numbers.iter().map(|num| {
-            (*num as f64 - mean).powi(2)
+        let sum_of_squares = counts.iter().map(|(num, count)| {
+            (*num as f64 - mean).powi(2) * *count as f64
         }).sum();
 
         Self { quantiles, sample_size, mean, sum_of_squares }
     }
 
-    fn calculate_quants(numbers: &Vec<usize>, factors: &Vec<f64>) -> Vec<Quantile> {
-        let n = numbers.len();
-        let sorted_nums = numbers.iter().sorted().collect_vec();
+    pub fn get_quantiles(&self) -> &Vec<Quantile> { &self.quantiles }
+    pub fn get_sample_size(&self) -> usize { self.sample_size }
+    pub fn get_mean(&self) -> f64 { self.mean }
+
+    fn calculate_quants(counts: &BTreeMap<usize, usize>, factors: &Vec<f64>) -> Vec<Quantile> {
+        let n = counts.values().sum::<usize>();
 
         if n < factors.len() {
             warn!("Trying to determine more quantiles than numbers in sequence.");
-            let mut v = Vec::new();
-            for factor in factors {
-                v.push(Quantile { factor: 0.0, value: 0 });
-            }
-            return v;
+            return vec![Quantile { factor: 0.0, value: 0 }; factors.len()];
         }
 
         let mut quantiles = Vec::new();
@@ -155,9 +192,16 @@ impl DistStats {
             // also, we make sure to not undershoot by subtracting, by enforcing a minimum of zero
             let index = rank.saturating_sub(1);
 
+            // find the value at the index of the sorted numbers, by summing up the occurrences of the values
+            let mut seen = 0;
+            let (value, _count) = counts.iter().find(|(_num, count)| {
+                seen += *count;
+                seen > index
+            }).unwrap();
+
             // construct the quantile
-            let factor = factor.clone();
-            let value = sorted_nums[index].clone();
+            let factor = *factor;
+            let value = *value;
             let quantile = Quantile { factor, value };
 
             quantiles.push(quantile);
@@ -224,44 +268,26 @@ pub struct AlnStats {
 
 impl AlnStats {
     pub fn from_data(aln_data: &Vec<AlnData>, quantile_factors: &Vec<f64>) -> Self {
-        let (
-            length_distribution,
-            quality_distribution,
-            score_distribution,
-            editing_distance_distribution
-        ) = Self::calculate_distributions(aln_data, quantile_factors);
-
-        Self {
-            length_distribution,
-            quality_distribution,
-            score_distribution,
-            editing_distance_distribution,
+        let mut counts = AlnDataCounts::default();
+        for data in aln_data {
+            counts.add(data);
         }
+        Self::from_counts(&counts, quantile_factors)
     }
 
-    fn calculate_distributions(aln_data: &Vec<AlnData>, quantile_factors: &Vec<f64>)
-                               -> (DistStats, DistStats, DistStats, DistStats) {
-        // split aln data into seperate vectors
-        let mut lengths = Vec::new();
-        let mut qualities = Vec::new();
-        let mut scores = Vec::new();
-        let mut distances = Vec::new();
-
-        for data in aln_data {
-            lengths.push(data.length);
-            qualities.push(data.mapq as usize);
-            scores.push(data.score);
-            distances.push(data.distance);
+    pub fn from_counts(counts: &AlnDataCounts, quantile_factors: &Vec<f64>) -> Self {
+        Self {
+            length_distribution: DistStats::from_counts(&counts.lengths, quantile_factors),
+            quality_distribution: DistStats::from_counts(&counts.qualities, quantile_factors),
+            score_distribution: DistStats::from_counts(&counts.scores, quantile_factors),
+            editing_distance_distribution: DistStats::from_counts(&counts.distances, quantile_factors),
         }
-
-        // calculate dist stats
-        let length_distribution = DistStats::from_numbers(lengths, quantile_factors);
-        let quality_distribution = DistStats::from_numbers(qualities, quantile_factors);
-        let score_distribution = DistStats::from_numbers(scores, quantile_factors);
-        let editing_distance_distribution = DistStats::from_numbers(distances, quantile_factors);
-
-        (length_distribution, quality_distribution, score_distribution, editing_distance_distribution)
     }
+
+    pub fn get_length_distribution(&self) -> &DistStats { &self.length_distribution }
+    pub fn get_quality_distribution(&self) -> &DistStats { &self.quality_distribution }
+    pub fn get_score_distribution(&self) -> &DistStats { &self.score_distribution }
+    pub fn get_editing_distance_distribution(&self) -> &DistStats { &self.editing_distance_distribution }
 }
 
 #[pymethods]
@@ -272,7 +298,7 @@ impl AlnStats {
         Self::from_data(&data, &factors)
     }
 
-    /// Number of reads that these statistics were generated from.
+    /// Number of alignment records that these statistics were generated from.
     #[getter]
     pub fn sample_size(&self) -> usize {
         self.length_distribution.sample_size
@@ -287,3 +313,114 @@ impl AlnStats {
         )
     }
 }
+
+/// Statistics of UMI families, i.e. reads sharing a UMI, strand and mapping position,
+/// that were collapsed into a single family consensus read.
+#[derive(Debug, Clone)]
+#[pyclass]
+pub struct FamilyStats {
+    /// Number of families.
+    #[pyo3(get)]
+    families: usize,
+
+    /// Number of families, that were discarded for having fewer members than the minimum family size.
+    #[pyo3(get)]
+    discarded_families: usize,
+
+    /// Number of families with a single member.
+    #[pyo3(get)]
+    singletons: usize,
+
+    /// Statistics describing the distribution of family sizes.
+    #[pyo3(get)]
+    size_distribution: DistStats,
+}
+
+impl FamilyStats {
+    /// Summarize the sizes of UMI families.
+    pub fn from_sizes(sizes: Vec<usize>, min_family_size: usize, quantile_factors: &Vec<f64>) -> Self {
+        let families = sizes.len();
+        let discarded_families = sizes.iter().filter(|size| **size < min_family_size).count();
+        let singletons = sizes.iter().filter(|size| **size == 1).count();
+        let size_distribution = DistStats::from_numbers(sizes, quantile_factors);
+
+        Self { families, discarded_families, singletons, size_distribution }
+    }
+
+    pub fn get_families(&self) -> usize { self.families }
+    pub fn get_discarded_families(&self) -> usize { self.discarded_families }
+    pub fn get_singletons(&self) -> usize { self.singletons }
+    pub fn get_size_distribution(&self) -> &DistStats { &self.size_distribution }
+}
+
+#[pymethods]
+impl FamilyStats {
+    fn __repr__(&self) -> String {
+        format!(
+            "FamilyStats(families={}, discarded_families={}, singletons={}, size_distribution={})",
+            self.families, self.discarded_families, self.singletons, self.size_distribution.__repr__(),
+        )
+    }
+}
+
+/// Compute the Wilson score interval for a binomial proportion, e.g. the frequency of an allele.
+///
+/// - `successes: usize`: Number of observations displaying the property.
+/// - `trials: usize`: Total number of observations.
+/// - `z: f64`: Quantile of the standard normal distribution, e.g. `1.96` for a 95 % interval.
+///
+/// Returns the lower and upper bound of the interval. Without observations, the interval is `(0, 1)`.
+pub fn wilson_interval(successes: usize, trials: usize, z: f64) -> (f64, f64) {
+    if trials == 0 {
+        return (0.0, 1.0);
+    }
+
+    let n = trials as f64;
+    let p = (successes as f64 / n).min(1.0);
+    let z2 = z * z;
+
+    let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let margin = z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+
+    ((center - margin).max(0.0), (center + margin).min(1.0))
+}
+
+/// Average consecutive values in equally sized bins, such that there are at most `max_bins` bins.
+///
+/// Returns the zero-based index of the first value in every bin, paired with the mean of the bin.
+pub fn binned_means(values: &[usize], max_bins: usize) -> Vec<(usize, f64)> {
+    let bin_size = values.len().div_ceil(max_bins.max(1)).max(1);
+    values
+        .chunks(bin_size)
+        .enumerate()
+        .map(|(i, bin)| (i * bin_size, bin.iter().sum::<usize>() as f64 / bin.len() as f64))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn family_stats_from_sizes() {
+        let stats = FamilyStats::from_sizes(vec![1, 3, 2, 1, 5], 2, &vec![0.0, 0.5, 1.0]);
+
+        assert_eq!(stats.families, 5);
+        assert_eq!(stats.discarded_families, 2);
+        assert_eq!(stats.singletons, 2);
+        assert_eq!(stats.size_distribution.mean, 2.4);
+        assert_eq!(stats.size_distribution.quantiles.last().unwrap().value, 5);
+    }
+
+    #[test]
+    fn dist_stats_from_counts() {
+        let factors = vec![0.0, 0.2, 0.5, 1.0];
+        let numbers = DistStats::from_numbers(vec![4, 1, 3, 3, 9], &factors);
+        let counts = DistStats::from_counts(&BTreeMap::from([(1, 1), (3, 2), (4, 1), (9, 1)]), &factors);
+
+        let values = |stats: &DistStats| stats.quantiles.iter().map(|quant| quant.value).collect::<Vec<usize>>();
+        assert_eq!(values(&numbers), vec![1, 1, 3, 9]);
+        assert_eq!(values(&counts), values(&numbers));
+        assert_eq!((counts.sample_size, counts.mean, counts.sum_of_squares), (5, 4.0, 36.0));
+    }
+}
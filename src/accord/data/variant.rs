@@ -0,0 +1,409 @@
+//! This module provides the `MinorVariant` struct, describing alleles that were observed in the aligned reads,
+//! but did not make it into the consensus.
+
+use std::fmt::{Display, Formatter};
+
+use pyo3::{pyclass, pymethods};
+
+use super::consensus::AnalysisResult;
+use super::indel::InDel;
+use super::seq::Seq;
+use super::stats::wilson_interval;
+
+/// Quantile of the standard normal distribution, used for 95 % confidence intervals.
+const Z_95: f64 = 1.959964;
+
+/// The type of a minor variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[pyclass(eq, eq_int)]
+pub enum VariantKind {
+    Snv,
+    Insertion,
+    Deletion,
+}
+
+impl Display for VariantKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            VariantKind::Snv => "snv",
+            VariantKind::Insertion => "insertion",
+            VariantKind::Deletion => "deletion",
+        };
+        write!(f, "{kind}")
+    }
+}
+
+/// An allele, that differs from the consensus, with its frequency among the reads covering its position.
+#[derive(Debug, Clone, PartialEq)]
+#[pyclass]
+pub struct MinorVariant {
+    /// Zero-based position on the reference. For insertions, the position left of the inserted bases.
+    #[pyo3(get)]
+    position: usize,
+
+    /// The type of the variant.
+    #[pyo3(get)]
+    kind: VariantKind,
+
+    /// Bases of the reference at the variant site. Empty for insertions.
+    #[pyo3(get)]
+    ref_allele: String,
+
+    /// Bases of the consensus at the variant site, before applying indels. Empty for insertions.
+    #[pyo3(get)]
+    consensus_allele: String,
+
+    /// Bases of the variant. Empty for deletions.
+    #[pyo3(get)]
+    alt_allele: String,
+
+    /// Number of reads displaying the variant.
+    #[pyo3(get)]
+    count: usize,
+
+    /// Number of reads covering the variant site.
+    #[pyo3(get)]
+    depth: usize,
+
+    /// Fraction of reads covering the variant site, that display the variant.
+    #[pyo3(get)]
+    frequency: f64,
+
+    /// Lower bound of the 95 % Wilson score interval of the frequency.
+    #[pyo3(get)]
+    ci_lower: f64,
+
+    /// Upper bound of the 95 % Wilson score interval of the frequency.
+    #[pyo3(get)]
+    ci_upper: f64,
+
+    /// Number of reads on the forward strand displaying the variant.
+    #[pyo3(get)]
+    forward_count: usize,
+
+    /// Number of reads on the reverse strand displaying the variant.
+    #[pyo3(get)]
+    reverse_count: usize,
+}
+
+impl MinorVariant {
+    /// Find all alleles, that differ from the consensus and suffice the frequency and count thresholds.
+    /// Positions, in which the consensus was not called from the reads, have no minor variants.
+    ///
+    /// - `ref_seq: &Seq`: The reference sequence the reads were aligned against.
+    /// - `called_bases: &[Option<u8>]`: The consensus base for every reference position, before applying indels,
+    ///   if it was called from the reads.
+    /// - `applied_indels: &[InDel]`: The indels applied to the consensus. These are not minor variants.
+    /// - `analysis_result: &AnalysisResult`: The counted alleles.
+    /// - `min_frequency: f64`: Minimum fraction of covering reads, that display a variant.
+    /// - `min_count: usize`: Minimum number of reads, that display a variant.
+    ///
+    /// Returns the variants ordered by position.
+    pub fn call(
+        ref_seq: &Seq,
+        called_bases: &[Option<u8>],
+        applied_indels: &[InDel],
+        analysis_result: &AnalysisResult,
+        min_frequency: f64,
+        min_count: usize,
+    ) -> Vec<Self> {
+        let mut variants = Vec::new();
+
+        // single nucleotide variants
+        for (pos, base_counter) in analysis_result.base_counts.iter().enumerate() {
+            let depth = analysis_result.coverage[pos];
+            let consensus_base = match called_bases[pos] {
+                Some(base) => base,
+                None => continue,
+            };
+            for (base, count) in base_counter.iter() {
+                if base.eq_ignore_ascii_case(&consensus_base) || *base == b'N' {
+                    continue;
+                }
+
+                let reverse_count = analysis_result.reverse_base_counts[pos][base];
+                let variant = Self::new(
+                    pos,
+                    VariantKind::Snv,
+                    ref_seq.get_sequence()[pos..=pos].to_vec(),
+                    vec![consensus_base],
+                    vec![*base],
+                    (*count, reverse_count),
+                    depth,
+                );
+                if variant.suffices(min_frequency, min_count) {
+                    variants.push(variant);
+                }
+            }
+        }
+
+        // indels, that were not applied to the consensus
+        for (indel, count) in analysis_result.indel_counts.iter() {
+            if applied_indels.contains(indel) || called_bases[indel.get_start()].is_none() {
+                continue;
+            }
+
+            // deleted bases of the consensus fall back to the reference, where they were not called from the reads
+            let (kind, ref_allele, consensus_allele) = match indel {
+                InDel::Ins(_) => (VariantKind::Insertion, Vec::new(), Vec::new()),
+                InDel::Del(_) => {
+                    let deleted = indel.get_start()..indel.get_stop();
+                    let consensus = deleted.clone().map(|pos| called_bases[pos].unwrap_or(ref_seq[pos])).collect();
+                    (VariantKind::Deletion, ref_seq.get_sequence()[deleted].to_vec(), consensus)
+                }
+            };
+
+            let depth = (indel.spanning_reads(*count, &analysis_result.coverage).round() as usize).max(*count);
+            let reverse_count = analysis_result.reverse_indel_counts[indel];
+            let variant = Self::new(
+                indel.get_start(),
+                kind,
+                ref_allele,
+                consensus_allele,
+                indel.get_seq().to_vec(),
+                (*count, reverse_count),
+                depth,
+            );
+            if variant.suffices(min_frequency, min_count) {
+                variants.push(variant);
+            }
+        }
+
+        variants.sort_by(|a, b| {
+            (a.position, a.kind, &a.alt_allele).cmp(&(b.position, b.kind, &b.alt_allele))
+        });
+        variants
+    }
+
+    fn new(
+        position: usize,
+        kind: VariantKind,
+        ref_allele: Vec<u8>,
+        consensus_allele: Vec<u8>,
+        alt_allele: Vec<u8>,
+        (count, reverse_count): (usize, usize),
+        depth: usize,
+    ) -> Self {
+        let frequency = if depth > 0 { count as f64 / depth as f64 } else { 0.0 };
+        let (ci_lower, ci_upper) = wilson_interval(count, depth, Z_95);
+
+        Self {
+            position,
+            kind,
+            ref_allele: String::from_utf8_lossy(&ref_allele).to_string(),
+            consensus_allele: String::from_utf8_lossy(&consensus_allele).to_string(),
+            alt_allele: String::from_utf8_lossy(&alt_allele).to_string(),
+            count,
+            depth,
+            frequency,
+            ci_lower,
+            ci_upper,
+            forward_count: count - reverse_count,
+            reverse_count,
+        }
+    }
+
+    fn suffices(&self, min_frequency: f64, min_count: usize) -> bool {
+        self.count >= min_count && self.frequency >= min_frequency
+    }
+
+    pub fn get_position(&self) -> usize { self.position }
+    pub fn get_kind(&self) -> VariantKind { self.kind }
+    pub fn get_ref_allele(&self) -> &String { &self.ref_allele }
+    pub fn get_consensus_allele(&self) -> &String { &self.consensus_allele }
+    pub fn get_alt_allele(&self) -> &String { &self.alt_allele }
+    pub fn get_count(&self) -> usize { self.count }
+    pub fn get_depth(&self) -> usize { self.depth }
+    pub fn get_frequency(&self) -> f64 { self.frequency }
+    pub fn get_ci(&self) -> (f64, f64) { (self.ci_lower, self.ci_upper) }
+    pub fn get_forward_count(&self) -> usize { self.forward_count }
+    pub fn get_reverse_count(&self) -> usize { self.reverse_count }
+}
+
+#[pymethods]
+impl MinorVariant {
+    fn __repr__(&self) -> String {
+        format!(
+            "MinorVariant(position={}, kind={}, ref_allele='{}', alt_allele='{}', count={}, depth={}, frequency={:.4})",
+            self.position, self.kind, self.ref_allele, self.alt_allele, self.count, self.depth, self.frequency
+        )
+    }
+}
+
+/// A position, in which the consensus base differs from the reference base.
+#[derive(Debug, Clone, PartialEq)]
+#[pyclass]
+pub struct Substitution {
+    /// Zero-based position on the reference.
+    #[pyo3(get)]
+    position: usize,
+
+    /// Base of the reference.
+    #[pyo3(get)]
+    ref_base: char,
+
+    /// Base of the consensus, before applying indels.
+    #[pyo3(get)]
+    consensus_base: char,
+
+    /// Number of reads displaying the consensus base.
+    #[pyo3(get)]
+    count: usize,
+
+    /// Number of reads covering the position.
+    #[pyo3(get)]
+    depth: usize,
+}
+
+impl Substitution {
+    /// Find all positions, in which the consensus base differs from the reference base.
+    /// Bases are compared case-insensitively, so soft-masked references can be used.
+    ///
+    /// - `ref_seq: &Seq`: The reference sequence the reads were aligned against.
+    /// - `consensus_bases: &[u8]`: The consensus base for every reference position, before applying indels.
+    /// - `analysis_result: &AnalysisResult`: The counted alleles.
+    ///
+    /// Returns the substitutions ordered by position.
+    pub fn call(ref_seq: &Seq, consensus_bases: &[u8], analysis_result: &AnalysisResult) -> Vec<Self> {
+        consensus_bases
+            .iter()
+            .enumerate()
+            .filter(|(pos, base)| !ref_seq[*pos].eq_ignore_ascii_case(base))
+            .map(|(pos, base)| Self {
+                position: pos,
+                ref_base: ref_seq[pos] as char,
+                consensus_base: *base as char,
+                count: analysis_result.base_counts[pos][base],
+                depth: analysis_result.coverage[pos],
+            })
+            .collect()
+    }
+
+    pub fn get_position(&self) -> usize { self.position }
+    pub fn get_ref_base(&self) -> char { self.ref_base }
+    pub fn get_consensus_base(&self) -> char { self.consensus_base }
+    pub fn get_count(&self) -> usize { self.count }
+    pub fn get_depth(&self) -> usize { self.depth }
+
+    /// Fraction of reads covering the position, that display the consensus base.
+    pub fn frequency(&self) -> f64 {
+        if self.depth == 0 {
+            return 0.0;
+        }
+        self.count as f64 / self.depth as f64
+    }
+}
+
+#[pymethods]
+impl Substitution {
+    #[getter(frequency)]
+    fn py_frequency(&self) -> f64 {
+        self.frequency()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Substitution(position={}, ref_base='{}', consensus_base='{}', count={}, depth={})",
+            self.position, self.ref_base, self.consensus_base, self.count, self.depth
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accord::data::indel::{Deletion, Insertion};
+    use counter::Counter;
+    use itertools::Itertools;
+
+    fn init_result() -> AnalysisResult {
+        let mut base_counts = vec![Counter::new(); 4];
+        let mut reverse_base_counts = vec![Counter::new(); 4];
+        base_counts[1][&b'C'] = 90;
+        base_counts[1][&b'T'] = 8;
+        base_counts[1][&b'G'] = 2;
+        reverse_base_counts[1][&b'T'] = 3;
+
+        let mut indel_counts = Counter::new();
+        let mut reverse_indel_counts = Counter::new();
+        indel_counts[&InDel::Ins(Insertion::new(2, b"AA".to_vec()))] = 10;
+        reverse_indel_counts[&InDel::Ins(Insertion::new(2, b"AA".to_vec()))] = 4;
+        indel_counts[&InDel::Del(Deletion::new(2, 3))] = 40;
+
+        let coverage = vec![0, 100, 100, 60];
+        AnalysisResult::new(coverage, base_counts, reverse_base_counts, indel_counts, reverse_indel_counts)
+    }
+
+    #[test]
+    fn minor_variants_thresholds() {
+        let ref_seq = Seq::new(String::from("ref"), b"ACGT".to_vec());
+        let result = init_result();
+        let applied = [InDel::Del(Deletion::new(2, 3))];
+
+        let called = [None, Some(b'C'), Some(b'G'), Some(b'T')];
+        let variants = MinorVariant::call(&ref_seq, &called, &applied, &result, 0.05, 5);
+        assert_eq!(variants.len(), 2);
+
+        let snv = &variants[0];
+        assert_eq!((snv.kind, snv.alt_allele.as_str(), snv.count, snv.depth), (VariantKind::Snv, "T", 8, 100));
+        assert_eq!((snv.forward_count, snv.reverse_count), (5, 3));
+        assert!(snv.ci_lower < 0.08 && 0.08 < snv.ci_upper);
+
+        let ins = &variants[1];
+        assert_eq!((ins.kind, ins.alt_allele.as_str(), ins.position), (VariantKind::Insertion, "AA", 2));
+        assert_eq!((ins.forward_count, ins.reverse_count), (6, 4));
+    }
+
+    #[test]
+    fn minor_variants_of_called_consensus() {
+        let ref_seq = Seq::new(String::from("ref"), b"acgt".to_vec());
+        let result = init_result();
+
+        // without a called consensus base, the alleles are not compared to the reference
+        let variants = MinorVariant::call(&ref_seq, &[None; 4], &[], &result, 0.05, 5);
+        assert!(variants.is_empty());
+
+        // the majority allele is no minor variant on a soft-masked reference,
+        // and the consensus allele of a deletion is taken from the consensus
+        let called = [None, Some(b'C'), Some(b'A'), None];
+        let variants = MinorVariant::call(&ref_seq, &called, &[], &result, 0.05, 5);
+        let alleles = variants.iter().map(|v| (v.kind, v.consensus_allele.as_str(), v.alt_allele.as_str())).collect_vec();
+        assert_eq!(
+            alleles,
+            vec![(VariantKind::Snv, "C", "T"), (VariantKind::Insertion, "", "AA"), (VariantKind::Deletion, "A", "")]
+        );
+    }
+
+    #[test]
+    fn substitutions_against_reference() {
+        let ref_seq = Seq::new(String::from("ref"), b"ACGT".to_vec());
+        let result = init_result();
+
+        let substitutions = Substitution::call(&ref_seq, b"ATGT", &result);
+        assert_eq!(substitutions.len(), 1);
+
+        let snv = &substitutions[0];
+        assert_eq!((snv.position, snv.ref_base, snv.consensus_base), (1, 'C', 'T'));
+        assert_eq!((snv.count, snv.depth), (8, 100));
+        assert_eq!(snv.frequency(), 0.08);
+
+        // soft-masked bases of the reference match the consensus
+        let masked = Seq::new(String::from("ref"), b"AcgT".to_vec());
+        let substitutions = Substitution::call(&masked, b"ATGT", &result);
+        assert_eq!(substitutions.len(), 1);
+        assert_eq!((substitutions[0].position, substitutions[0].ref_base), (1, 'c'));
+    }
+
+    #[test]
+    fn wilson_interval_bounds() {
+        assert_eq!(wilson_interval(0, 0, Z_95), (0.0, 1.0));
+
+        let (lower, upper) = wilson_interval(0, 10, Z_95);
+        assert_eq!(lower, 0.0);
+        assert!((upper - 0.2775).abs() < 1e-3);
+
+        let (lower, upper) = wilson_interval(50, 100, Z_95);
+        assert!((lower - 0.4038).abs() < 1e-3);
+        assert!((upper - 0.5962).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,105 @@
+//! This module parses and formats SAM flags, which may be given as decimal or hexadecimal numbers,
+//! or as comma-separated flag names.
+
+use pyo3::FromPyObject;
+use serde::Deserialize;
+
+/// Names of the SAM flags, as used by `samtools flags`, ordered by their bit.
+const FLAG_NAMES: [(&str, u16); 12] = [
+    ("PAIRED", 0x1),
+    ("PROPER_PAIR", 0x2),
+    ("UNMAP", 0x4),
+    ("MUNMAP", 0x8),
+    ("REVERSE", 0x10),
+    ("MREVERSE", 0x20),
+    ("READ1", 0x40),
+    ("READ2", 0x80),
+    ("SECONDARY", 0x100),
+    ("QCFAIL", 0x200),
+    ("DUP", 0x400),
+    ("SUPPLEMENTARY", 0x800),
+];
+
+/// SAM flags as passed in a config file or from Python, either as a number, or as text to parse.
+#[derive(Deserialize, FromPyObject, Debug, Clone)]
+#[serde(untagged)]
+pub enum FlagsSetting {
+    Bits(u16),
+    Text(String),
+}
+
+impl FlagsSetting {
+    /// Get the flags as bits, parsing text with `parse_flags`.
+    pub fn to_bits(&self) -> Result<u16, String> {
+        match self {
+            FlagsSetting::Bits(bits) => Ok(*bits),
+            FlagsSetting::Text(text) => parse_flags(text),
+        }
+    }
+}
+
+pub fn parse_flags(text: &str) -> Result<u16, String> {
+    //! Parse SAM flags from a decimal number like `1540`, a hexadecimal number like `0x604`,
+    //! or comma-separated flag names like `UNMAP,QCFAIL,DUP`. Names are case-insensitive,
+    //! and may be mixed with numbers. An empty text means no flags.
+    let mut flags = 0;
+    for part in text.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let bits = if let Some(hex) = part.strip_prefix("0x").or_else(|| part.strip_prefix("0X")) {
+            u16::from_str_radix(hex, 16).map_err(|e| format!("Invalid hexadecimal SAM flags '{part}': {e}"))?
+        } else if part.starts_with(|c: char| c.is_ascii_digit()) {
+            part.parse::<u16>().map_err(|e| format!("Invalid SAM flags '{part}': {e}"))?
+        } else {
+            match FLAG_NAMES.iter().find(|(name, _)| name.eq_ignore_ascii_case(part)) {
+                Some((_, bit)) => *bit,
+                None => {
+                    let names = FLAG_NAMES.map(|(name, _)| name).join(", ");
+                    return Err(format!("Unknown SAM flag '{part}', expected a number or one of {names}"));
+                }
+            }
+        };
+        flags |= bits;
+    }
+    Ok(flags)
+}
+
+pub fn flag_names(flags: u16) -> String {
+    //! Format SAM flags as comma-separated flag names, e.g. `UNMAP,QCFAIL,DUP` for `1540`, or `0` without flags.
+    //! Bits without a name are appended in hexadecimal.
+    if flags == 0 {
+        return String::from("0");
+    }
+
+    let mut names = FLAG_NAMES
+        .iter()
+        .filter(|(_, bit)| flags & bit != 0)
+        .map(|(name, _)| name.to_string())
+        .collect::<Vec<String>>();
+
+    let unnamed = flags & !FLAG_NAMES.iter().fold(0, |all, (_, bit)| all | bit);
+    if unnamed != 0 {
+        names.push(format!("{unnamed:#x}"));
+    }
+
+    names.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_round_trip() {
+        assert_eq!(parse_flags("1540"), Ok(1540));
+        assert_eq!(parse_flags("0x604"), Ok(1540));
+        assert_eq!(parse_flags("UNMAP,QCFAIL, dup"), Ok(1540));
+        assert_eq!(parse_flags("unmap,0x600"), Ok(1540));
+        assert_eq!(parse_flags(""), Ok(0));
+        assert!(parse_flags("UNMAPPED").is_err());
+        assert!(parse_flags("0x10000").is_err());
+
+        assert_eq!(flag_names(1540), "UNMAP,QCFAIL,DUP");
+        assert_eq!(flag_names(0), "0");
+        assert_eq!(flag_names(0x1004), "UNMAP,0x1000");
+        assert_eq!(parse_flags(&flag_names(3844)), Ok(3844));
+    }
+}
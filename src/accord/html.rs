@@ -0,0 +1,296 @@
+//! This module renders a self-contained HTML report of a run, with plots as inline SVG.
+//! The report does not load any external resources, so it can be viewed offline.
+
+use std::fmt::Write;
+
+use itertools::Itertools;
+
+use super::data::consensus::Consensus;
+use super::data::indel::InDel;
+use super::data::stats::{binned_means, DistStats};
+use super::data::variant::VariantKind;
+
+/// Maximum number of points per consensus in the coverage plot. Longer coverages are averaged in bins.
+const MAX_COVERAGE_POINTS: usize = 1000;
+
+/// Width and height of the coverage plot, in pixels.
+const COVERAGE_PLOT_SIZE: (f64, f64) = (900.0, 220.0);
+
+/// Width and height of the histograms, in pixels.
+const HISTOGRAM_SIZE: (f64, f64) = (420.0, 200.0);
+
+/// Margin around the plot area for axis labels, in pixels.
+const PLOT_MARGIN: f64 = 45.0;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; color: #222; }
+h2 { border-bottom: 1px solid #ccc; padding-bottom: 0.2em; }
+table { border-collapse: collapse; margin: 0.5em 0 1.5em 0; font-size: 0.9em; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }
+th { background: #f0f0f0; }
+.histograms { display: flex; flex-wrap: wrap; gap: 1em; }
+.PASS { color: #1a7f37; } .WARN { color: #b58105; } .FAIL { color: #c62828; }
+svg text { font-size: 11px; fill: #444; }";
+
+/// A self-contained HTML report, collecting the consensuses of multiple samples.
+#[derive(Default)]
+pub struct HtmlReport {
+    /// Rendered sections, one per sample.
+    sections: Vec<String>,
+
+    /// The effective settings of the run, if known.
+    settings: Option<String>,
+}
+
+impl HtmlReport {
+    /// Echo the effective settings of the run at the top of the report.
+    pub fn with_settings(settings: String) -> Self {
+        Self { settings: Some(settings), ..Self::default() }
+    }
+
+    /// Add the consensuses of a sample to the report.
+    pub fn add_sample(&mut self, sample_id: &str, consensuses: &[Consensus]) {
+        let mut section = format!("<section id=\"{0}\">\n<h2>{0}</h2>\n", escape(sample_id));
+        for consensus in consensuses {
+            section.push_str(&Self::render_consensus(consensus));
+        }
+        section.push_str("</section>\n");
+        self.sections.push(section);
+    }
+
+    /// Render the complete HTML document.
+    pub fn render(&self) -> String {
+        let settings = match &self.settings {
+            Some(settings) => format!("<p>Settings: <code>{}</code></p>\n", escape(settings)),
+            None => String::new(),
+        };
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>accord report</title>\n\
+             <style>\n{STYLE}\n</style>\n</head>\n<body>\n<h1>accord report</h1>\n{settings}{}</body>\n</html>\n",
+            self.sections.concat()
+        )
+    }
+
+    fn render_consensus(consensus: &Consensus) -> String {
+        let ref_seq = consensus.get_ref_seq();
+        let qc_stats = consensus.get_qc_stats();
+        let mut html = format!("<h3>{}</h3>\n", escape(ref_seq.get_label()));
+
+        let verdict = qc_stats.get_verdict().to_string();
+        let accounting = consensus.get_read_accounting();
+        let reasons = accounting.get_rejected().iter().map(|(reason, count)| format!("{reason}: {count}")).collect::<Vec<String>>();
+        let rejected = match reasons.is_empty() {
+            true => String::from("0"),
+            false => format!("{} ({})", accounting.total_rejected(), escape(&reasons.join(", "))),
+        };
+        let summary = [
+            ("Reference length", ref_seq.len().to_string()),
+            ("Consensus length", consensus.get_consensus_seq().len().to_string()),
+            ("Reads seen", consensus.get_total_reads().to_string()),
+            ("Valid reads", consensus.valid_reads().to_string()),
+            ("Rejected reads", rejected),
+            ("Mean depth", format!("{:.2}", qc_stats.get_mean_depth())),
+            ("Called fraction", format!("{:.4}", qc_stats.get_called_fraction())),
+            ("QC verdict", format!("<span class=\"{verdict}\">{verdict}</span> {}", escape(&qc_stats.get_reasons().join(", ")))),
+        ];
+        html.push_str("<table>\n");
+        for (name, value) in summary {
+            let _ = writeln!(html, "<tr><th>{name}</th><td>{value}</td></tr>");
+        }
+        html.push_str("</table>\n");
+
+        html.push_str("<h4>Coverage</h4>\n");
+        html.push_str(&Self::coverage_plot(consensus.get_coverage()));
+
+        html.push_str("<h4>Applied indels</h4>\n");
+        let applied = consensus.get_indels().iter().map(Self::indel_row).collect();
+        html.push_str(&table(&["Position", "Kind", "Length", "Sequence"], applied));
+
+        html.push_str("<h4>Rejected indels</h4>\n");
+        html.push_str("<p>Indels, that were not applied, but suffice the minor variant thresholds.</p>\n");
+        let rejected = consensus
+            .get_minor_variants()
+            .iter()
+            .filter(|variant| variant.get_kind() != VariantKind::Snv)
+            .map(|variant| {
+                let sequence = match variant.get_kind() {
+                    VariantKind::Deletion => variant.get_ref_allele(),
+                    _ => variant.get_alt_allele(),
+                };
+                vec![
+                    variant.get_position().to_string(),
+                    variant.get_kind().to_string(),
+                    sequence.len().to_string(),
+                    escape(sequence),
+                    variant.get_count().to_string(),
+                    variant.get_depth().to_string(),
+                    format!("{:.4}", variant.get_frequency()),
+                ]
+            })
+            .collect();
+        let header = ["Position", "Kind", "Length", "Sequence", "Count", "Depth", "Frequency"];
+        html.push_str(&table(&header, rejected));
+
+        html.push_str("<h4>SNVs against the reference</h4>\n");
+        let substitutions = consensus
+            .get_substitutions()
+            .iter()
+            .map(|snv| {
+                vec![
+                    snv.get_position().to_string(),
+                    snv.get_ref_base().to_string(),
+                    snv.get_consensus_base().to_string(),
+                    snv.get_count().to_string(),
+                    snv.get_depth().to_string(),
+                    format!("{:.4}", snv.frequency()),
+                ]
+            })
+            .collect();
+        let header = ["Position", "Reference", "Consensus", "Count", "Depth", "Frequency"];
+        html.push_str(&table(&header, substitutions));
+
+        html.push_str("<h4>Alignment statistics</h4>\n<div class=\"histograms\">\n");
+        let aln_stats = consensus.get_aln_stats();
+        let distributions = [
+            ("Aligned read length", aln_stats.get_length_distribution()),
+            ("Mapping quality", aln_stats.get_quality_distribution()),
+            ("Alignment score", aln_stats.get_score_distribution()),
+            ("Edit distance", aln_stats.get_editing_distance_distribution()),
+        ];
+        for (title, distribution) in distributions {
+            html.push_str(&Self::histogram(title, distribution));
+        }
+        html.push_str("</div>\n");
+
+        html
+    }
+
+    fn indel_row(indel: &InDel) -> Vec<String> {
+        let (kind, sequence) = match indel {
+            InDel::Ins(_) => (VariantKind::Insertion, String::from_utf8_lossy(indel.get_seq()).to_string()),
+            InDel::Del(_) => (VariantKind::Deletion, String::new()),
+        };
+        vec![indel.get_start().to_string(), kind.to_string(), indel.len().to_string(), escape(&sequence)]
+    }
+
+    /// Plot the coverage along the reference as a line.
+    fn coverage_plot(coverage: &[usize]) -> String {
+        let (width, height) = COVERAGE_PLOT_SIZE;
+        let bins = binned_means(coverage, MAX_COVERAGE_POINTS);
+        let max_depth = bins.iter().map(|(_, depth)| *depth).fold(1.0, f64::max);
+        let len = coverage.len().max(1) as f64;
+
+        let x = |pos: f64| PLOT_MARGIN + pos / len * (width - 2.0 * PLOT_MARGIN);
+        let y = |depth: f64| height - PLOT_MARGIN - depth / max_depth * (height - 2.0 * PLOT_MARGIN);
+        let points = bins
+            .iter()
+            .map(|(pos, depth)| format!("{:.1},{:.1}", x(*pos as f64), y(*depth)))
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        let mut svg = svg_frame(width, height, "Position", "Depth");
+        let _ = writeln!(svg, "<polyline fill=\"none\" stroke=\"#1f77b4\" stroke-width=\"1\" points=\"{points}\"/>");
+        svg.push_str(&axis_ticks(width, height, (0.0, len), (0.0, max_depth)));
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Plot a distribution as histogram of its quantiles. Every bar spans the values between two consecutive
+    /// quantiles, and its area corresponds to the fraction of reads within.
+    fn histogram(title: &str, distribution: &DistStats) -> String {
+        let (width, height) = HISTOGRAM_SIZE;
+        let quantiles = distribution.get_quantiles();
+        let (min, max) = match (quantiles.first(), quantiles.last()) {
+            (Some(first), Some(last)) if distribution.get_sample_size() > 0 => (first.get_value() as f64, last.get_value() as f64),
+            _ => return format!("<div><h5>{title}</h5><p>No reads.</p></div>\n"),
+        };
+
+        // bars span at least one unit, so distributions with identical quantiles are visible,
+        // and bars of identical quantiles are merged
+        let mut bars: Vec<(f64, f64, f64)> = Vec::new();
+        for (lower, upper) in quantiles.iter().tuple_windows() {
+            let start = lower.get_value() as f64;
+            let end = (upper.get_value() as f64).max(start + 1.0);
+            let fraction = upper.get_factor() - lower.get_factor();
+            match bars.last_mut() {
+                Some((last_start, last_end, last_fraction)) if *last_start == start && *last_end == end => {
+                    *last_fraction += fraction
+                }
+                _ => bars.push((start, end, fraction)),
+            }
+        }
+        let bars = bars.into_iter().map(|(start, end, fraction)| (start, end, fraction / (end - start))).collect::<Vec<_>>();
+        let max_density = bars.iter().map(|(_, _, density)| *density).fold(f64::MIN_POSITIVE, f64::max);
+        let span = (max + 1.0 - min).max(1.0);
+
+        let x = |value: f64| PLOT_MARGIN + (value - min) / span * (width - 2.0 * PLOT_MARGIN);
+        let plot_height = height - 2.0 * PLOT_MARGIN;
+
+        let mut svg = format!("<div><h5>{title}</h5>\n");
+        svg.push_str(&svg_frame(width, height, title, "Density"));
+        for (start, end, density) in bars {
+            let bar_height = density / max_density * plot_height;
+            let _ = writeln!(
+                svg,
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#1f77b4\" fill-opacity=\"0.6\"/>",
+                x(start), height - PLOT_MARGIN - bar_height, x(end) - x(start), bar_height
+            );
+        }
+        svg.push_str(&axis_ticks(width, height, (min, min + span), (0.0, max_density)));
+        svg.push_str("</svg>\n</div>\n");
+        svg
+    }
+}
+
+/// Open an SVG element and draw the axes with their labels.
+fn svg_frame(width: f64, height: f64, xlab: &str, ylab: &str) -> String {
+    let (left, bottom, right) = (PLOT_MARGIN, height - PLOT_MARGIN, width - PLOT_MARGIN);
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n\
+         <line x1=\"{left}\" y1=\"{PLOT_MARGIN}\" x2=\"{left}\" y2=\"{bottom}\" stroke=\"#444\"/>\n\
+         <line x1=\"{left}\" y1=\"{bottom}\" x2=\"{right}\" y2=\"{bottom}\" stroke=\"#444\"/>\n\
+         <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>\n\
+         <text x=\"12\" y=\"{}\" text-anchor=\"middle\" transform=\"rotate(-90 12 {})\">{}</text>\n",
+        width / 2.0, height - 8.0, escape(xlab), height / 2.0, height / 2.0, escape(ylab)
+    )
+}
+
+/// Label the minimum and maximum of both axes.
+fn axis_ticks(width: f64, height: f64, (x_min, x_max): (f64, f64), (y_min, y_max): (f64, f64)) -> String {
+    let (left, bottom, right) = (PLOT_MARGIN, height - PLOT_MARGIN, width - PLOT_MARGIN);
+    let label = |value: f64| if value.fract() == 0.0 { format!("{value}") } else { format!("{value:.3}") };
+    format!(
+        "<text x=\"{left}\" y=\"{}\" text-anchor=\"middle\">{}</text>\n\
+         <text x=\"{right}\" y=\"{}\" text-anchor=\"middle\">{}</text>\n\
+         <text x=\"{}\" y=\"{bottom}\" text-anchor=\"end\">{}</text>\n\
+         <text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>\n",
+        bottom + 14.0, label(x_min), bottom + 14.0, label(x_max),
+        left - 4.0, label(y_min), left - 4.0, PLOT_MARGIN + 4.0, label(y_max)
+    )
+}
+
+/// Render rows as HTML table, or a note if there are none.
+fn table(header: &[&str], rows: Vec<Vec<String>>) -> String {
+    if rows.is_empty() {
+        return String::from("<p>None.</p>\n");
+    }
+
+    let mut html = String::from("<table>\n<tr>");
+    for name in header {
+        let _ = write!(html, "<th>{name}</th>");
+    }
+    html.push_str("</tr>\n");
+    for row in rows {
+        let _ = writeln!(html, "<tr><td>{}</td></tr>", row.join("</td><td>"));
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+/// Escape the characters with a special meaning in HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
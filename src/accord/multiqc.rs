@@ -0,0 +1,173 @@
+//! This module renders custom content for MultiQC, so that the consensus calculation of all samples
+//! appears in the same MultiQC report as other tools.
+
+use serde_json::{json, Map, Value};
+
+use super::data::consensus::Consensus;
+use super::data::stats::{binned_means, DistStats};
+
+/// Maximum number of points per consensus in the coverage line graph. Longer coverages are averaged in bins.
+const MAX_COVERAGE_POINTS: usize = 1000;
+
+/// Line graphs of the alignment statistics, as ID, title, and label of the value axis.
+const DISTRIBUTIONS: [(&str, &str, &str); 4] = [
+    ("aln_length", "Aligned read length", "Length"),
+    ("mapq", "Mapping quality", "MAPQ"),
+    ("score", "Alignment score", "AS"),
+    ("distance", "Edit distance", "NM"),
+];
+
+/// MultiQC custom content, collecting the consensuses of multiple samples.
+#[derive(Default)]
+pub struct MultiQc {
+    /// General statistics per consensus.
+    general_stats: Map<String, Value>,
+
+    /// Mean coverage per bin of positions, per consensus.
+    coverage: Map<String, Value>,
+
+    /// Quantiles of the alignment statistics per consensus, in the order of `DISTRIBUTIONS`.
+    distributions: [Map<String, Value>; 4],
+
+    /// Depths, for which the breadth of coverage is reported.
+    depth_thresholds: Vec<usize>,
+
+    /// The effective settings of the run, if known.
+    settings: Option<String>,
+}
+
+impl MultiQc {
+    /// Echo the effective settings of the run in the description of the coverage section.
+    pub fn with_settings(settings: String) -> Self {
+        Self { settings: Some(settings), ..Self::default() }
+    }
+
+    /// Add the consensuses of a sample. With multiple references, every consensus is named after
+    /// the sample ID and the first word of the reference label.
+    pub fn add_sample(&mut self, sample_id: &str, consensuses: &[Consensus]) {
+        for consensus in consensuses {
+            let name = if consensuses.len() == 1 {
+                sample_id.to_string()
+            } else {
+                let label = consensus.get_ref_seq().get_label();
+                format!("{sample_id}_{}", label.split_whitespace().next().unwrap_or(label))
+            };
+
+            let qc_stats = consensus.get_qc_stats();
+            if self.depth_thresholds.is_empty() {
+                self.depth_thresholds = qc_stats.get_breadth().iter().map(|(depth, _)| *depth).collect();
+            }
+
+            let mut stats = Map::new();
+            stats.insert(String::from("total_reads"), json!(consensus.get_total_reads()));
+            stats.insert(String::from("valid_reads"), json!(consensus.valid_reads()));
+            stats.insert(String::from("mean_depth"), json!(qc_stats.get_mean_depth()));
+            for (depth, breadth) in qc_stats.get_breadth() {
+                stats.insert(format!("breadth_{depth}x"), json!(breadth * 100.0));
+            }
+            let n_count = consensus.get_consensus_seq().get_sequence().iter().filter(|base| **base == b'N').count();
+            stats.insert(String::from("n_count"), json!(n_count));
+            stats.insert(String::from("applied_indels"), json!(consensus.get_indels().len()));
+            self.general_stats.insert(name.clone(), Value::Object(stats));
+
+            self.coverage.insert(name.clone(), Self::binned_coverage(consensus.get_coverage()));
+
+            let aln_stats = consensus.get_aln_stats();
+            let distributions = [
+                aln_stats.get_length_distribution(),
+                aln_stats.get_quality_distribution(),
+                aln_stats.get_score_distribution(),
+                aln_stats.get_editing_distance_distribution(),
+            ];
+            for (series, distribution) in self.distributions.iter_mut().zip(distributions) {
+                series.insert(name.clone(), Self::quantiles(distribution));
+            }
+        }
+    }
+
+    /// Render the general statistics table, as `accord_general_stats_mqc.json`.
+    pub fn general_stats_to_json(&self) -> String {
+        let mut headers = vec![
+            json!({"total_reads": {"title": "Reads", "description": "Reads seen", "format": "{:,.0f}"}}),
+            json!({"valid_reads": {"title": "Valid reads", "description": "Reads considered for the consensus", "format": "{:,.0f}"}}),
+            json!({"mean_depth": {"title": "Mean depth", "description": "Mean depth of coverage", "suffix": "X"}}),
+        ];
+        for depth in &self.depth_thresholds {
+            let description = format!("Fraction of positions with a depth of at least {depth}");
+            let header = json!({"title": format!("≥ {depth}X"), "description": description, "suffix": "%", "max": 100, "min": 0});
+            headers.push(json!({ format!("breadth_{depth}x"): header }));
+        }
+        headers.push(json!({"n_count": {"title": "Ns", "description": "Number of N in the consensus", "format": "{:,.0f}"}}));
+        headers.push(json!({"applied_indels": {"title": "Indels", "description": "Indels applied to the consensus", "format": "{:,.0f}"}}));
+
+        let content = json!({
+            "id": "accord_general_stats",
+            "plot_type": "generalstats",
+            "pconfig": headers,
+            "data": self.general_stats,
+        });
+        Self::to_json(&content)
+    }
+
+    /// Render the coverage line graph, as `accord_coverage_mqc.json`.
+    pub fn coverage_to_json(&self) -> String {
+        let mut description = String::from("Coverage of the reference by reads considered for the consensus.");
+        if let Some(settings) = &self.settings {
+            description.push_str(&format!(" Settings: {settings}."));
+        }
+        let content = json!({
+            "id": "accord_coverage",
+            "section_name": "accord: Coverage",
+            "description": description,
+            "plot_type": "linegraph",
+            "pconfig": {"id": "accord_coverage_plot", "title": "accord: Coverage", "xlab": "Position", "ylab": "Depth"},
+            "data": self.coverage,
+        });
+        Self::to_json(&content)
+    }
+
+    /// Render the line graphs of the alignment statistics, paired with their file names.
+    pub fn distributions_to_json(&self) -> Vec<(String, String)> {
+        DISTRIBUTIONS
+            .iter()
+            .zip(&self.distributions)
+            .map(|((id, title, ylab), data)| {
+                let content = json!({
+                    "id": format!("accord_{id}"),
+                    "section_name": format!("accord: {title}"),
+                    "description": format!("Quantiles of the {} of reads considered for the consensus.", title.to_lowercase()),
+                    "plot_type": "linegraph",
+                    "pconfig": {"id": format!("accord_{id}_plot"), "title": format!("accord: {title}"), "xlab": "Quantile", "ylab": ylab},
+                    "data": data,
+                });
+                (format!("accord_{id}_mqc.json"), Self::to_json(&content))
+            })
+            .collect()
+    }
+
+    /// Mean coverage per bin of positions, keyed by the one-based start of the bin.
+    fn binned_coverage(coverage: &[usize]) -> Value {
+        let bins = binned_means(coverage, MAX_COVERAGE_POINTS)
+            .into_iter()
+            .map(|(start, mean)| ((start + 1).to_string(), json!(mean)))
+            .collect::<Map<String, Value>>();
+        Value::Object(bins)
+    }
+
+    /// Quantile values of a distribution, keyed by the quantile factor.
+    fn quantiles(distribution: &DistStats) -> Value {
+        let quantiles = distribution
+            .get_quantiles()
+            .iter()
+            .map(|quantile| (quantile.get_factor().to_string(), json!(quantile.get_value())))
+            .collect::<Map<String, Value>>();
+        Value::Object(quantiles)
+    }
+
+    fn to_json(content: &Value) -> String {
+        match serde_json::to_string_pretty(content) {
+            Ok(json) => json,
+            Err(e) => panic!("Unable to serialize MultiQC content: {e}"),
+        }
+    }
+}
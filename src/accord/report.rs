@@ -0,0 +1,485 @@
+//! This module renders reports, that summarize the consensus calculation for a sample.
+
+use serde::Serialize;
+
+use super::data::accounting::ReadAccounting;
+use super::data::consensus::Consensus;
+use super::data::mutation::AaMutation;
+use super::data::variant::{MinorVariant, VariantKind};
+use super::settings::AlnQualityReqs;
+
+/// An amino acid mutation, labelled with the sample and reference it was found in.
+#[derive(Serialize)]
+struct AaMutationRecord<'a> {
+    sample: &'a str,
+    reference: &'a str,
+
+    #[serde(flatten)]
+    mutation: &'a AaMutation,
+}
+
+/// A tabular summary of the consensus sequences calculated for a sample.
+pub struct Report<'a> {
+    /// ID of the sample the consensuses were calculated for.
+    sample_id: &'a str,
+
+    /// The consensuses calculated for the sample, one per reference sequence.
+    consensuses: &'a [Consensus],
+
+    /// The effective alignment quality requirements, if known.
+    aln_reqs: Option<&'a AlnQualityReqs>,
+}
+
+impl<'a> Report<'a> {
+    pub fn new(sample_id: &'a str, consensuses: &'a [Consensus]) -> Self {
+        Self { sample_id, consensuses, aln_reqs: None }
+    }
+
+    /// Echo the effective alignment quality requirements in the report and the VCF header.
+    pub fn with_aln_reqs(self, aln_reqs: &'a AlnQualityReqs) -> Self {
+        Self { aln_reqs: Some(aln_reqs), ..self }
+    }
+
+    /// Render the report as a TSV table with one row per reference sequence.
+    pub fn to_tsv(&self) -> String {
+        let header = [
+            "sample",
+            "reference",
+            "aln_path",
+            "ref_length",
+            "consensus_length",
+            "total_reads",
+            "valid_reads",
+            "rejected_reads",
+            "rejection_reasons",
+            "mean_aln_length",
+            "mean_mapq",
+            "mean_score",
+            "mean_distance",
+            "mean_coverage",
+            "mean_fragment_coverage",
+            "mean_entropy",
+            "mean_minor_allele_frequency",
+            "applied_indels",
+            "frameshifts",
+            "start_extension",
+            "end_extension",
+            "umi_families",
+            "discarded_families",
+            "mean_family_size",
+        ];
+
+        // the breadth is reported for the same depth thresholds in every consensus
+        let depth_thresholds = match self.consensuses.first() {
+            Some(consensus) => consensus.get_qc_stats().get_breadth().iter().map(|(depth, _)| *depth).collect(),
+            None => Vec::new(),
+        };
+        let qc_header = ["mean_depth".to_string(), "median_depth".to_string()]
+            .into_iter()
+            .chain(depth_thresholds.iter().map(|depth: &usize| format!("breadth_{depth}x")))
+            .chain(["called_fraction".to_string(), "qc_verdict".to_string(), "qc_reasons".to_string()])
+            .chain(["aln_quality_reqs".to_string()]);
+        let settings = match self.aln_reqs {
+            Some(aln_reqs) => aln_reqs.to_string(),
+            None => String::from("NA"),
+        };
+
+        let mut tsv = header.map(String::from).into_iter().chain(qc_header).collect::<Vec<String>>().join("\t");
+        tsv.push('\n');
+
+        for consensus in self.consensuses {
+            let stats = consensus.get_aln_stats();
+            let qc_stats = consensus.get_qc_stats();
+            let (start_extension, end_extension) = consensus.get_end_extensions();
+            let accounting = consensus.get_read_accounting();
+
+            // family statistics are only available, if reads were collapsed into UMI families
+            let family_columns = match consensus.get_family_stats() {
+                Some(family_stats) => [
+                    family_stats.get_families().to_string(),
+                    family_stats.get_discarded_families().to_string(),
+                    format!("{:.2}", family_stats.get_size_distribution().get_mean()),
+                ],
+                None => [String::from("NA"), String::from("NA"), String::from("NA")],
+            };
+
+            let row = [
+                self.sample_id.to_string(),
+                consensus.get_ref_seq().get_label().clone(),
+                consensus.get_aln_path().clone(),
+                consensus.get_ref_seq().len().to_string(),
+                consensus.get_consensus_seq().len().to_string(),
+                consensus.get_total_reads().to_string(),
+                consensus.valid_reads().to_string(),
+                accounting.total_rejected().to_string(),
+                Self::rejection_reasons(accounting),
+                format!("{:.2}", stats.get_length_distribution().get_mean()),
+                format!("{:.2}", stats.get_quality_distribution().get_mean()),
+                format!("{:.2}", stats.get_score_distribution().get_mean()),
+                format!("{:.2}", stats.get_editing_distance_distribution().get_mean()),
+                format!("{:.2}", Self::mean(consensus.get_coverage())),
+                format!("{:.2}", Self::mean(consensus.get_fragment_coverage())),
+                format!("{:.4}", consensus.get_diversity().mean_entropy()),
+                format!("{:.4}", consensus.get_diversity().mean_minor_allele_frequency()),
+                consensus.get_indels().len().to_string(),
+                consensus.get_frameshifts().len().to_string(),
+                start_extension.to_string(),
+                end_extension.to_string(),
+            ];
+
+            tsv.push_str(row.join("\t").as_str());
+            tsv.push('\t');
+            tsv.push_str(family_columns.join("\t").as_str());
+
+            let reasons = qc_stats.get_reasons().join("; ");
+            let qc_columns = [format!("{:.2}", qc_stats.get_mean_depth()), format!("{:.1}", qc_stats.get_median_depth())]
+                .into_iter()
+                .chain(qc_stats.get_breadth().iter().map(|(_, breadth)| format!("{breadth:.4}")))
+                .chain([format!("{:.4}", qc_stats.get_called_fraction()), qc_stats.get_verdict().to_string()])
+                .chain([if reasons.is_empty() { String::from("NA") } else { reasons }])
+                .chain([settings.clone()]);
+            tsv.push('\t');
+            tsv.push_str(qc_columns.collect::<Vec<String>>().join("\t").as_str());
+            tsv.push('\n');
+        }
+
+        tsv
+    }
+
+    /// Rejected reads per reason, like `low_mapq=3;prohibited_flag:DUP=12`, or `NA` if no read was rejected.
+    fn rejection_reasons(accounting: &ReadAccounting) -> String {
+        if accounting.get_rejected().is_empty() {
+            return String::from("NA");
+        }
+        accounting
+            .get_rejected()
+            .iter()
+            .map(|(reason, count)| format!("{reason}={count}"))
+            .collect::<Vec<String>>()
+            .join(";")
+    }
+
+    /// Mean of a coverage vector, or zero for an empty vector.
+    fn mean(coverage: &[usize]) -> f64 {
+        if coverage.is_empty() {
+            return 0.0;
+        }
+        coverage.iter().sum::<usize>() as f64 / coverage.len() as f64
+    }
+
+    /// Render the diversity tracks of all consensuses as a TSV table with one row per reference position.
+    pub fn diversity_to_tsv(&self) -> String {
+        let header = ["sample", "reference", "position", "depth", "entropy", "minor_allele_frequency", "support"];
+
+        let mut tsv = header.join("\t");
+        tsv.push('\n');
+
+        for consensus in self.consensuses {
+            let label = consensus.get_ref_seq().get_label();
+            let diversity = consensus.get_diversity();
+            for pos in 0..diversity.get_depth().len() {
+                let row = [
+                    self.sample_id.to_string(),
+                    label.clone(),
+                    pos.to_string(),
+                    diversity.get_depth()[pos].to_string(),
+                    format!("{:.4}", diversity.get_entropy()[pos]),
+                    format!("{:.4}", diversity.get_minor_allele_frequency()[pos]),
+                    format!("{:.4}", diversity.get_support()[pos]),
+                ];
+
+                tsv.push_str(row.join("\t").as_str());
+                tsv.push('\n');
+            }
+        }
+
+        tsv
+    }
+
+    /// Render the diversity of all consensuses, summarized in windows of `size` positions,
+    /// as a TSV table with one row per window.
+    pub fn diversity_windows_to_tsv(&self, size: usize) -> String {
+        let header = [
+            "sample",
+            "reference",
+            "start",
+            "end",
+            "mean_depth",
+            "mean_entropy",
+            "mean_minor_allele_frequency",
+            "mean_support",
+        ];
+
+        let mut tsv = header.join("\t");
+        tsv.push('\n');
+
+        for consensus in self.consensuses {
+            for window in consensus.get_diversity().windows(size) {
+                let row = [
+                    self.sample_id.to_string(),
+                    consensus.get_ref_seq().get_label().clone(),
+                    window.get_start().to_string(),
+                    window.get_end().to_string(),
+                    format!("{:.2}", window.get_mean_depth()),
+                    format!("{:.4}", window.get_mean_entropy()),
+                    format!("{:.4}", window.get_mean_minor_allele_frequency()),
+                    format!("{:.4}", window.get_mean_support()),
+                ];
+
+                tsv.push_str(row.join("\t").as_str());
+                tsv.push('\n');
+            }
+        }
+
+        tsv
+    }
+
+    /// Render the amino acid mutations of all consensuses as a TSV table with one row per mutation.
+    pub fn aa_mutations_to_tsv(&self) -> String {
+        let header = [
+            "sample",
+            "reference",
+            "cds",
+            "kind",
+            "codon_position",
+            "ref_pos",
+            "ref_aa",
+            "alt_aa",
+            "ref_codons",
+            "alt_codons",
+            "notation",
+        ];
+
+        let mut tsv = header.join("\t");
+        tsv.push('\n');
+
+        for record in self.aa_mutation_records() {
+            let mutation = record.mutation;
+            let row = [
+                record.sample.to_string(),
+                record.reference.to_string(),
+                mutation.get_cds().clone(),
+                mutation.get_kind().to_string(),
+                mutation.get_codon_position().to_string(),
+                mutation.get_ref_pos().to_string(),
+                mutation.get_ref_aa().clone(),
+                mutation.get_alt_aa().clone(),
+                mutation.get_ref_codons().clone(),
+                mutation.get_alt_codons().clone(),
+                mutation.get_notation().clone(),
+            ];
+
+            tsv.push_str(row.join("\t").as_str());
+            tsv.push('\n');
+        }
+
+        tsv
+    }
+
+    /// Render the amino acid mutations of all consensuses as a JSON array with one object per mutation.
+    pub fn aa_mutations_to_json(&self) -> String {
+        let records = self.aa_mutation_records();
+        match serde_json::to_string_pretty(&records) {
+            Ok(json) => json,
+            Err(e) => panic!("Unable to serialize amino acid mutations: {e}"),
+        }
+    }
+
+    fn aa_mutation_records(&self) -> Vec<AaMutationRecord<'_>> {
+        self.consensuses
+            .iter()
+            .flat_map(|consensus| {
+                let reference = consensus.get_ref_seq().get_label().as_str();
+                consensus.get_aa_mutations().iter().map(move |mutation| AaMutationRecord {
+                    sample: self.sample_id,
+                    reference,
+                    mutation,
+                })
+            })
+            .collect()
+    }
+
+    /// Render the coverage statistics of the regions as a TSV table with one row per region.
+    /// Start and end are zero-based and half-open, like in BED files.
+    pub fn regions_to_tsv(&self) -> String {
+        let header = [
+            "sample",
+            "reference",
+            "region",
+            "start",
+            "end",
+            "length",
+            "mean_coverage",
+            "min_coverage",
+            "max_coverage",
+            "breadth",
+        ];
+
+        let mut tsv = header.join("\t");
+        tsv.push('\n');
+
+        for consensus in self.consensuses {
+            for stats in consensus.get_region_stats() {
+                let region = stats.get_region();
+                let row = [
+                    self.sample_id.to_string(),
+                    consensus.get_ref_seq().get_label().clone(),
+                    region.get_name().clone(),
+                    region.get_start().to_string(),
+                    region.get_end().to_string(),
+                    (region.get_end() - region.get_start()).to_string(),
+                    format!("{:.2}", stats.get_mean_coverage()),
+                    stats.get_min_coverage().to_string(),
+                    stats.get_max_coverage().to_string(),
+                    format!("{:.4}", stats.get_breadth()),
+                ];
+
+                tsv.push_str(row.join("\t").as_str());
+                tsv.push('\n');
+            }
+        }
+
+        tsv
+    }
+
+    /// Render the minor variants of all consensuses as a TSV table with one row per variant.
+    pub fn minor_variants_to_tsv(&self) -> String {
+        let header = [
+            "sample",
+            "reference",
+            "position",
+            "kind",
+            "ref_allele",
+            "consensus_allele",
+            "alt_allele",
+            "count",
+            "depth",
+            "frequency",
+            "ci_lower",
+            "ci_upper",
+            "forward_count",
+            "reverse_count",
+        ];
+
+        let mut tsv = header.join("\t");
+        tsv.push('\n');
+
+        for consensus in self.consensuses {
+            for variant in consensus.get_minor_variants() {
+                let (ci_lower, ci_upper) = variant.get_ci();
+                let row = [
+                    self.sample_id.to_string(),
+                    consensus.get_ref_seq().get_label().clone(),
+                    variant.get_position().to_string(),
+                    variant.get_kind().to_string(),
+                    variant.get_ref_allele().clone(),
+                    variant.get_consensus_allele().clone(),
+                    variant.get_alt_allele().clone(),
+                    variant.get_count().to_string(),
+                    variant.get_depth().to_string(),
+                    format!("{:.4}", variant.get_frequency()),
+                    format!("{ci_lower:.4}"),
+                    format!("{ci_upper:.4}"),
+                    variant.get_forward_count().to_string(),
+                    variant.get_reverse_count().to_string(),
+                ];
+
+                tsv.push_str(row.join("\t").as_str());
+                tsv.push('\n');
+            }
+        }
+
+        tsv
+    }
+
+    /// Render the minor variants of all consensuses in VCF format, relative to the reference sequences.
+    ///
+    /// Contigs are named by the first word of the reference label.
+    /// Alleles matching the reference, i.e. minor alleles at positions where the consensus differs from the
+    /// reference, can't be represented and are omitted.
+    pub fn minor_variants_to_vcf(&self) -> String {
+        let mut vcf = String::from("##fileformat=VCFv4.2\n##source=accord\n");
+        if let Some(aln_reqs) = self.aln_reqs {
+            vcf.push_str(&format!("##accordAlnQualityReqs=\"{aln_reqs}\"\n"));
+        }
+        for consensus in self.consensuses {
+            let ref_seq = consensus.get_ref_seq();
+            vcf.push_str(&format!("##contig=<ID={},length={}>\n", Self::contig_id(ref_seq.get_label()), ref_seq.len()));
+        }
+
+        let info_lines = [
+            "##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Number of reads covering the variant site\">",
+            "##INFO=<ID=AO,Number=1,Type=Integer,Description=\"Number of reads displaying the variant\">",
+            "##INFO=<ID=AF,Number=1,Type=Float,Description=\"Frequency of the variant\">",
+            "##INFO=<ID=AFCI,Number=2,Type=Float,Description=\"95% Wilson score interval of the frequency\">",
+            "##INFO=<ID=SB,Number=2,Type=Integer,Description=\"Number of reads displaying the variant on the forward and reverse strand\">",
+        ];
+        for line in info_lines {
+            vcf.push_str(line);
+            vcf.push('\n');
+        }
+        vcf.push_str("#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n");
+
+        for consensus in self.consensuses {
+            let ref_seq = consensus.get_ref_seq();
+            let contig = Self::contig_id(ref_seq.get_label());
+
+            for variant in consensus.get_minor_variants() {
+                let (pos, ref_allele, alt_allele) = match Self::vcf_alleles(variant, ref_seq.get_sequence()) {
+                    Some(alleles) => alleles,
+                    None => continue,
+                };
+
+                let (ci_lower, ci_upper) = variant.get_ci();
+                let info = format!(
+                    "DP={};AO={};AF={:.4};AFCI={ci_lower:.4},{ci_upper:.4};SB={},{}",
+                    variant.get_depth(),
+                    variant.get_count(),
+                    variant.get_frequency(),
+                    variant.get_forward_count(),
+                    variant.get_reverse_count(),
+                );
+                let row = [contig, &pos.to_string(), ".", &ref_allele, &alt_allele, ".", "PASS", &info];
+
+                vcf.push_str(row.join("\t").as_str());
+                vcf.push('\n');
+            }
+        }
+
+        vcf
+    }
+
+    /// Determine the one-based position, and the reference and alternative alleles of a variant in VCF.
+    /// Indels are anchored to the preceding reference base, or the following one at the start of the reference.
+    fn vcf_alleles(variant: &MinorVariant, ref_bases: &[u8]) -> Option<(usize, String, String)> {
+        let pos = variant.get_position();
+        let alt = variant.get_alt_allele().as_bytes();
+
+        let (start, ref_allele, alt_allele) = match variant.get_kind() {
+            VariantKind::Snv if alt.eq_ignore_ascii_case(&ref_bases[pos..=pos]) => return None,
+            VariantKind::Snv => (pos, ref_bases[pos..=pos].to_vec(), alt.to_vec()),
+            VariantKind::Insertion => {
+                let anchor = ref_bases[pos];
+                (pos, vec![anchor], [&[anchor], alt].concat())
+            }
+            VariantKind::Deletion => {
+                let end = pos + variant.get_ref_allele().len();
+                if pos > 0 {
+                    (pos - 1, ref_bases[pos - 1..end].to_vec(), vec![ref_bases[pos - 1]])
+                } else if end < ref_bases.len() {
+                    (pos, ref_bases[pos..=end].to_vec(), vec![ref_bases[end]])
+                } else {
+                    return None;
+                }
+            }
+        };
+
+        let to_string = |bases: Vec<u8>| String::from_utf8_lossy(&bases).to_ascii_uppercase();
+        Some((start + 1, to_string(ref_allele), to_string(alt_allele)))
+    }
+
+    /// Name of the contig for a reference label, i.e. its first word.
+    fn contig_id(label: &str) -> &str {
+        label.split_whitespace().next().unwrap_or(label)
+    }
+}
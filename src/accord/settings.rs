@@ -1,8 +1,23 @@
 //! This module contains settings for consensus generation.
 
-use clap::Args;
-use pyo3::{pyclass, pymethods};
+use std::fmt::{Display, Formatter};
+use std::fs;
+
+use clap::{Args, ValueEnum};
+use pyo3::exceptions::PyValueError;
+use pyo3::types::PyType;
+use pyo3::{pyclass, pymethods, Bound, PyResult};
+use rust_htslib::bam::record::Aux;
 use rust_htslib::bam::Record;
+use serde::Deserialize;
+
+use super::data::accounting::RejectionReason;
+use super::data::indel::InDel;
+use super::flags::{flag_names, parse_flags, FlagsSetting};
+use super::data::qc::{QcStats, QcVerdict};
+
+/// Minimum coverage for calling a position in long-read mode, where fewer agreeing bases suffice.
+pub const LONG_READ_MIN_OBSERVATIONS: usize = 10;
 
 /// Requirements for alignment quality.
 #[derive(Debug, Clone, Args)]
@@ -14,12 +29,14 @@ pub struct AlnQualityReqs {
     pub min_mapq: u8,
 
     /// SAM-flags that must be present for a read to be considered.
-    #[arg(long, default_value_t = 0)]
+    /// Either a decimal or hexadecimal number, or comma-separated flag names, e.g. `PAIRED,PROPER_PAIR`.
+    #[arg(long, default_value = "0", value_parser = parse_flags)]
     #[pyo3(get)]
     pub mandatory_flags: u16,
 
     /// SAM-flags that may not be present for a read to be considered.
-    #[arg(long, default_value_t = 1540)]
+    /// Either a decimal or hexadecimal number, or comma-separated flag names, e.g. `UNMAP,SECONDARY`.
+    #[arg(long, default_value = "UNMAP,QCFAIL,DUP", value_parser = parse_flags)]
     #[pyo3(get)]
     pub prohibited_flags: u16,
 
@@ -29,6 +46,13 @@ pub struct AlnQualityReqs {
     #[pyo3(get)]
     pub indel_cutoff: f64,
 
+    /// Fraction of reads spanning a frameshifting indel within an annotated coding sequence, that have to
+    /// display the indel for it to be added to the consensus. Applies instead of `indel_cutoff`.
+    /// E.g. `0.5` means the indel has to appear in half of the reads that span its site.
+    #[arg(long, default_value_t = 0.5)]
+    #[pyo3(get)]
+    pub frameshift_cutoff: f64,
+
     /// Has no purpose at this point.
     ///
     /// Probably useless.
@@ -42,40 +66,742 @@ pub struct AlnQualityReqs {
     #[arg(long, default_value_t = 50)]
     #[pyo3(get)]
     pub min_observations: usize,
+
+    /// Long-read mode, for reads with frequent homopolymer errors, e.g. from Oxford Nanopore.
+    /// Indels shortening or lengthening homopolymer runs need more support, as set by `homopolymer_factor`.
+    /// It lowers `min_observations` to 10, unless that is set explicitly, by a preset, or in a config file.
+    #[arg(long)]
+    #[pyo3(get)]
+    pub long_reads: bool,
+
+    /// In long-read mode, the support needed by an indel within a homopolymer run grows by this fraction
+    /// per base of the run beyond the first. E.g. with an `indel_cutoff` of `0.4`, and a factor of `0.1`,
+    /// indels in a run of four bases have to appear in 70% of the reads.
+    #[arg(long, default_value_t = 0.1)]
+    #[pyo3(get)]
+    pub homopolymer_factor: f64,
+
+    /// Minimal number of aligned bases, i.e. bases of a read that are not clipped, for a read to be considered.
+    #[arg(long)]
+    #[pyo3(get)]
+    pub min_aligned_length: Option<usize>,
+
+    /// Maximal edit distance (`NM` tag) of a read, as a fraction of its aligned bases, for it to be considered.
+    /// Reads without an `NM` tag are discarded, if this is set.
+    #[arg(long)]
+    #[pyo3(get)]
+    pub max_edit_fraction: Option<f64>,
+
+    /// Minimal alignment score (`AS` tag) for a read to be considered.
+    /// Reads without an `AS` tag are discarded, if this is set.
+    #[arg(long)]
+    #[pyo3(get)]
+    pub min_alignment_score: Option<i64>,
+
+    /// Maximal fraction of soft-clipped bases of a read for it to be considered.
+    #[arg(long)]
+    #[pyo3(get)]
+    pub max_softclip_fraction: Option<f64>,
+
+    /// Only consider reads, that are aligned as a proper pair with their mate.
+    #[arg(long)]
+    #[pyo3(get)]
+    pub proper_pairs_only: bool,
+
+    /// Minimal insert size (absolute `TLEN`) for a read to be considered.
+    /// Reads with an unknown insert size of zero are discarded, if this is set.
+    #[arg(long)]
+    #[pyo3(get)]
+    pub min_insert_size: Option<u64>,
+
+    /// Maximal insert size (absolute `TLEN`) for a read to be considered.
+    #[arg(long)]
+    #[pyo3(get)]
+    pub max_insert_size: Option<u64>,
 }
 
 impl AlnQualityReqs {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        min_mapq: u8,
+        mandatory_flags: u16,
+        prohibited_flags: u16,
+        indel_cutoff: f64,
+        save_ends: usize,
+        min_observations: usize,
+        frameshift_cutoff: f64,
+        long_reads: bool,
+        homopolymer_factor: f64,
+        min_aligned_length: Option<usize>,
+        max_edit_fraction: Option<f64>,
+        min_alignment_score: Option<i64>,
+        max_softclip_fraction: Option<f64>,
+        proper_pairs_only: bool,
+        min_insert_size: Option<u64>,
+        max_insert_size: Option<u64>,
+    ) -> Self {
+        Self {
+            min_mapq,
+            mandatory_flags,
+            prohibited_flags,
+            indel_cutoff,
+            frameshift_cutoff,
+            save_ends,
+            min_observations,
+            long_reads,
+            homopolymer_factor,
+            min_aligned_length,
+            max_edit_fraction,
+            min_alignment_score,
+            max_softclip_fraction,
+            proper_pairs_only,
+            min_insert_size,
+            max_insert_size,
+        }
+    }
+
     pub fn is_suitable(&self, record: &Record) -> bool {
         //! Calculate whether a given SAM/BAM record is suitable for inclusion while counting bases.
         //!
-        //! A read is suitable iff it has the minimum quality, does not have prohibited flags and
-        //! has all mandatory flags.
+        //! A read is suitable iff it has the minimum quality, does not have prohibited flags,
+        //! has all mandatory flags, and passes the optional read filters.
+        self.rejection(record).is_none()
+    }
 
-        let qual_ok = record.mapq() >= self.min_mapq;
+    pub fn rejection(&self, record: &Record) -> Option<RejectionReason> {
+        //! Get the reason for rejecting a SAM/BAM record, or `None`, if it is suitable.
+        //!
+        //! Flags are checked first, then the mapping quality, and then the optional read filters.
+        //! Only the first failed check is reported.
 
+        // the lowest set bit identifies a single flag
         let flags = record.flags();
-        let mandatory_masked = self.mandatory_flags & flags;
-        let has_mandatory = mandatory_masked == self.mandatory_flags;
-
         let prohibited_masked = self.prohibited_flags & flags;
-        let no_prohibited = prohibited_masked == 0;
-        let flags_ok = has_mandatory & no_prohibited;
+        if prohibited_masked != 0 {
+            return Some(RejectionReason::ProhibitedFlag(prohibited_masked & prohibited_masked.wrapping_neg()));
+        }
+
+        let missing_mandatory = self.mandatory_flags & !flags;
+        if missing_mandatory != 0 {
+            return Some(RejectionReason::MissingFlag(missing_mandatory & missing_mandatory.wrapping_neg()));
+        }
+
+        if record.mapq() < self.min_mapq {
+            return Some(RejectionReason::LowMapq);
+        }
+
+        self.read_filter_rejection(record)
+    }
+
+    /// Get the first of the optional read filters on aligned length, edit distance, alignment score,
+    /// soft-clipping, pairing and insert size, that a record fails, or `None` if it passes all of them.
+    fn read_filter_rejection(&self, record: &Record) -> Option<RejectionReason> {
+        // the CIGAR is only parsed, if a filter needs the number of soft-clipped bases
+        let needs_softclips =
+            self.min_aligned_length.is_some() || self.max_edit_fraction.is_some() || self.max_softclip_fraction.is_some();
+        let read_len = record.seq_len();
+        let softclips = if needs_softclips {
+            let cigar = record.cigar();
+            (cigar.leading_softclips() + cigar.trailing_softclips()) as usize
+        } else {
+            0
+        };
+        let aligned_len = read_len.saturating_sub(softclips);
 
-        qual_ok & flags_ok
+        let length_ok = self.min_aligned_length.is_none_or(|min_len| aligned_len >= min_len);
+        let edits_ok = self.max_edit_fraction.is_none_or(|max_fraction| {
+            Self::integer_tag(record, b"NM").is_some_and(|nm| nm as f64 <= max_fraction * aligned_len as f64)
+        });
+        let score_ok = self
+            .min_alignment_score
+            .is_none_or(|min_score| Self::integer_tag(record, b"AS").is_some_and(|score| score >= min_score));
+        let clips_ok = self
+            .max_softclip_fraction
+            .is_none_or(|max_fraction| softclips as f64 <= max_fraction * read_len as f64);
+        let pair_ok = !self.proper_pairs_only || record.is_proper_pair();
+
+        let insert_size = record.insert_size().unsigned_abs();
+        let min_insert_ok = self.min_insert_size.is_none_or(|min_size| insert_size > 0 && insert_size >= min_size);
+        let max_insert_ok = self.max_insert_size.is_none_or(|max_size| insert_size <= max_size);
+
+        let checks = [
+            (length_ok, RejectionReason::ShortAlignment),
+            (edits_ok, RejectionReason::EditDistance),
+            (score_ok, RejectionReason::AlignmentScore),
+            (clips_ok, RejectionReason::SoftClipping),
+            (pair_ok, RejectionReason::ImproperPair),
+            (min_insert_ok && max_insert_ok, RejectionReason::InsertSize),
+        ];
+        checks.into_iter().find(|(ok, _reason)| !ok).map(|(_ok, reason)| reason)
+    }
+
+    /// Value of an integer tag of a record, or `None`, if the tag is missing or not an integer.
+    fn integer_tag(record: &Record, tag: &[u8]) -> Option<i64> {
+        match record.aux(tag) {
+            Ok(Aux::I8(v)) => Some(v as i64),
+            Ok(Aux::U8(v)) => Some(v as i64),
+            Ok(Aux::I16(v)) => Some(v as i64),
+            Ok(Aux::U16(v)) => Some(v as i64),
+            Ok(Aux::I32(v)) => Some(v as i64),
+            Ok(Aux::U32(v)) => Some(v as i64),
+            _ => None,
+        }
+    }
+
+    pub fn indel_cutoff_for(&self, cutoff: f64, indel: &InDel, ref_seq: &[u8]) -> f64 {
+        //! Get the support an indel needs, given the `cutoff` for indels outside homopolymer runs.
+        //!
+        //! In long-read mode, the cutoff of indels changing a homopolymer run grows with the length of the run,
+        //! but never exceeds one.
+        if !self.long_reads {
+            return cutoff;
+        }
+
+        let extra_bases = indel.homopolymer_run(ref_seq).saturating_sub(1);
+        (cutoff + self.homopolymer_factor * extra_bases as f64).min(1.0)
+    }
+
+    pub fn from_preset(preset: Preset) -> Self {
+        //! Get the alignment quality requirements of a named preset.
+        match preset {
+            // duplicates are expected for amplicons, so only unmapped, secondary, failed and supplementary reads are excluded
+            Preset::IlluminaAmplicon => Self { min_mapq: 20, prohibited_flags: 2820, min_observations: 20, ..Self::default() },
+            Preset::IlluminaShotgun => Self { min_mapq: 20, prohibited_flags: 3844, min_observations: 10, ..Self::default() },
+            // split reads are common, so supplementary alignments are kept,
+            // and with frequent base errors, fewer agreeing bases suffice for calling a position
+            Preset::Nanopore => Self {
+                min_mapq: 10,
+                prohibited_flags: 772,
+                indel_cutoff: 0.4,
+                frameshift_cutoff: 0.6,
+                min_observations: LONG_READ_MIN_OBSERVATIONS,
+                long_reads: true,
+                ..Self::default()
+            },
+        }
+    }
+
+    pub fn load(preset: Option<Preset>, config_path: Option<&str>) -> Self {
+        //! Load alignment quality requirements from a preset and a TOML file.
+        //!
+        //! - `preset: Option<Preset>`: A preset, taking precedence over a preset named in the file.
+        //! - `config_path: Option<&str>`: Path to a TOML file, with a setting per key, and optionally a `preset`.
+        //!
+        //! Settings missing from the file are taken from the preset, or the defaults.
+        //! In long-read mode, the default `min_observations` is lowered, like in `with_long_read_default`.
+        let (reqs, sets_min_observations) = Self::load_settings(preset, config_path);
+        reqs.with_long_read_default(sets_min_observations)
+    }
+
+    pub fn load_settings(preset: Option<Preset>, config_path: Option<&str>) -> (Self, bool) {
+        //! Like `load`, but without lowering `min_observations` in long-read mode.
+        //! Also tells, whether `min_observations` was set by the preset or the file, instead of taken from the defaults.
+        let file = match config_path {
+            Some(path) => AlnQualityReqsFile::read(path),
+            None => AlnQualityReqsFile::default(),
+        };
+
+        let preset = preset.or(file.preset);
+        let sets_min_observations = preset.is_some() || file.min_observations.is_some();
+        let reqs = match preset {
+            Some(preset) => Self::from_preset(preset),
+            None => Self::default(),
+        };
+        (file.apply(reqs), sets_min_observations)
+    }
+
+    pub fn with_long_read_default(self, sets_min_observations: bool) -> Self {
+        //! In long-read mode, lower `min_observations` to `LONG_READ_MIN_OBSERVATIONS`,
+        //! unless it was set explicitly, as told by `sets_min_observations`.
+        match self.long_reads && !sets_min_observations {
+            true => Self { min_observations: LONG_READ_MIN_OBSERVATIONS, ..self },
+            false => self,
+        }
+    }
+
+    pub fn merged_with<F: Fn(&str) -> bool>(self, base: Self, is_explicit: F) -> Self {
+        //! Take the settings from `base`, that were not set explicitly, e.g. by command line flags.
+        //! `is_explicit` receives the name of a setting, and tells whether it was set explicitly.
+        Self {
+            min_mapq: if is_explicit("min_mapq") { self.min_mapq } else { base.min_mapq },
+            mandatory_flags: if is_explicit("mandatory_flags") { self.mandatory_flags } else { base.mandatory_flags },
+            prohibited_flags: if is_explicit("prohibited_flags") { self.prohibited_flags } else { base.prohibited_flags },
+            indel_cutoff: if is_explicit("indel_cutoff") { self.indel_cutoff } else { base.indel_cutoff },
+            frameshift_cutoff: if is_explicit("frameshift_cutoff") { self.frameshift_cutoff } else { base.frameshift_cutoff },
+            save_ends: if is_explicit("save_ends") { self.save_ends } else { base.save_ends },
+            min_observations: if is_explicit("min_observations") { self.min_observations } else { base.min_observations },
+            long_reads: if is_explicit("long_reads") { self.long_reads } else { base.long_reads },
+            homopolymer_factor: if is_explicit("homopolymer_factor") { self.homopolymer_factor } else { base.homopolymer_factor },
+            min_aligned_length: if is_explicit("min_aligned_length") { self.min_aligned_length } else { base.min_aligned_length },
+            max_edit_fraction: if is_explicit("max_edit_fraction") { self.max_edit_fraction } else { base.max_edit_fraction },
+            min_alignment_score: if is_explicit("min_alignment_score") { self.min_alignment_score } else { base.min_alignment_score },
+            max_softclip_fraction: if is_explicit("max_softclip_fraction") { self.max_softclip_fraction } else { base.max_softclip_fraction },
+            proper_pairs_only: if is_explicit("proper_pairs_only") { self.proper_pairs_only } else { base.proper_pairs_only },
+            min_insert_size: if is_explicit("min_insert_size") { self.min_insert_size } else { base.min_insert_size },
+            max_insert_size: if is_explicit("max_insert_size") { self.max_insert_size } else { base.max_insert_size },
+        }
+    }
+
+    /// Format the settings as comma-separated `key=value` pairs, with flags given by name.
+    /// Read filters are only included, if they are set.
+    /// With `python`, values are formatted as Python literals, like the arguments of the constructor.
+    fn format_settings(&self, python: bool) -> String {
+        let quote = if python { "'" } else { "" };
+        let boolean = |value: bool| match (python, value) {
+            (true, true) => String::from("True"),
+            (true, false) => String::from("False"),
+            (false, value) => value.to_string(),
+        };
+
+        let mut settings = vec![
+            format!("min_mapq={}", self.min_mapq),
+            format!("mandatory_flags={quote}{}{quote}", flag_names(self.mandatory_flags)),
+            format!("prohibited_flags={quote}{}{quote}", flag_names(self.prohibited_flags)),
+            format!("indel_cutoff={}", self.indel_cutoff),
+            format!("save_ends={}", self.save_ends),
+            format!("min_observations={}", self.min_observations),
+            format!("frameshift_cutoff={}", self.frameshift_cutoff),
+            format!("long_reads={}", boolean(self.long_reads)),
+            format!("homopolymer_factor={}", self.homopolymer_factor),
+        ];
+
+        let filters = [
+            ("min_aligned_length", self.min_aligned_length.map(|v| v.to_string())),
+            ("max_edit_fraction", self.max_edit_fraction.map(|v| v.to_string())),
+            ("min_alignment_score", self.min_alignment_score.map(|v| v.to_string())),
+            ("max_softclip_fraction", self.max_softclip_fraction.map(|v| v.to_string())),
+            ("proper_pairs_only", self.proper_pairs_only.then(|| boolean(true))),
+            ("min_insert_size", self.min_insert_size.map(|v| v.to_string())),
+            ("max_insert_size", self.max_insert_size.map(|v| v.to_string())),
+        ];
+        for (name, value) in filters {
+            if let Some(value) = value {
+                settings.push(format!("{name}={value}"));
+            }
+        }
+
+        settings.join(", ")
+    }
+}
+
+impl Default for AlnQualityReqs {
+    fn default() -> Self {
+        Self::new(0, 0, 1540, 0.2, 0, 50, 0.5, false, 0.1, None, None, None, None, false, None, None)
+    }
+}
+
+impl Display for AlnQualityReqs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.format_settings(false))
+    }
+}
+
+/// Named presets of alignment quality requirements for common sequencing setups.
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+#[pyclass(eq, eq_int)]
+pub enum Preset {
+    /// Illumina reads of amplicons. Duplicates are kept.
+    IlluminaAmplicon,
+
+    /// Illumina reads of randomly fragmented DNA. Duplicates are discarded.
+    IlluminaShotgun,
+
+    /// Oxford Nanopore reads. Indels need more support.
+    Nanopore,
+}
+
+/// Alignment quality requirements as read from a TOML file, where every setting is optional.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct AlnQualityReqsFile {
+    preset: Option<Preset>,
+    min_mapq: Option<u8>,
+    mandatory_flags: Option<FlagsSetting>,
+    prohibited_flags: Option<FlagsSetting>,
+    indel_cutoff: Option<f64>,
+    frameshift_cutoff: Option<f64>,
+    save_ends: Option<usize>,
+    min_observations: Option<usize>,
+    long_reads: Option<bool>,
+    homopolymer_factor: Option<f64>,
+    min_aligned_length: Option<usize>,
+    max_edit_fraction: Option<f64>,
+    min_alignment_score: Option<i64>,
+    max_softclip_fraction: Option<f64>,
+    proper_pairs_only: Option<bool>,
+    min_insert_size: Option<u64>,
+    max_insert_size: Option<u64>,
+}
+
+impl AlnQualityReqsFile {
+    /// Read the settings from a TOML file.
+    fn read(path: &str) -> Self {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => panic!("Unable to read settings from {path}: {e}"),
+        };
+        match toml::from_str(&content) {
+            Ok(file) => file,
+            Err(e) => panic!("Invalid settings in {path}: {e}"),
+        }
+    }
+
+    /// Override the requirements with the settings present in the file.
+    fn apply(self, reqs: AlnQualityReqs) -> AlnQualityReqs {
+        let flags = |setting: Option<FlagsSetting>, default: u16| match setting.map(|flags| flags.to_bits()) {
+            Some(Ok(bits)) => bits,
+            Some(Err(e)) => panic!("Invalid settings: {e}"),
+            None => default,
+        };
+        AlnQualityReqs {
+            min_mapq: self.min_mapq.unwrap_or(reqs.min_mapq),
+            mandatory_flags: flags(self.mandatory_flags, reqs.mandatory_flags),
+            prohibited_flags: flags(self.prohibited_flags, reqs.prohibited_flags),
+            indel_cutoff: self.indel_cutoff.unwrap_or(reqs.indel_cutoff),
+            frameshift_cutoff: self.frameshift_cutoff.unwrap_or(reqs.frameshift_cutoff),
+            save_ends: self.save_ends.unwrap_or(reqs.save_ends),
+            min_observations: self.min_observations.unwrap_or(reqs.min_observations),
+            long_reads: self.long_reads.unwrap_or(reqs.long_reads),
+            homopolymer_factor: self.homopolymer_factor.unwrap_or(reqs.homopolymer_factor),
+            min_aligned_length: self.min_aligned_length.or(reqs.min_aligned_length),
+            max_edit_fraction: self.max_edit_fraction.or(reqs.max_edit_fraction),
+            min_alignment_score: self.min_alignment_score.or(reqs.min_alignment_score),
+            max_softclip_fraction: self.max_softclip_fraction.or(reqs.max_softclip_fraction),
+            proper_pairs_only: self.proper_pairs_only.unwrap_or(reqs.proper_pairs_only),
+            min_insert_size: self.min_insert_size.or(reqs.min_insert_size),
+            max_insert_size: self.max_insert_size.or(reqs.max_insert_size),
+        }
     }
 }
 
 #[pymethods]
 impl AlnQualityReqs {
+    /// SAM flags may be given as numbers, or as text like `"UNMAP,QCFAIL,DUP"` or `"0x604"`.
+    /// Without `min_observations`, it defaults to 50, or to 10 in long-read mode.
     #[new]
-    pub fn new(min_mapq: u8, mandatory_flags: u16, prohibited_flags: u16, indel_cutoff: f64, save_ends: usize, min_observations: usize) -> Self {
-        Self { min_mapq, mandatory_flags, prohibited_flags, indel_cutoff, save_ends, min_observations }
+    #[pyo3(signature = (
+        min_mapq = 0,
+        mandatory_flags = FlagsSetting::Bits(0),
+        prohibited_flags = FlagsSetting::Bits(1540),
+        indel_cutoff = 0.2,
+        save_ends = 0,
+        min_observations = None,
+        frameshift_cutoff = 0.5,
+        long_reads = false,
+        homopolymer_factor = 0.1,
+        min_aligned_length = None,
+        max_edit_fraction = None,
+        min_alignment_score = None,
+        max_softclip_fraction = None,
+        proper_pairs_only = false,
+        min_insert_size = None,
+        max_insert_size = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn py_new(
+        min_mapq: u8,
+        mandatory_flags: FlagsSetting,
+        prohibited_flags: FlagsSetting,
+        indel_cutoff: f64,
+        save_ends: usize,
+        min_observations: Option<usize>,
+        frameshift_cutoff: f64,
+        long_reads: bool,
+        homopolymer_factor: f64,
+        min_aligned_length: Option<usize>,
+        max_edit_fraction: Option<f64>,
+        min_alignment_score: Option<i64>,
+        max_softclip_fraction: Option<f64>,
+        proper_pairs_only: bool,
+        min_insert_size: Option<u64>,
+        max_insert_size: Option<u64>,
+    ) -> PyResult<Self> {
+        let mandatory_flags = mandatory_flags.to_bits().map_err(PyValueError::new_err)?;
+        let prohibited_flags = prohibited_flags.to_bits().map_err(PyValueError::new_err)?;
+        Ok(Self::new(
+            min_mapq,
+            mandatory_flags,
+            prohibited_flags,
+            indel_cutoff,
+            save_ends,
+            min_observations.unwrap_or(50),
+            frameshift_cutoff,
+            long_reads,
+            homopolymer_factor,
+            min_aligned_length,
+            max_edit_fraction,
+            min_alignment_score,
+            max_softclip_fraction,
+            proper_pairs_only,
+            min_insert_size,
+            max_insert_size,
+        )
+        .with_long_read_default(min_observations.is_some()))
+    }
+
+    #[classmethod]
+    #[pyo3(name = "from_preset")]
+    fn py_from_preset(_cls: &Bound<'_, PyType>, preset: Preset) -> Self {
+        Self::from_preset(preset)
+    }
+
+    #[classmethod]
+    #[pyo3(name = "from_toml", signature = (path, preset = None))]
+    fn py_from_toml(_cls: &Bound<'_, PyType>, path: String, preset: Option<Preset>) -> Self {
+        Self::load(preset, Some(&path))
     }
 
     fn __repr__(&self) -> String {
+        format!("AlnQualityReqs({})", self.format_settings(true))
+    }
+}
+
+/// Settings for analysing aligned reads, that are independent of alignment quality.
+#[derive(Debug, Clone, Args)]
+#[pyclass]
+pub struct AnalysisSettings {
+    /// Path to a file mapping reference labels to contig names in the alignment files.
+    /// Every line holds a FASTA label and the corresponding `@SQ` name, separated by a tab.
+    /// Without a mapping, contigs are matched by the full label, or its first word.
+    #[arg(long)]
+    #[pyo3(get)]
+    pub contig_aliases: Option<String>,
+
+    /// Path to a GFF3 or BED file with annotated coding sequences (CDS).
+    /// Frameshifting indels within a CDS have to satisfy the `frameshift_cutoff`.
+    #[arg(long)]
+    #[pyo3(get)]
+    pub cds_annotation: Option<String>,
+
+    /// Minimum fraction of covering reads, that have to display an allele differing from the consensus,
+    /// for it to be reported as minor variant.
+    #[arg(long, default_value_t = 0.03)]
+    #[pyo3(get)]
+    pub min_variant_frequency: f64,
+
+    /// Minimum number of reads, that have to display an allele differing from the consensus,
+    /// for it to be reported as minor variant.
+    #[arg(long, default_value_t = 5)]
+    #[pyo3(get)]
+    pub min_variant_count: usize,
+
+    /// Two-letter SAM tag, e.g. `RG`, `CB` or `BC`, by which the reads of an alignment file are grouped.
+    /// A separate consensus is calculated for every tag value, as if it were a sample of its own.
+    /// Reads without the tag are ignored.
+    #[arg(long)]
+    #[pyo3(get)]
+    pub group_tag: Option<String>,
+
+    /// Two-letter SAM tag holding the UMI of a read, e.g. `RX`. If set, reads sharing a UMI, strand and mapping position
+    /// are collapsed into a single family consensus read before counting bases and indels.
+    /// Reads without the tag are ignored.
+    #[arg(long)]
+    #[pyo3(get)]
+    pub umi_tag: Option<String>,
+
+    /// Minimum number of reads in a UMI family, for the family to be considered. Only used with `umi_tag`.
+    #[arg(long, default_value_t = 1)]
+    #[pyo3(get)]
+    pub min_family_size: usize,
+
+    /// How to count positions, in which both mates of a pair overlap.
+    #[arg(long, value_enum, default_value_t = MateOverlap::CountBoth)]
+    #[pyo3(get)]
+    pub mate_overlap: MateOverlap,
+
+    /// A region as `contig:start-end`, with one-based, inclusive coordinates, or a path to a BED file with regions.
+    /// If set, only reads within the regions are analysed, and references without a region are skipped.
+    #[arg(long)]
+    #[pyo3(get)]
+    pub regions: Option<String>,
+
+    /// How the consensus is handled outside the regions.
+    #[arg(long, value_enum, default_value_t = OutsideRegions::Reference)]
+    #[pyo3(get)]
+    pub outside_regions: OutsideRegions,
+
+    /// Extend the consensus beyond the ends of the reference, using the bases soft-clipped by reads aligned to the ends.
+    #[arg(long)]
+    #[pyo3(get)]
+    pub extend_ends: bool,
+
+    /// Minimum number of soft-clipped reads, that have to agree on a base for extending an end of the consensus by it.
+    /// Additionally, the agreeing reads have to be the majority of the reads reaching that far. Only used with `extend_ends`.
+    #[arg(long, default_value_t = 10)]
+    #[pyo3(get)]
+    pub min_extension_reads: usize,
+}
+
+/// Rules for the quality control of consensuses.
+#[derive(Debug, Clone, Args)]
+#[pyclass]
+pub struct QcRules {
+    /// Depths, for which the breadth of coverage is reported. The breadth at `min_observations` is always reported.
+    #[arg(long = "qc-depth-thresholds", value_delimiter = ',', default_values_t = [1, 10])]
+    #[pyo3(get)]
+    pub depth_thresholds: Vec<usize>,
+
+    /// Minimum fraction of positions, in which the consensus is called from the reads instead of the reference.
+    /// Consensuses below fail the quality control.
+    #[arg(long = "qc-min-called-fraction", default_value_t = 0.5)]
+    #[pyo3(get)]
+    pub min_called_fraction: f64,
+
+    /// Fraction of positions, in which the consensus is called from the reads instead of the reference,
+    /// below which a warning is issued.
+    #[arg(long = "qc-warn-called-fraction", default_value_t = 0.9)]
+    #[pyo3(get)]
+    pub warn_called_fraction: f64,
+
+    /// Minimum mean depth of coverage. Consensuses below fail the quality control.
+    #[arg(long = "qc-min-mean-depth", default_value_t = 0.0)]
+    #[pyo3(get)]
+    pub min_mean_depth: f64,
+}
+
+impl QcRules {
+    pub fn judge(&self, stats: &QcStats) -> (QcVerdict, Vec<String>) {
+        //! Judge the coverage statistics of a consensus.
+        //!
+        //! Returns the most severe verdict of all rules, and a reason for every rule, that was not passed.
+        let mut verdict = QcVerdict::Pass;
+        let mut reasons = Vec::new();
+
+        let called_fraction = stats.get_called_fraction();
+        if called_fraction < self.min_called_fraction {
+            verdict = verdict.max(QcVerdict::Fail);
+            reasons.push(format!("called fraction {called_fraction:.4} < {}", self.min_called_fraction));
+        } else if called_fraction < self.warn_called_fraction {
+            verdict = verdict.max(QcVerdict::Warn);
+            reasons.push(format!("called fraction {called_fraction:.4} < {}", self.warn_called_fraction));
+        }
+
+        let mean_depth = stats.get_mean_depth();
+        if mean_depth < self.min_mean_depth {
+            verdict = verdict.max(QcVerdict::Fail);
+            reasons.push(format!("mean depth {mean_depth:.2} < {}", self.min_mean_depth));
+        }
+
+        (verdict, reasons)
+    }
+}
+
+impl Default for QcRules {
+    fn default() -> Self {
+        Self::new(vec![1, 10], 0.5, 0.9, 0.0)
+    }
+}
+
+#[pymethods]
+impl QcRules {
+    #[new]
+    #[pyo3(signature = (depth_thresholds = vec![1, 10], min_called_fraction = 0.5, warn_called_fraction = 0.9, min_mean_depth = 0.0))]
+    pub fn new(depth_thresholds: Vec<usize>, min_called_fraction: f64, warn_called_fraction: f64, min_mean_depth: f64) -> Self {
+        Self { depth_thresholds, min_called_fraction, warn_called_fraction, min_mean_depth }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "QcRules(depth_thresholds={:?}, min_called_fraction={}, warn_called_fraction={}, min_mean_depth={})",
+            self.depth_thresholds, self.min_called_fraction, self.warn_called_fraction, self.min_mean_depth
+        )
+    }
+}
+
+/// Handling of the consensus outside the regions, that the consensus calculation is restricted to.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+#[pyclass(eq, eq_int)]
+pub enum OutsideRegions {
+    /// Use the reference sequence.
+    Reference,
+
+    /// Replace the bases with `N`.
+    Mask,
+
+    /// Trim the consensus to the span of the regions. Bases between regions are replaced with `N`.
+    Trim,
+}
+
+/// Handling of positions, in which both mates of a pair overlap.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+#[pyclass(eq, eq_int)]
+pub enum MateOverlap {
+    /// Count both mates, i.e. the fragment is counted twice.
+    CountBoth,
+
+    /// Count the fragment once. If the mates disagree, the base with the higher quality is counted.
+    PreferQuality,
+
+    /// Count the fragment once. If the mates disagree, the fragment is not counted in this position.
+    DropDiscordant,
+}
+
+impl Default for AnalysisSettings {
+    fn default() -> Self {
+        Self::new(None, None, 0.03, 5, None, None, 1, MateOverlap::CountBoth, None, OutsideRegions::Reference, false, 10)
+    }
+}
+
+#[pymethods]
+impl AnalysisSettings {
+    #[new]
+    #[pyo3(signature = (
+        contig_aliases = None,
+        cds_annotation = None,
+        min_variant_frequency = 0.03,
+        min_variant_count = 5,
+        group_tag = None,
+        umi_tag = None,
+        min_family_size = 1,
+        mate_overlap = MateOverlap::CountBoth,
+        regions = None,
+        outside_regions = OutsideRegions::Reference,
+        extend_ends = false,
+        min_extension_reads = 10,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        contig_aliases: Option<String>,
+        cds_annotation: Option<String>,
+        min_variant_frequency: f64,
+        min_variant_count: usize,
+        group_tag: Option<String>,
+        umi_tag: Option<String>,
+        min_family_size: usize,
+        mate_overlap: MateOverlap,
+        regions: Option<String>,
+        outside_regions: OutsideRegions,
+        extend_ends: bool,
+        min_extension_reads: usize,
+    ) -> Self {
+        Self {
+            contig_aliases,
+            cds_annotation,
+            min_variant_frequency,
+            min_variant_count,
+            group_tag,
+            umi_tag,
+            min_family_size,
+            mate_overlap,
+            regions,
+            outside_regions,
+            extend_ends,
+            min_extension_reads,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        let format_opt = |opt: &Option<String>| match opt {
+            Some(value) => format!("'{value}'"),
+            None => String::from("None"),
+        };
         format!(
-            "AlnQualityReqs(min_mapq={}, mandatory_flags={}, prohibited_flags={}, indel_cutoff={}, save_ends={}, min_observations={})",
-            self.min_mapq, self.mandatory_flags, self.prohibited_flags, self.indel_cutoff, self.save_ends, self.min_observations
+            "AnalysisSettings(contig_aliases={}, cds_annotation={}, min_variant_frequency={}, min_variant_count={}, group_tag={}, umi_tag={}, min_family_size={}, mate_overlap={:?}, regions={}, outside_regions={:?}, extend_ends={}, min_extension_reads={})",
+            format_opt(&self.contig_aliases), format_opt(&self.cds_annotation),
+            self.min_variant_frequency, self.min_variant_count, format_opt(&self.group_tag),
+            format_opt(&self.umi_tag), self.min_family_size, self.mate_overlap,
+            format_opt(&self.regions), self.outside_regions, self.extend_ends, self.min_extension_reads,
         )
     }
 }
@@ -98,8 +824,18 @@ mod tests {
             mandatory_flags: mandatory,
             prohibited_flags: prohibited,
             indel_cutoff: 0.2,
+            frameshift_cutoff: 0.5,
             save_ends: 24,
             min_observations: 50,
+            long_reads: false,
+            homopolymer_factor: 0.1,
+            min_aligned_length: None,
+            max_edit_fraction: None,
+            min_alignment_score: None,
+            max_softclip_fraction: None,
+            proper_pairs_only: false,
+            min_insert_size: None,
+            max_insert_size: None,
         }
     }
 
@@ -157,4 +893,66 @@ mod tests {
             assert!(reqs.is_suitable(&rec))
         }
     }
+
+    #[test]
+    fn aln_reqs_filter_reads() {
+        use rust_htslib::bam::record::{Cigar, CigarString};
+
+        let mut rec = Record::new();
+        let cigar = CigarString(vec![Cigar::SoftClip(20), Cigar::Match(80)]);
+        rec.set(b"read", Some(&cigar), &[b'A'; 100], &[30; 100]);
+        rec.push_aux(b"NM", Aux::U8(4)).unwrap();
+        rec.set_flags(0);
+        rec.set_insert_size(-300);
+
+        let reqs = AlnQualityReqs::default();
+        assert!(reqs.is_suitable(&rec));
+        assert!(AlnQualityReqs { min_aligned_length: Some(80), max_edit_fraction: Some(0.05), ..reqs.clone() }.is_suitable(&rec));
+        assert!(!AlnQualityReqs { min_aligned_length: Some(81), ..reqs.clone() }.is_suitable(&rec));
+        assert!(!AlnQualityReqs { max_edit_fraction: Some(0.04), ..reqs.clone() }.is_suitable(&rec));
+        assert!(!AlnQualityReqs { min_alignment_score: Some(0), ..reqs.clone() }.is_suitable(&rec));
+        assert!(!AlnQualityReqs { max_softclip_fraction: Some(0.1), ..reqs.clone() }.is_suitable(&rec));
+        assert!(!AlnQualityReqs { proper_pairs_only: true, ..reqs.clone() }.is_suitable(&rec));
+        assert!(AlnQualityReqs { min_insert_size: Some(300), max_insert_size: Some(300), ..reqs.clone() }.is_suitable(&rec));
+        assert!(!AlnQualityReqs { max_insert_size: Some(299), ..reqs }.is_suitable(&rec));
+    }
+
+    #[test]
+    fn aln_reqs_from_file_preset_and_flags() {
+        let toml = "preset = \"nanopore\"\nmin_mapq = 5\nprohibited_flags = \"UNMAP,SUPPLEMENTARY\"\n";
+        let file: AlnQualityReqsFile = toml::from_str(toml).unwrap();
+        assert_eq!(file.preset, Some(Preset::Nanopore));
+
+        let preset = AlnQualityReqs::from_preset(Preset::Nanopore);
+        let reqs = file.apply(preset.clone());
+        assert_eq!(reqs.min_mapq, 5);
+        assert_eq!(reqs.indel_cutoff, preset.indel_cutoff);
+        assert_eq!(reqs.prohibited_flags, 0x804);
+
+        let flags = AlnQualityReqs { min_observations: 3, ..AlnQualityReqs::default() };
+        let merged = flags.merged_with(reqs, |name| name == "min_observations");
+        assert_eq!((merged.min_mapq, merged.min_observations), (5, 3));
+
+        assert!(toml::from_str::<AlnQualityReqsFile>("min_mapk = 5").is_err());
+    }
+
+    #[test]
+    fn long_read_min_observations() {
+        let long_reads = AlnQualityReqs { long_reads: true, ..AlnQualityReqs::default() };
+        assert_eq!(long_reads.clone().with_long_read_default(false).min_observations, LONG_READ_MIN_OBSERVATIONS);
+        assert_eq!(long_reads.with_long_read_default(true).min_observations, 50);
+        assert_eq!(AlnQualityReqs::default().with_long_read_default(false).min_observations, 50);
+
+        // a value from the file, or the preset, is kept
+        let path = std::env::temp_dir().join(format!("{}_long_reads.toml", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        fs::write(&path, "long_reads = true\nmin_observations = 30\n").unwrap();
+        let (reqs, sets_min_observations) = AlnQualityReqs::load_settings(None, Some(&path));
+        assert_eq!((reqs.min_observations, sets_min_observations), (30, true));
+        assert_eq!(AlnQualityReqs::load(None, Some(&path)).min_observations, 30);
+        fs::write(&path, "long_reads = true\n").unwrap();
+        assert_eq!(AlnQualityReqs::load(None, Some(&path)).min_observations, LONG_READ_MIN_OBSERVATIONS);
+        assert_eq!(AlnQualityReqs::load(Some(Preset::IlluminaAmplicon), Some(&path)).min_observations, 20);
+        fs::remove_file(&path).unwrap();
+    }
 }
@@ -0,0 +1,331 @@
+//! Module for keeping track of reads, while a pileup passes over them.
+//!
+//! Instead of remembering every seen read, only the alignments and reads around the pileup are held.
+//! Per reference, memory is bounded by the alignments overlapping the last `EVICTION_INTERVAL` positions
+//! of the pileup, and the reads with an alignment starting within them, or with a mate or supplementary alignment
+//! still to come, each taking about 100 bytes plus the length of the read name and group. So it grows with the depth,
+//! the insert sizes and the distances between the parts of split reads, but not with the number of reads
+//! in the alignment file.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use itertools::Itertools;
+use rust_htslib::bam::record::Aux;
+use rust_htslib::bam::Record;
+
+use super::data::accounting::{ReadAccounting, RejectionReason};
+
+/// Number of reference positions, after which decisions of alignments the pileup has passed are dropped.
+const EVICTION_INTERVAL: i64 = 1000;
+
+/// Identifies an alignment record by read name, flags and start position.
+/// The flags tell mates, and primary, secondary and supplementary alignments of a read apart.
+type AlignmentKey = (Vec<u8>, u16, i64);
+
+/// Cached decisions on alignments, e.g. whether they are suitable, so that every alignment is only evaluated once,
+/// instead of at every pileup position it covers. Decisions are dropped, once the pileup has passed
+/// the end of the respective alignment, so the cache only holds the alignments overlapping the pileup.
+#[derive(Default)]
+pub struct ReadDecisions<T: Copy> {
+    /// Decisions per alignment, with the end position of the alignment.
+    decisions: HashMap<AlignmentKey, (T, i64)>,
+
+    /// Position of the pileup, at which decisions were last dropped.
+    evicted_at: i64,
+}
+
+impl<T: Copy> ReadDecisions<T> {
+    /// Get the cached decision for an alignment, or make it with `decide` and cache it.
+    pub fn decide<F: FnOnce(&Record) -> T>(&mut self, record: &Record, decide: F) -> T {
+        let key = (record.qname().to_vec(), record.flags(), record.pos());
+        let (decision, _end) = *self
+            .decisions
+            .entry(key)
+            .or_insert_with(|| (decide(record), record.cigar().end_pos()));
+        decision
+    }
+
+    /// Drop the decisions of alignments ending before `pos`. Dropping is skipped, unless the pileup moved on
+    /// by `EVICTION_INTERVAL` positions, or moved backward, e.g. to the next interval, since dropping last.
+    pub fn evict_before(&mut self, pos: i64) {
+        if pos >= self.evicted_at && pos - self.evicted_at < EVICTION_INTERVAL {
+            return;
+        }
+        self.decisions.retain(|_key, (_decision, end)| *end >= pos);
+        self.evicted_at = pos;
+    }
+
+    /// Number of cached decisions.
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.decisions.len()
+    }
+}
+
+/// Keys of things spanning several pileup positions, e.g. UMI families, that were already seen by the pileup.
+/// Like decisions, keys are dropped once the pileup has passed the end of what they identify.
+pub struct SeenKeys<K: Hash + Eq> {
+    /// Seen keys, with the end position of what they identify.
+    keys: HashMap<K, i64>,
+
+    /// Position of the pileup, at which keys were last dropped.
+    evicted_at: i64,
+}
+
+impl<K: Hash + Eq> Default for SeenKeys<K> {
+    fn default() -> Self {
+        Self { keys: HashMap::new(), evicted_at: 0 }
+    }
+}
+
+impl<K: Hash + Eq> SeenKeys<K> {
+    /// Whether `key` is seen for the first time. If so, it is remembered until the pileup passes `end`.
+    pub fn first_seen<F: FnOnce() -> i64>(&mut self, key: K, end: F) -> bool {
+        if self.keys.contains_key(&key) {
+            return false;
+        }
+        self.keys.insert(key, end());
+        true
+    }
+
+    /// Drop the keys of things ending before `pos`, like `ReadDecisions::evict_before`.
+    pub fn evict_before(&mut self, pos: i64) {
+        if pos >= self.evicted_at && pos - self.evicted_at < EVICTION_INTERVAL {
+            return;
+        }
+        self.keys.retain(|_key, end| *end >= pos);
+        self.evicted_at = pos;
+    }
+}
+
+/// Counts distinct reads per group, accepted or rejected for the consensus calculation, without remembering them.
+///
+/// A read is accepted, if any of its primary or supplementary alignments is accepted, e.g. one of its mates.
+/// Otherwise, it is rejected for the reason of the first of them. A read is held, until the pileup has passed
+/// the start of its mate, which is known from `RNEXT` and `PNEXT`, and of its other supplementary or primary
+/// alignments on the reference, which are known from the `SA` tag. Secondary alignments are not referenced
+/// by the other alignments of their read, so they are not counted. Reads with only secondary alignments
+/// in the analysed positions are not counted at all.
+///
+/// This relies on the pileup passing the positions in ascending order, so that every alignment is seen in the first
+/// analysed position it covers. After a position is analysed, no alignment starting in or before it is seen anymore.
+pub struct ReadTally {
+    /// Name of the reference contig, to tell the alignments in the `SA` tag on it apart.
+    contig: Vec<u8>,
+
+    /// Held reads by group and read name, with the decision so far, and the start of their last known alignment.
+    held: HashMap<(String, Vec<u8>), (Option<RejectionReason>, i64)>,
+
+    /// Counted reads per group.
+    counts: HashMap<String, ReadAccounting>,
+
+    /// Position of the pileup column, that is currently analysed.
+    current: Option<i64>,
+
+    /// Position of the last completely analysed pileup column.
+    analysed: Option<i64>,
+
+    /// Position of the pileup, at which completed reads were last counted.
+    counted_at: i64,
+}
+
+impl ReadTally {
+    pub fn new(contig: &[u8]) -> Self {
+        Self {
+            contig: contig.to_vec(),
+            held: HashMap::new(),
+            counts: HashMap::new(),
+            current: None,
+            analysed: None,
+            counted_at: 0,
+        }
+    }
+
+    /// Move on to the pileup column in `pos`, and count the held reads, whose alignments have all been seen.
+    /// Like with `ReadDecisions`, held reads are only checked every `EVICTION_INTERVAL` positions.
+    pub fn advance(&mut self, pos: i64) {
+        self.analysed = self.current;
+        self.current = Some(pos);
+
+        if pos - self.counted_at < EVICTION_INTERVAL {
+            return;
+        }
+        self.count_completed();
+        self.counted_at = pos;
+    }
+
+    /// Register the decision on an alignment of a read in `group`. Every alignment must be registered once.
+    /// Secondary alignments are ignored.
+    pub fn register(&mut self, group: &str, record: &Record, rejection: Option<RejectionReason>) {
+        if record.is_secondary() {
+            return;
+        }
+        let key = (group.to_string(), record.qname().to_vec());
+        let last_known_pos = self.last_known_pos(record);
+
+        if let Some((decision, held_until)) = self.held.get_mut(&key) {
+            *decision = Self::combine(*decision, rejection);
+            *held_until = (*held_until).max(last_known_pos);
+        } else {
+            self.held.insert(key, (rejection, last_known_pos));
+        }
+    }
+
+    /// Count the held reads, and get the counts per group.
+    pub fn finish(mut self) -> HashMap<String, ReadAccounting> {
+        for ((group, _name), (decision, _held_until)) in std::mem::take(&mut self.held) {
+            self.count(&group, decision);
+        }
+        self.counts
+    }
+
+    /// Number of held reads.
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.held.len()
+    }
+
+    /// The start position of the last alignment of the read on the reference, that is known from the passed alignment,
+    /// i.e. from its mate, and from the other parts of a split read in the `SA` tag.
+    fn last_known_pos(&self, record: &Record) -> i64 {
+        let mut last_known_pos = record.pos();
+        if record.is_paired() && !record.is_mate_unmapped() && record.mtid() == record.tid() {
+            last_known_pos = last_known_pos.max(record.mpos());
+        }
+
+        // `SA` holds `rname,pos,strand,CIGAR,mapQ,NM;` per other alignment, with 1-based positions
+        if let Ok(Aux::String(split_alignments)) = record.aux(b"SA") {
+            let split_positions = split_alignments
+                .split_terminator(';')
+                .filter_map(|alignment| alignment.split(',').next_tuple())
+                .filter(|(rname, _pos)| rname.as_bytes() == self.contig)
+                .filter_map(|(_rname, pos)| pos.parse::<i64>().ok());
+            for pos in split_positions {
+                last_known_pos = last_known_pos.max(pos - 1);
+            }
+        }
+
+        last_known_pos
+    }
+
+    /// Count the held reads, whose last known alignment starts in or before the last completely analysed position.
+    fn count_completed(&mut self) {
+        let analysed = match self.analysed {
+            Some(analysed) => analysed,
+            None => return,
+        };
+        let completed = self.held.extract_if(|_key, (_decision, held_until)| *held_until <= analysed).collect_vec();
+        for ((group, _name), (decision, _held_until)) in completed {
+            self.count(&group, decision);
+        }
+    }
+
+    fn count(&mut self, group: &str, decision: Option<RejectionReason>) {
+        self.counts.entry(group.to_string()).or_default().count(decision);
+    }
+
+    /// Combine the decisions on two alignments of a read: it is accepted, if either is accepted,
+    /// and otherwise rejected for the first reason.
+    fn combine(first: Option<RejectionReason>, second: Option<RejectionReason>) -> Option<RejectionReason> {
+        match (first, second) {
+            (Some(reason), Some(_)) => Some(reason),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_htslib::bam::record::{Cigar, CigarString};
+    use std::collections::BTreeMap;
+    use RejectionReason::{LowMapq, SoftClipping};
+
+    fn init_record(name: &[u8], pos: i64, len: u32) -> Record {
+        let mut rec = Record::new();
+        let seq = vec![b'A'; len as usize];
+        let qual = vec![30; len as usize];
+        rec.set(name, Some(&CigarString(vec![Cigar::Match(len)])), &seq, &qual);
+        rec.set_pos(pos);
+        rec
+    }
+
+    #[test]
+    fn decisions_are_cached_and_evicted() {
+        let mut decisions = ReadDecisions::default();
+        let short = init_record(b"short", 0, 10);
+        let long = init_record(b"long", 0, 2000);
+
+        assert!(decisions.decide(&short, |_| true));
+        assert!(decisions.decide(&short, |_| panic!("Decision was not cached.")));
+        assert!(!decisions.decide(&long, |_| false));
+        assert_eq!(decisions.len(), 2);
+
+        decisions.evict_before(500);
+        assert_eq!(decisions.len(), 2);
+        decisions.evict_before(1500);
+        assert_eq!(decisions.len(), 1);
+        assert!(!decisions.decide(&long, |_| panic!("Decision was not cached.")));
+    }
+
+    #[test]
+    fn keys_are_seen_once() {
+        let mut seen = SeenKeys::default();
+        assert!(seen.first_seen("family", || 1200));
+        assert!(!seen.first_seen("family", || panic!("End was computed again.")));
+
+        seen.evict_before(1100);
+        assert!(!seen.first_seen("family", || 1200));
+        seen.evict_before(2200);
+        assert!(seen.first_seen("family", || 3000));
+    }
+
+    fn init_alignment(name: &[u8], pos: i64, flags: u16, mate_pos: Option<i64>, split: Option<&str>) -> Record {
+        let mut rec = init_record(name, pos, 10);
+        rec.set_tid(0);
+        rec.set_flags(flags);
+        if let Some(mate_pos) = mate_pos {
+            rec.set_mtid(0);
+            rec.set_mpos(mate_pos);
+        }
+        if let Some(split) = split {
+            rec.push_aux(b"SA", Aux::String(split)).unwrap();
+        }
+        rec
+    }
+
+    #[test]
+    fn reads_are_counted_once() {
+        let mut tally = ReadTally::new(b"ref");
+        let first = init_alignment(b"pair", 0, 0x41, Some(1500), None);
+        let second = init_alignment(b"pair", 1500, 0x81, Some(0), None);
+        let primary = init_alignment(b"split", 10, 0, None, Some("other,5,+,10M,60,0;ref,2001,+,10M,60,0;"));
+        let supplementary = init_alignment(b"split", 2000, 0x800, None, Some("ref,11,+,10M,60,0;"));
+        let secondary = init_alignment(b"split", 2500, 0x100, None, None);
+        let single = init_alignment(b"single", 2500, 0, None, None);
+
+        tally.advance(0);
+        tally.register("a", &first, Some(LowMapq));
+        tally.advance(10);
+        tally.register("a", &primary, Some(SoftClipping));
+        // both reads are held for their other alignments
+        tally.advance(1500);
+        assert_eq!(tally.len(), 2);
+        tally.register("a", &second, None);
+        tally.advance(2000);
+        tally.register("a", &supplementary, Some(LowMapq));
+        tally.advance(2500);
+        assert_eq!(tally.len(), 0);
+
+        // secondary alignments are not counted
+        tally.register("a", &secondary, Some(LowMapq));
+        tally.register("b", &single, Some(LowMapq));
+        assert_eq!(tally.len(), 1);
+
+        let counts = tally.finish();
+        assert_eq!(counts["a"].get_accepted(), 1);
+        assert_eq!(counts["a"].get_rejected(), &BTreeMap::from([(SoftClipping, 1)]));
+        assert_eq!(counts["b"].total(), 1);
+    }
+}
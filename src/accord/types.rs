@@ -1,6 +1,8 @@
+use crate::accord::data::consensus::Consensus;
 use crate::accord::data::indel::InDel;
+use crate::accord::data::sample::Sample;
 use counter::Counter;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// A list of base counts for every position in the reference sequence.
 pub type BaseCounts = Vec<Counter<u8>>;
@@ -13,3 +15,9 @@ pub type InDelCounts = Counter<InDel>;
 
 /// Vector containing coverage of a reference genome per base position.
 pub type Coverage = Vec<usize>;
+
+/// Consensuses per group of reads, keyed by the value of the tag by which reads were grouped.
+pub type GroupedConsensuses = BTreeMap<String, Vec<Consensus>>;
+
+/// Samples paired with the outcome of processing them, which is either a result or an error message.
+pub type BatchResults<T> = Vec<(Sample, Result<T, String>)>;
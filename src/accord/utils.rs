@@ -1,24 +1,86 @@
 //! Module containing utility functions for file IO.
 
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::Path;
 
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+/// Magic bytes at the start of gzip (and thereby bgzip) compressed files.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
 pub fn write_file(content: &String, path: &str) -> () {
     //! Helper function to write files.
+    //! Files with a `.gz` suffix are gzip compressed.
+
+    if let Err(e) = try_write_file(content, path) {
+        panic!("{e}");
+    }
+}
+
+pub fn try_write_file(content: &str, path: &str) -> Result<(), String> {
+    //! Like `write_file`, but returns an error instead of panicking, e.g. if the file already exists.
+
+    let path = Path::new(path);
+    let loc = path.display(); // used in error messages
+
+    let file = File::create_new(path).map_err(|e| format!("Failed to create {loc}: {e}"))?;
+    let mut file = wrap_writer(file, path);
+    file.write_all(content.as_bytes()).and_then(|_| file.flush()).map_err(|e| format!("Failed to write {loc}: {e}"))
+}
+
+pub fn create_file(path: &str) -> Box<dyn Write> {
+    //! Helper function to create files for buffered writing.
+    //! Existing files are not overwritten. Files with a `.gz` suffix are gzip compressed.
 
     let path = Path::new(path);
     let loc = path.display(); // used in error messages
 
     // open file for writing
-    let mut file = match File::create_new(&path) {
+    let file = match File::create_new(path) {
         Err(e) => panic!("Failed to create {loc}: {e}"),
         Ok(f) => f,
     };
 
-    match file.write_all(content.as_bytes()) {
+    wrap_writer(file, path)
+}
+
+/// Buffer writing to a file, and compress it with gzip, if `path` has a `.gz` suffix.
+fn wrap_writer(file: File, path: &Path) -> Box<dyn Write> {
+    let writer = BufWriter::new(file);
+    let is_gzipped = path.extension().is_some_and(|ext| ext == "gz");
+    if is_gzipped {
+        Box::new(GzEncoder::new(writer, Compression::default()))
+    } else {
+        Box::new(writer)
+    }
+}
+
+pub fn open_file(path: &str) -> Box<dyn BufRead> {
+    //! Helper function to open files for buffered reading.
+    //! Gzip and bgzip compressed files are decompressed transparently.
+
+    let path = Path::new(path);
+    let loc = path.display(); // used in error messages
+
+    let file = match File::open(path) {
+        Err(e) => panic!("Failed to open {loc}: {e}"),
+        Ok(f) => f,
+    };
+
+    // peek at the first bytes, to determine whether the file is compressed
+    let mut reader = BufReader::new(file);
+    let is_gzipped = match reader.fill_buf() {
         Err(e) => panic!("Failed to read {loc}: {e}"),
-        Ok(_) => {}
+        Ok(buf) => buf.starts_with(&GZIP_MAGIC),
+    };
+
+    if is_gzipped {
+        Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+    } else {
+        Box::new(reader)
     }
 }
 
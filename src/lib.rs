@@ -6,6 +6,9 @@ use accord::calculator;
 use accord::data;
 use accord::settings;
 
+/// The command line application, which the `accord` binary runs.
+pub use accord::app::App;
+
 /// The internals of `accord-rs`. These are implemented in Rust.
 #[pymodule(name = "_internal")]
 mod py_accord {
@@ -18,18 +21,64 @@ mod py_accord {
     #[pymodule(name = "data")]
     mod py_data {
         use super::*;
+        use data::accounting;
+        use data::annotation;
         use data::consensus;
+        use data::diversity;
+        use data::mutation;
+        use data::qc;
+        use data::region;
+        use data::sample;
         use data::seq;
         use data::stats;
+        use data::variant;
 
+        #[pymodule_export]
+        use accounting::ReadAccounting;
+        #[pymodule_export]
+        use annotation::Cds;
         #[pymodule_export]
         use consensus::AnalysisResult;
         #[pymodule_export]
         use consensus::Consensus;
         #[pymodule_export]
+        use diversity::DiversityTrack;
+        #[pymodule_export]
+        use diversity::DiversityWindow;
+        #[pymodule_export]
+        use mutation::AaMutation;
+        #[pymodule_export]
+        use mutation::MutationKind;
+        #[pymodule_export]
+        use qc::QcStats;
+        #[pymodule_export]
+        use qc::QcVerdict;
+        #[pymodule_export]
+        use sample::Sample;
+        #[pymodule_export]
         use seq::Seq;
         #[pymodule_export]
         use settings::AlnQualityReqs;
+        #[pymodule_export]
+        use settings::AnalysisSettings;
+        #[pymodule_export]
+        use settings::MateOverlap;
+        #[pymodule_export]
+        use settings::OutsideRegions;
+        #[pymodule_export]
+        use settings::Preset;
+        #[pymodule_export]
+        use settings::QcRules;
+        #[pymodule_export]
+        use region::Region;
+        #[pymodule_export]
+        use region::RegionStats;
+        #[pymodule_export]
+        use variant::MinorVariant;
+        #[pymodule_export]
+        use variant::Substitution;
+        #[pymodule_export]
+        use variant::VariantKind;
 
         /// Classes for working with InDels.
         #[pymodule(name = "indel")]
@@ -39,6 +88,8 @@ mod py_accord {
             #[pymodule_export]
             use data::indel::Deletion;
             #[pymodule_export]
+            use data::indel::Frameshift;
+            #[pymodule_export]
             use data::indel::InDel;
             #[pymodule_export]
             use data::indel::Insertion;
@@ -54,6 +105,8 @@ mod py_accord {
             #[pymodule_export]
             use stats::DistStats;
             #[pymodule_export]
+            use stats::FamilyStats;
+            #[pymodule_export]
             use stats::Quantile;
         }
     }